@@ -0,0 +1,27 @@
+use criterion::{Criterion, criterion_group, criterion_main};
+use image::{GenericImage, Rgb, RgbImage};
+
+use photo_date_bench::image_ops::blit_rgb;
+
+/// A 6x4 inch print at 300 dpi with the photo fitted inside a white border.
+fn images() -> (RgbImage, RgbImage) {
+    let canvas = RgbImage::from_pixel(1800, 1200, Rgb([255, 255, 255]));
+    let photo = RgbImage::from_fn(1700, 1100, |x, y| Rgb([x as u8, y as u8, (x ^ y) as u8]));
+    (canvas, photo)
+}
+
+fn compose(c: &mut Criterion) {
+    let (mut canvas, photo) = images();
+    let mut group = c.benchmark_group("compose");
+    // The path before blit_rgb, a get and put per pixel
+    group.bench_function("copy_from", |b| {
+        b.iter(|| canvas.copy_from(&photo, 50, 50).unwrap())
+    });
+    group.bench_function("blit_rgb", |b| {
+        b.iter(|| blit_rgb(&mut canvas, &photo, 50, 50).unwrap())
+    });
+    group.finish();
+}
+
+criterion_group!(benches, compose);
+criterion_main!(benches);
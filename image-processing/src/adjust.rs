@@ -0,0 +1,147 @@
+use std::path::PathBuf;
+use std::str::FromStr;
+
+use image::{imageops, RgbImage};
+use serde::{Deserialize, Serialize};
+
+use crate::error::AppError;
+
+/// Corrections applied to every photo right after it is decoded.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Adjustments {
+    /// Added to every channel, from -255 to 255
+    pub brightness: i32,
+    /// Percentage to change the contrast by, negative values lower it
+    pub contrast: f32,
+    /// 0 is grayscale, 1 keeps the colors, above 1 makes them stronger
+    pub saturation: f32,
+    /// Above 1 lightens the mid tones, below 1 darkens them
+    pub gamma: f32,
+}
+
+impl Default for Adjustments {
+    fn default() -> Self {
+        Self {
+            brightness: 0,
+            contrast: 0.0,
+            saturation: 1.0,
+            gamma: 1.0,
+        }
+    }
+}
+
+impl Adjustments {
+    pub fn validate(&self) -> Result<(), AppError> {
+        if !(-255..=255).contains(&self.brightness) {
+            return Err(AppError::Adjustment(format!(
+                "brightness {} must be from -255 to 255",
+                self.brightness
+            )));
+        }
+        if self.saturation < 0.0 {
+            return Err(AppError::Adjustment(format!(
+                "saturation {} can not be negative",
+                self.saturation
+            )));
+        }
+        if self.gamma <= 0.0 {
+            return Err(AppError::Adjustment(format!(
+                "gamma {} must be more than 0",
+                self.gamma
+            )));
+        }
+        Ok(())
+    }
+
+    pub fn apply(&self, img: &mut RgbImage) {
+        if *self == Self::default() {
+            return;
+        }
+        if self.brightness != 0 {
+            imageops::colorops::brighten_in_place(img, self.brightness);
+        }
+        if self.contrast != 0.0 {
+            imageops::colorops::contrast_in_place(img, self.contrast);
+        }
+        if self.saturation != 1.0 {
+            for pixel in img.pixels_mut() {
+                let [r, g, b] = pixel.0.map(f32::from);
+                // Rec. 601 luma
+                let luma = 0.299 * r + 0.587 * g + 0.114 * b;
+                pixel.0 = [r, g, b].map(|c| {
+                    (luma + (c - luma) * self.saturation)
+                        .round()
+                        .clamp(0.0, 255.0) as u8
+                });
+            }
+        }
+        if self.gamma != 1.0 {
+            let table: Vec<u8> = (0..=255)
+                .map(|v| (255.0 * (v as f32 / 255.0).powf(1.0 / self.gamma)).round() as u8)
+                .collect();
+            for channel in img.iter_mut() {
+                *channel = table[*channel as usize];
+            }
+        }
+    }
+}
+
+/// Turns a sideways photo upright, clockwise in quarter turns.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Rotation {
+    Cw90,
+    Cw180,
+    Cw270,
+}
+
+impl Rotation {
+    /// The rotation by `degrees` clockwise, negative for counterclockwise. `None` for a
+    /// full turn.
+    pub fn from_degrees(degrees: i32) -> Result<Option<Self>, String> {
+        match degrees.rem_euclid(360) {
+            0 => Ok(None),
+            90 => Ok(Some(Rotation::Cw90)),
+            180 => Ok(Some(Rotation::Cw180)),
+            270 => Ok(Some(Rotation::Cw270)),
+            _ => Err(format!(
+                "{degrees} is not a quarter turn like 90, 180 or -90"
+            )),
+        }
+    }
+
+    pub fn apply(&self, img: &RgbImage) -> RgbImage {
+        match self {
+            Rotation::Cw90 => imageops::rotate90(img),
+            Rotation::Cw180 => imageops::rotate180(img),
+            Rotation::Cw270 => imageops::rotate270(img),
+        }
+    }
+}
+
+/// A rotation for one original, e.g. `IMG_0001.jpg=90`, fixed in the app or on the command
+/// line without editing the original.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RotationOverride {
+    pub path: PathBuf,
+    pub degrees: i32,
+}
+
+impl FromStr for RotationOverride {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (path, degrees) = s
+            .rsplit_once('=')
+            .ok_or_else(|| format!("{s} is not a file and a rotation like IMG_0001.jpg=90"))?;
+        let degrees = degrees
+            .trim()
+            .parse()
+            .map_err(|_| format!("{degrees} is not a number of degrees"))?;
+        Rotation::from_degrees(degrees)?;
+        Ok(Self {
+            path: PathBuf::from(path),
+            degrees,
+        })
+    }
+}
@@ -0,0 +1,175 @@
+use std::collections::HashSet;
+use std::fmt;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use serde::{Deserialize, Serialize};
+
+use crate::config::Config;
+use crate::error::AppError;
+use crate::ledger::{self, RunTotals};
+use crate::{manifest, resume};
+
+/// Bytes of jpeg per pixel of the print, for a guess of the output before there are earlier runs.
+const OUTPUT_BYTES_PER_PIXEL: f64 = 0.35;
+
+/// Limits above which a run has to be confirmed before it starts, so a wrong source folder does
+/// not keep the computer busy for hours or fill the disk.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Budget {
+    pub max_photos: Option<usize>,
+    pub max_input_mb: Option<u64>,
+    pub max_output_mb: Option<u64>,
+    pub max_minutes: Option<f64>,
+    /// Seconds one cpu takes per MB of originals, until the ledger of the target has runs to go by
+    pub seconds_per_mb: f64,
+}
+
+impl Default for Budget {
+    fn default() -> Self {
+        Self {
+            max_photos: Some(5000),
+            max_input_mb: None,
+            max_output_mb: Some(10 * 1024),
+            max_minutes: Some(60.0),
+            seconds_per_mb: 0.3,
+        }
+    }
+}
+
+/// What a run is expected to take.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Estimate {
+    pub photos: usize,
+    pub input_bytes: u64,
+    pub output_bytes: u64,
+    pub seconds: f64,
+    /// Whether the estimate comes from the earlier runs in the ledger of the target
+    pub from_ledger: bool,
+}
+
+impl Estimate {
+    /// Estimates processing `images` into `target` with `cpus` workers. The output size and
+    /// time come from the earlier runs in the ledger of the target when it has them, otherwise
+    /// from the print size and `seconds_per_mb` of the config.
+    pub fn new(
+        images: &[PathBuf],
+        target: &Path,
+        config: &Config,
+        cpus: usize,
+    ) -> Result<Self, AppError> {
+        let input_bytes: u64 = images
+            .iter()
+            .filter_map(|path| fs::metadata(path).ok())
+            .map(|m| m.len())
+            .sum();
+        let history = ledger::run_history(target)?.filter(|h| h.input_bytes > 0);
+        let (output_bytes, cpu_seconds) = match history {
+            Some(RunTotals {
+                input_bytes: past_input,
+                output_bytes: past_output,
+                seconds,
+                ..
+            }) => {
+                let per_byte = input_bytes as f64 / past_input as f64;
+                (past_output as f64 * per_byte, seconds * per_byte)
+            }
+            None => {
+                let output = match config.resize {
+                    true => {
+                        let pixels =
+                            config.target_width_px() as f64 * config.target_height_px() as f64;
+                        pixels * OUTPUT_BYTES_PER_PIXEL * images.len() as f64
+                    }
+                    false => input_bytes as f64,
+                };
+                let mb = input_bytes as f64 / (1024. * 1024.);
+                (output, mb * config.budget.seconds_per_mb)
+            }
+        };
+        Ok(Self {
+            photos: images.len(),
+            input_bytes,
+            output_bytes: output_bytes as u64,
+            seconds: cpu_seconds / cpus.max(1) as f64,
+            from_ledger: history.is_some(),
+        })
+    }
+
+    /// The limits of `budget` the run is over, empty when it is within all of them.
+    pub fn over_budget(&self, budget: &Budget) -> Vec<String> {
+        let mut over = vec![];
+        if let Some(max) = budget.max_photos
+            && self.photos > max
+        {
+            over.push(format!("more than {max} photos"));
+        }
+        if let Some(max) = budget.max_input_mb
+            && self.input_bytes > max * 1024 * 1024
+        {
+            over.push(format!("more than {max} MB of originals"));
+        }
+        if let Some(max) = budget.max_output_mb
+            && self.output_bytes > max * 1024 * 1024
+        {
+            over.push(format!("more than {max} MB of output"));
+        }
+        if let Some(max) = budget.max_minutes
+            && self.seconds > max * 60.0
+        {
+            over.push(format!("longer than {max} minutes"));
+        }
+        over
+    }
+}
+
+/// Estimates a run over `sources` into `target` before it starts, e.g. to ask the user first.
+/// Like the run it leaves out the photos in the ledger and the ones the interrupted run it
+/// continues finished.
+pub fn estimate_run(
+    sources: &[PathBuf],
+    target: &Path,
+    config: &Config,
+    cpus: usize,
+) -> Result<Estimate, AppError> {
+    let template = config.name_template()?;
+    let mut processed = HashSet::new();
+    if config.ledger {
+        processed.extend(
+            ledger::read_processed(target)?
+                .into_iter()
+                .map(|(source, _)| source),
+        );
+    }
+    if config.resume
+        && let Some(interrupted) = resume::interrupted(target, sources)?
+    {
+        let recorded = manifest::read_manifest(target)?;
+        processed.extend(crate::finished_by(&recorded, &interrupted.run.run_id));
+    }
+    let mut images = vec![];
+    for source in sources {
+        let found = crate::walk_source(source, &template).0;
+        images.extend(found.into_iter().filter(|path| !processed.contains(path)));
+    }
+    Estimate::new(&images, target, config, cpus)
+}
+
+impl fmt::Display for Estimate {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mb = |bytes: u64| bytes as f64 / (1024. * 1024.);
+        write!(
+            f,
+            "{} photo(s), {:.1} MB of originals, about {:.1} MB of output in about {:.1} minutes",
+            self.photos,
+            mb(self.input_bytes),
+            mb(self.output_bytes),
+            self.seconds / 60.0
+        )?;
+        match self.from_ledger {
+            true => write!(f, " (from the earlier runs)"),
+            false => write!(f, " (a first guess, runs with the ledger on improve it)"),
+        }
+    }
+}
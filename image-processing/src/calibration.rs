@@ -0,0 +1,173 @@
+use std::fs;
+use std::path::Path;
+
+use ab_glyph::{FontArc, PxScale};
+use image::{Rgb, RgbImage};
+use imageproc::drawing::{draw_filled_rect_mut, draw_hollow_rect_mut, draw_text_mut};
+use imageproc::rect::Rect;
+use jiff::civil::DateTime;
+
+use crate::config::{Color, Config};
+use crate::draw_text::pt_to_px;
+use crate::error::AppError;
+use crate::{fonts, sheet};
+
+const BLACK: Rgb<u8> = Rgb([0, 0, 0]);
+const GRAY: Rgb<u8> = Rgb([128, 128, 128]);
+/// Size of the cm numbers on the rulers
+const RULER_PT: usize = 5;
+/// Width of the rulers along the top and left edges
+const RULER_MM: f32 = 4.0;
+/// Size of a color swatch
+const SWATCH_MM: f32 = 5.0;
+/// Colors every printer should tell apart, after the colors of the stamps
+const SWATCHES: [[u8; 3]; 10] = [
+    [255, 0, 0],
+    [0, 255, 0],
+    [0, 0, 255],
+    [0, 255, 255],
+    [255, 0, 255],
+    [255, 255, 0],
+    [0, 0, 0],
+    [64, 64, 64],
+    [128, 128, 128],
+    [192, 192, 192],
+];
+
+/// Renders a test page the size of a print at the configured dpi: rulers in cm along the
+/// edges, the date and label stamps in their fonts and sizes and color swatches. Printed once,
+/// it shows whether the printer keeps the true physical sizes before a big job.
+pub fn render_calibration(config: &Config, out: &Path) -> Result<(), AppError> {
+    let (width, height) = (config.target_width_px(), config.target_height_px());
+    let mut page = RgbImage::from_pixel(width, height, config.background.rgb());
+    let px = |mm: f32| (mm / 25.4) * config.dpi;
+    let ruler_font = fonts::load_font(&config.label.font)?;
+
+    // The edges of the print and the safe area the stamps are kept in
+    draw_hollow_rect_mut(&mut page, Rect::at(0, 0).of_size(width, height), BLACK);
+    let safe = config.safe_area_px();
+    if safe > 0 && width > 2 * safe && height > 2 * safe {
+        let safe_area =
+            Rect::at(safe as i32, safe as i32).of_size(width - 2 * safe, height - 2 * safe);
+        draw_hollow_rect_mut(&mut page, safe_area, GRAY);
+    }
+
+    // Rulers with a tick every mm, longer ones every 5 mm and the cm numbered
+    let ruler_scale = PxScale::from(pt_to_px(RULER_PT, config.dpi));
+    let width_mm = config.width_cm * 10.0;
+    let height_mm = config.height_cm * 10.0;
+    for mm in 0..=width_mm as u32 {
+        let x = px(mm as f32).round() as i32;
+        let tick = px(tick_mm(mm)).round() as u32;
+        draw_filled_rect_mut(&mut page, Rect::at(x, 0).of_size(1, tick.max(1)), BLACK);
+        if mm % 10 == 0 && mm > 0 {
+            let label = (mm / 10).to_string();
+            draw_text_mut(
+                &mut page,
+                BLACK,
+                x + 2,
+                tick as i32,
+                ruler_scale,
+                &ruler_font,
+                &label,
+            );
+        }
+    }
+    for mm in 0..=height_mm as u32 {
+        let y = px(mm as f32).round() as i32;
+        let tick = px(tick_mm(mm)).round() as u32;
+        draw_filled_rect_mut(&mut page, Rect::at(0, y).of_size(tick.max(1), 1), BLACK);
+        if mm % 10 == 0 && mm > 0 {
+            let label = (mm / 10).to_string();
+            draw_text_mut(
+                &mut page,
+                BLACK,
+                tick as i32 + 2,
+                y + 2,
+                ruler_scale,
+                &ruler_font,
+                &label,
+            );
+        }
+    }
+
+    // The stamps in their fonts, sizes and colors
+    let left = px(RULER_MM * 2.0).round() as i32;
+    let mut lines = Lines {
+        page: &mut page,
+        x: left,
+        y: px(RULER_MM * 2.0),
+        dpi: config.dpi,
+    };
+    let date_font = fonts::load_font(config.date_font())?;
+    let sample_date = DateTime::constant(2025, 12, 24, 14, 30, 0, 0);
+    for (text, pt) in config.date_lines(&sample_date) {
+        lines.draw(&date_font, pt, config.date.color, &text);
+    }
+    let label_font = fonts::load_font(&config.label.font)?;
+    let label = config.label_template().replace("{number}", "1");
+    lines.draw(
+        &label_font,
+        config.label.size_pt,
+        config.label.color,
+        &label,
+    );
+    let size = format!(
+        "{} x {} cm, {} dpi, {width} x {height} px",
+        config.width_cm, config.height_cm, config.dpi
+    );
+    lines.draw(&ruler_font, RULER_PT, Color([0, 0, 0, 255]), &size);
+    let y = lines.y;
+
+    // Color swatches in a row, starting with the colors of the stamps
+    let swatch = px(SWATCH_MM).round() as u32;
+    let colors = [config.date.color.rgb(), config.label.color.rgb()]
+        .into_iter()
+        .chain(SWATCHES.map(Rgb));
+    let top = (y + px(1.0)).round() as i32;
+    for (i, color) in colors.enumerate() {
+        let x = left + (i as u32 * swatch) as i32;
+        if x as u32 + swatch > width || top as u32 + swatch > height {
+            break;
+        }
+        draw_filled_rect_mut(&mut page, Rect::at(x, top).of_size(swatch, swatch), color);
+        draw_hollow_rect_mut(&mut page, Rect::at(x, top).of_size(swatch, swatch), GRAY);
+    }
+
+    fs::write(out, sheet::encode_jpeg(&page, config)?)?;
+    Ok(())
+}
+
+/// Length of the ruler tick at `mm`.
+fn tick_mm(mm: u32) -> f32 {
+    match (mm % 10, mm % 5) {
+        (0, _) => RULER_MM,
+        (_, 0) => RULER_MM * 0.66,
+        _ => RULER_MM * 0.33,
+    }
+}
+
+/// Lines of text drawn one under the other.
+struct Lines<'a> {
+    page: &'a mut RgbImage,
+    x: i32,
+    y: f32,
+    dpi: f32,
+}
+
+impl Lines<'_> {
+    fn draw(&mut self, font: &FontArc, pt: usize, color: Color, text: &str) {
+        let px = pt_to_px(pt, self.dpi);
+        let scale = PxScale::from(px);
+        draw_text_mut(
+            self.page,
+            color.rgb(),
+            self.x,
+            self.y.round() as i32,
+            scale,
+            font,
+            text,
+        );
+        self.y += px * 1.2;
+    }
+}
@@ -0,0 +1,170 @@
+use std::collections::HashMap;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+use tracing::debug;
+
+use crate::error::AppError;
+
+/// Name of the file in a source folder with the captions of its photos.
+pub const CAPTIONS_FILE_NAME: &str = "captions.csv";
+
+/// Extra label lines for single photos, from the `captions.csv` files of the sources. Every row
+/// is the path of a photo relative to the source folder, e.g. `IMG_0001.jpg` or
+/// `2019/IMG_0001.jpg`, and then its caption. Further columns and line breaks in a quoted
+/// caption are further lines. A first row of `filename,caption` is taken as a header and
+/// rows starting with `#` as comments.
+#[derive(Debug, Default)]
+pub struct Captions {
+    by_path: HashMap<PathBuf, Vec<String>>,
+}
+
+impl Captions {
+    /// Reads the captions files of the `sources` that have one.
+    pub fn load(sources: &[PathBuf]) -> Result<Self, AppError> {
+        let mut captions = Self::default();
+        for source in sources.iter().filter(|source| source.is_dir()) {
+            let file = source.join(CAPTIONS_FILE_NAME);
+            let text = match fs::read_to_string(&file) {
+                Ok(text) => text,
+                Err(e) if e.kind() == io::ErrorKind::NotFound => continue,
+                Err(e) => return Err(e.into()),
+            };
+            let rows = parse_csv(&text).map_err(|e| AppError::Captions(file.clone(), e))?;
+            let mut count = 0;
+            for (i, row) in rows.into_iter().enumerate() {
+                let Some((name, lines)) = row.split_first() else {
+                    continue;
+                };
+                let name = name.trim();
+                if name.is_empty()
+                    || name.starts_with('#')
+                    || (i == 0 && name.eq_ignore_ascii_case("filename"))
+                {
+                    continue;
+                }
+                let lines: Vec<String> = lines
+                    .iter()
+                    .flat_map(|caption| caption.lines())
+                    .map(str::trim)
+                    .filter(|line| !line.is_empty())
+                    .map(ToString::to_string)
+                    .collect();
+                captions.by_path.insert(source.join(name), lines);
+                count += 1;
+            }
+            debug!("Read {count} caption(s) from {file:?}");
+        }
+        Ok(captions)
+    }
+
+    /// The caption lines of the photo at `path`, empty when it has none.
+    pub fn get(&self, path: &Path) -> &[String] {
+        self.by_path.get(path).map_or(&[], Vec::as_slice)
+    }
+}
+
+/// Splits `text` into rows of fields. Fields can be quoted with `"` to hold commas and line
+/// breaks, with `""` for a quote, as spreadsheets save them.
+fn parse_csv(text: &str) -> Result<Vec<Vec<String>>, String> {
+    let text = text.strip_prefix('\u{feff}').unwrap_or(text);
+    let mut rows = vec![];
+    let mut row = vec![];
+    let mut field = String::new();
+    let mut quoted = false;
+    let mut line = 1;
+    let mut quote_line = 0;
+    let mut chars = text.chars().peekable();
+    while let Some(c) = chars.next() {
+        match (quoted, c) {
+            (true, '"') if chars.peek() == Some(&'"') => {
+                chars.next();
+                field.push('"');
+            }
+            (true, '"') => quoted = false,
+            (true, c) => {
+                if c == '\n' {
+                    line += 1;
+                }
+                field.push(c);
+            }
+            (false, '"') if field.trim().is_empty() => {
+                field.clear();
+                quoted = true;
+                quote_line = line;
+            }
+            (false, ',') => row.push(std::mem::take(&mut field)),
+            (false, '\n') => {
+                row.push(std::mem::take(&mut field));
+                rows.push(std::mem::take(&mut row));
+                line += 1;
+            }
+            (false, '\r') => {}
+            (false, c) => field.push(c),
+        }
+    }
+    if quoted {
+        return Err(format!(
+            "the quote opened on line {quote_line} is not closed"
+        ));
+    }
+    if !field.is_empty() || !row.is_empty() {
+        row.push(field);
+        rows.push(row);
+    }
+    Ok(rows)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::temp_dir;
+
+    #[test]
+    fn quoted_fields_hold_commas_quotes_and_line_breaks() {
+        let rows =
+            parse_csv("a.jpg,\"Cape Town, harbour\"\r\nb.jpg,\"The \"\"Bench\"\"\nSecond line\"\n")
+                .unwrap();
+        assert_eq!(
+            rows,
+            [
+                vec!["a.jpg", "Cape Town, harbour"],
+                vec!["b.jpg", "The \"Bench\"\nSecond line"],
+            ]
+        );
+        assert_eq!(parse_csv("\u{feff}a.jpg,x").unwrap(), [vec!["a.jpg", "x"]]);
+    }
+
+    #[test]
+    fn an_unclosed_quote_names_its_line() {
+        let error = parse_csv("a.jpg,ok\nb.jpg,\"open\nc.jpg,x\n").unwrap_err();
+        assert_eq!(error, "the quote opened on line 2 is not closed");
+    }
+
+    #[test]
+    fn captions_skip_the_header_and_comments() {
+        let dir = temp_dir("captions");
+        let source = dir.path();
+        fs::write(
+            source.join(CAPTIONS_FILE_NAME),
+            "\u{feff}filename,caption\n\
+             # Taken by the second bench\n\
+             IMG_0001.jpg,Harbour,\"Boat\nDock\"\n\
+             2019/IMG_0002.jpg,  Table Mountain  \n",
+        )
+        .unwrap();
+
+        let captions = Captions::load(&[source.to_path_buf()]).unwrap();
+        assert_eq!(
+            captions.get(&source.join("IMG_0001.jpg")),
+            ["Harbour", "Boat", "Dock"]
+        );
+        assert_eq!(
+            captions.get(&source.join("2019/IMG_0002.jpg")),
+            ["Table Mountain"]
+        );
+        assert!(captions.get(&source.join("filename")).is_empty());
+        assert!(captions.get(&source.join("IMG_0003.jpg")).is_empty());
+    }
+}
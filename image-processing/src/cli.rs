@@ -0,0 +1,283 @@
+use std::path::PathBuf;
+
+use crate::completions::Shell;
+use crate::config::Config;
+use crate::error::AppError;
+use crate::locale::Locale;
+use crate::numbering::{CounterStyle, Numbering};
+use crate::sheet::SheetLayout;
+use crate::App;
+
+#[derive(Debug, clap::Parser)]
+#[clap(
+    about = "A command line tool to add dates to images and rescale them",
+    args_conflicts_with_subcommands = true,
+    arg_required_else_help = true,
+    allow_missing_positional = true
+)]
+pub struct Cli {
+    #[command(subcommand)]
+    pub command: Option<Command>,
+    #[command(flatten)]
+    pub app: Option<App>,
+}
+
+impl Cli {
+    /// The command and options for a crash report, without the webhook url and the logins of
+    /// remote targets.
+    pub fn crash_options(&self) -> String {
+        let app = self.app.as_ref().map(App::redacted);
+        format!("command: {:#?}\napp: {app:#?}", self.command)
+    }
+}
+
+/// The options a target was processed with that name its photos, so the commands reading the
+/// target parse the names the run wrote.
+#[derive(Debug, clap::Args)]
+pub struct ProcessedWith {
+    #[clap(
+        long,
+        help = "Path to the photo-bench.toml config the photos were processed with"
+    )]
+    pub config: Option<PathBuf>,
+    #[clap(long, help = "The preset the photos were processed with")]
+    pub preset: Option<String>,
+    #[clap(
+        long,
+        help = "The file name template the photos were processed with, when it was given on the command line"
+    )]
+    pub name_template: Option<String>,
+    #[clap(
+        long,
+        value_enum,
+        help = "The counter style the photos were processed with, when it was given on the command line"
+    )]
+    pub counter: Option<CounterStyle>,
+    #[clap(
+        long,
+        help = "The counter prefix the photos were processed with, when it was given on the command line"
+    )]
+    pub counter_prefix: Option<String>,
+    #[clap(
+        long,
+        value_enum,
+        help = "The numbering the photos were processed with, when it was given on the command line"
+    )]
+    pub numbering: Option<Numbering>,
+}
+
+impl ProcessedWith {
+    /// The config of the run that processed the photos, read like [`App::run_config`].
+    pub fn config(&self) -> Result<Config, AppError> {
+        App {
+            config: self.config.clone(),
+            preset: self.preset.clone(),
+            name_template: self.name_template.clone(),
+            counter: self.counter,
+            counter_prefix: self.counter_prefix.clone(),
+            numbering: self.numbering,
+            ..App::default()
+        }
+        .run_config()
+    }
+}
+
+#[derive(Debug, clap::Subcommand)]
+pub enum Command {
+    #[clap(about = "Summarize the processed photos in a target folder")]
+    Stats {
+        #[arg(help = "Path to the target directory containing the date folders")]
+        target: PathBuf,
+        #[command(flatten)]
+        processed_with: ProcessedWith,
+    },
+    #[clap(
+        about = "Check the integrity of the processed photos in a target folder and print a json report"
+    )]
+    Verify {
+        #[arg(help = "Path to the target directory containing the date folders")]
+        target: PathBuf,
+        #[command(flatten)]
+        processed_with: ProcessedWith,
+    },
+    #[clap(
+        about = "List the source images that are not in the target yet and the outputs whose originals are gone, as a json report"
+    )]
+    Diff {
+        #[arg(help = "Path to the directory containing the image files to be processed")]
+        source: PathBuf,
+        #[arg(help = "Path to the target directory containing the date folders")]
+        target: PathBuf,
+        #[command(flatten)]
+        processed_with: ProcessedWith,
+    },
+    #[clap(about = "Find identical and near identical images in a source folder")]
+    Dedupe {
+        #[arg(help = "Path to the directory conaining the image files to be processed")]
+        source: PathBuf,
+        #[clap(long, help = "Path to a photo-bench.toml config")]
+        config: Option<PathBuf>,
+        #[arg(
+            long,
+            help = "Move the duplicates into this directory so they are not processed"
+        )]
+        move_to: Option<PathBuf>,
+        #[arg(
+            long,
+            default_value_t = 4,
+            help = "How different two images may look and still be seen as duplicates. 0 only matches images that look exactly the same"
+        )]
+        max_distance: u32,
+    },
+    #[clap(about = "Compose the processed photos onto A4 pages with cut marks for printing")]
+    Sheets {
+        #[arg(help = "Path to the target directory containing the date folders")]
+        target: PathBuf,
+        #[command(flatten)]
+        processed_with: ProcessedWith,
+        #[arg(
+            long,
+            value_enum,
+            default_value_t = SheetLayout::Four,
+            help = "How many photos to put on a page"
+        )]
+        per_page: SheetLayout,
+        #[arg(
+            long,
+            default_value = "sheets",
+            help = "Directory to write the pages to"
+        )]
+        out: PathBuf,
+        #[arg(
+            long,
+            help = "Write one pdf with all the pages instead of a jpeg per page"
+        )]
+        pdf: bool,
+        #[arg(
+            long,
+            value_enum,
+            help = "Language of the page headers. Overrides the config"
+        )]
+        locale: Option<Locale>,
+    },
+    #[clap(
+        about = "Render a test page the size of a print with rulers in cm, the stamp fonts and color swatches, to check the printer keeps the true sizes"
+    )]
+    Calibration {
+        #[clap(long, help = "Path to a photo-bench.toml config")]
+        config: Option<PathBuf>,
+        #[arg(
+            long,
+            default_value = "calibration.jpg",
+            help = "Where to save the jpeg"
+        )]
+        out: PathBuf,
+    },
+    #[clap(
+        about = "Read the numbers and dates off photos stamped by the old tool and record them in the ledger of a target, so the numbering continues after them. The stamps are matched with the fonts of the config, or read with OCR with --ocr"
+    )]
+    ImportLegacy {
+        #[arg(help = "Path to the directory containing the stamped photos")]
+        legacy: PathBuf,
+        #[arg(help = "Path to the target directory the numbering continues in")]
+        target: PathBuf,
+        #[clap(
+            long,
+            help = "Path to the photo-bench.toml config with the fonts, colors and positions the photos were stamped with"
+        )]
+        config: Option<PathBuf>,
+        #[clap(
+            long,
+            help = "Read the stamps with Tesseract OCR, in any font, size and color. Only their positions and the label and date formats are taken from the config. Needs the ocr feature"
+        )]
+        ocr: bool,
+    },
+    #[clap(
+        about = "Add the program to the Send to menu of the Windows Explorer, so a folder can be sent to it and is processed into the dropped_target of the config"
+    )]
+    InstallSendTo,
+    #[cfg(feature = "self-update")]
+    #[clap(about = "Replace the program with the latest release from GitHub")]
+    SelfUpdate {
+        #[arg(long, help = "Only check if there is a newer release")]
+        check: bool,
+    },
+    #[clap(about = "Print the completion script for a shell")]
+    Completions {
+        #[arg(value_enum, help = "The shell to complete in")]
+        shell: Shell,
+    },
+    #[clap(about = "Print the man page with every option and command in roff")]
+    Manpage,
+    #[clap(about = "List the fonts that can be used for the stamps and preview them")]
+    Fonts {
+        #[command(subcommand)]
+        command: FontsCommand,
+    },
+    #[clap(about = "List the stamp presets shipped with the program, to use with --preset")]
+    Presets,
+    #[clap(about = "Manage the photo-bench.toml config")]
+    Config {
+        #[command(subcommand)]
+        command: ConfigCommand,
+    },
+}
+
+#[derive(Debug, clap::Subcommand)]
+pub enum ConfigCommand {
+    #[clap(about = "Write a config with all the default settings documented")]
+    Init {
+        #[arg(
+            long,
+            help = "Write the config to the OS config directory instead of the current directory"
+        )]
+        global: bool,
+        #[arg(long, help = "Overwrite an existing config")]
+        force: bool,
+    },
+}
+
+#[derive(Debug, clap::Subcommand)]
+pub enum FontsCommand {
+    #[clap(about = "List the bundled and installed fonts")]
+    List,
+    #[clap(about = "Render a sample of a font to a png")]
+    Preview {
+        #[arg(help = "Bundled font name, installed font name or path to a font file")]
+        family: String,
+        #[arg(help = "The text to render")]
+        text: String,
+        #[arg(long, default_value_t = 10, help = "Font size in points")]
+        size_pt: usize,
+        #[arg(long, default_value = "font-preview.png", help = "Where to save the png")]
+        out: PathBuf,
+    },
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use clap::Parser;
+    use std::path::Path;
+
+    #[test]
+    fn commands_reading_a_target_parse_the_names_of_the_command_line_of_the_run() {
+        let cli = Cli::parse_from([
+            "photo-date-bench",
+            "verify",
+            "target",
+            "--name-template",
+            "Foto {number}",
+            "--counter",
+            "alpha",
+            "--counter-prefix",
+            "S",
+        ]);
+        let Some(Command::Verify { processed_with, .. }) = cli.command else {
+            panic!("{:?}", cli.command);
+        };
+        let template = processed_with.config().unwrap().name_template().unwrap();
+        assert_eq!(template.number_of(Path::new("Foto SB.jpg")), Some(2));
+        assert_eq!(template.number_of(Path::new("20251224_1.jpg")), None);
+    }
+}
@@ -0,0 +1,50 @@
+use std::path::Path;
+
+use image::RgbImage;
+use lcms2::{ColorSpaceSignature, InfoType, Intent, Locale, PixelFormat, Profile, Transform};
+use tracing::debug;
+
+use crate::decode::DecodeBackend;
+use crate::error::AppError;
+
+/// Decodes an image to 8 bit RGB. When `to_srgb` is set, images with an embedded
+/// color profile, e.g. AdobeRGB or Display P3, are converted to sRGB so their
+/// colors do not shift in print.
+pub fn open_rgb8(path: &Path, to_srgb: bool, backend: DecodeBackend) -> Result<RgbImage, AppError> {
+    let (mut img, icc) = backend.decoder()?.decode(path, to_srgb)?;
+
+    let Some(icc) = icc else {
+        return Ok(img);
+    };
+    let profile = Profile::new_icc(&icc)?;
+    // Gray and CMYK photos were turned into RGB by the decoder, their profile does not fit it
+    if profile.color_space() != ColorSpaceSignature::RgbData {
+        debug!(
+            "Keeping the colors of {path:?}, its {:?} color profile is not for RGB",
+            profile.color_space()
+        );
+        return Ok(img);
+    }
+    let description = profile
+        .info(InfoType::Description, Locale::none())
+        .unwrap_or_default();
+    if description.contains("sRGB") {
+        return Ok(img);
+    }
+    debug!("Converting {path:?} from the {description:?} color profile to sRGB");
+    let transform = Transform::<[u8; 3], [u8; 3]>::new(
+        &profile,
+        PixelFormat::RGB_8,
+        &Profile::new_srgb(),
+        PixelFormat::RGB_8,
+        Intent::Perceptual,
+    )?;
+    let (pixels, _) = img.as_chunks_mut::<3>();
+    transform.transform_in_place(pixels);
+    Ok(img)
+}
+
+/// The sRGB color profile to embed in the outputs.
+pub fn srgb_icc() -> Result<Vec<u8>, AppError> {
+    Ok(Profile::new_srgb().icc()?)
+}
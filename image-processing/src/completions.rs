@@ -0,0 +1,22 @@
+use std::io::{self, Write};
+
+use clap::CommandFactory;
+use clap_complete::Generator;
+pub use clap_complete::Shell;
+
+use crate::cli::Cli;
+
+const BIN: &str = "photo-date-bench";
+
+/// Writes the completion script for `shell`.
+pub fn write_completions(shell: Shell, out: &mut impl Write) -> io::Result<()> {
+    // `clap_complete::generate` panics when the output is closed, e.g. piped into `head`
+    let mut cmd = Cli::command().bin_name(BIN);
+    cmd.build();
+    shell.try_generate(&cmd, out)
+}
+
+/// Writes a man page in roff with the options and commands.
+pub fn write_manpage(out: &mut impl Write) -> io::Result<()> {
+    clap_mangen::Man::new(Cli::command().name(BIN)).render(out)
+}
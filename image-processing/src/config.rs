@@ -0,0 +1,1313 @@
+use std::collections::HashSet;
+use std::fmt;
+use std::fs;
+use std::net::SocketAddr;
+use std::num::NonZeroUsize;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+use std::time::Duration;
+
+use image::{Rgb, Rgba};
+use jiff::civil::DateTime;
+use serde::{Deserialize, Serialize};
+use tracing::info;
+
+use crate::adjust::Adjustments;
+use crate::budget::Budget;
+use crate::date_source::{DateSource, DEFAULT_DATE_SOURCES};
+use crate::decode::DecodeBackend;
+use crate::draw_text::{pt_to_px, DrawPosition, StampOffset};
+use crate::error::AppError;
+use crate::fonts;
+use crate::grouping::GroupBy;
+use crate::label::{ChunkRules, LabelSource};
+use crate::locale::Locale;
+use crate::log_file::LogFile;
+use crate::metadata::{ExifSummary, GpsPosition};
+use crate::naming::{DirPrefix, NameTemplate};
+use crate::notify::Notify;
+use crate::numbering::{self, CounterFormat, CounterStyle, NumberRange, Numbering};
+use crate::quality::QualityThresholds;
+use crate::sink::{S3Settings, SftpSettings, WebDavSettings};
+use crate::skip::{CameraFilter, Resolution};
+use crate::transfer::Transfer;
+
+/// Name of the config file looked for in the current directory and the OS config directory.
+pub const CONFIG_FILE_NAME: &str = "photo-bench.toml";
+
+/// The bundled font the date is stamped in by default.
+const DEFAULT_DATE_FONT: &str = "arial-rounded-bold";
+/// The bundled font the date is stamped in by default for right-to-left locales, the default
+/// font has no Arabic or Hebrew letters and digits.
+const RTL_DATE_FONT: &str = "arial-bold";
+
+/// The default config with every setting documented. Written by `config init`.
+pub const DEFAULT_CONFIG_TOML: &str = r##"# Settings for photo-date-bench.
+#
+# The config is looked for in this order:
+#   1. The file passed with --config
+#   2. photo-bench.toml in the current directory
+#   3. photo-bench.toml in the OS config directory
+# Settings left out of the file keep the default values shown here.
+
+# Size of the printed photo
+width_cm = 8.0
+height_cm = 6.0
+# Resolution of the output. Together with the size this decides the output dimensions in pixels
+dpi = 300.0
+# Distance between the stamps and the edges of the photo
+margin_mm = 5.0
+# Distance the stamps are kept from the edges of the print, which printers clip. Stamps closer
+# to the edge, e.g. on photos filling the whole print, are moved inside
+safe_area_mm = 0.0
+# How much of every edge a full-bleed print cuts off. Stamps are kept this much further inside
+bleed_mm = 0.0
+# Jpeg quality of the output from 1 to 100
+jpeg_quality = 95
+# Convert photos with an embedded color profile, e.g. AdobeRGB or Display P3, to sRGB.
+# Without this their colors shift in print
+convert_to_srgb = true
+# Embed the sRGB color profile in the output photos
+embed_srgb_profile = false
+# Color of the canvas around photos that do not fill the whole size
+background = "#FFFFFF"
+# How the photo is fitted to the size of the print:
+#   letterbox  scaled to fit and centered on a canvas of the print size
+#   stretch    scaled to exactly the print size, not keeping the aspect ratio
+#   none       no canvas, scaled down to fit the longer side of the print and kept as it is
+fit_mode = "letterbox"
+# Scale the photos to the print size. With false the photos keep their own size and the
+# stamps are scaled to match, as if the photo was printed at the print size
+resize = true
+# Turn photos upright by their EXIF Orientation, as cameras store portrait photos sideways
+exif_orientation = true
+# Write the date of each photo into the EXIF data of its output, also when it came from the file
+# name, so photo managers sort the outputs by it
+exif_date = true
+# Where the date of a photo is taken from, tried in this order: exif-original, exif-digitized,
+# xmp (also the text chunks of PNGs), file-name, modified (when the file was last changed) and
+# ask, which skips and lists the photos none of the sources before it had a date for
+date_sources = ["exif-original", "xmp", "file-name"]
+# Where the stamps go:
+#   overlay        on the photo
+#   caption-strip  on a strip added under the photo, so no part of the photo is covered
+layout = "overlay"
+# Color of the caption strip
+caption_color = "#FFFFFF"
+# Scale all the stamps by this, their fonts, margins and offsets, e.g. 1.5 so small prints stay
+# readable for reviewers who see badly
+stamp_scale = 1.0
+# Language of the stamped text, e.g. the label and the month names: en, de, af, nl, fr, ar or he.
+# ar and he are stamped right-to-left and their stamps default to the opposite corners
+locale = "de"
+# global: one sequence of numbers over all the date folders
+# per-folder: every date folder starts at 0001
+numbering = "global"
+# Only use numbers in this range, e.g. "1000-1999", so several benches can write
+# into the same target without using the same numbers
+# number_range = "1000-1999"
+# Skip images smaller than this, like icons, screenshots and thumbnails in the source.
+# Portrait images are compared the other way around
+# min_resolution = "800x600"
+# Only process images taken with matching cameras, from the EXIF make and model. * matches
+# anything and ? one character, ignoring case. Images without a camera are skipped
+# camera_filter = "Canon*"
+# How the numbers are written in the file names and on the label:
+#   numeric  1, 2, 3, ...
+#   alpha    A, B, ... Z, AA, AB, ...
+counter = "numeric"
+# Written in front of every number, e.g. "S" for S1, S2 or SA, SB
+counter_prefix = ""
+# The folders the output photos are grouped into in the target:
+# day (20251224), month (202512), year (2025), camera (the EXIF camera model) or none
+group_by = "day"
+# Split folders with more photos than this into parts, e.g. 20251224_part1, 20251224_part2
+# max_per_folder = 200
+# Split every folder into subfolders for the part of the day in the EXIF time of the photos:
+# morning (05:00-12:00), afternoon (12:00-17:00), evening (17:00-22:00) and night.
+# Photos without an EXIF time go into unknown-time
+split_day_night = false
+# Target for a folder given on its own, e.g. dropped on the program or sent to it from the
+# Send to menu. {folder} is the name of the folder, and relative paths are next to it
+dropped_target = "{folder} processed"
+# File name of the output photos, without the extension. Placeholders:
+#   {number}     the number of the photo with the counter style and prefix,
+#                {number:04} zero pads numeric counters to 4 digits
+#   {date}       the date of the photo as YYYYMMDD
+#   {orig_stem}  the file name of the original without the extension
+#   {dir}        the folder of the original directly under the source, e.g. SampleA
+#                for source/SampleA/x.jpg
+# Files in the source matching the template are skipped as already processed.
+# Defaults to "{number}", or "{number:04}" with per-folder numbering.
+# name_template = "{date}_{number:04}_{orig_stem}"
+# Add the folder of the original directly under the source, e.g. SampleA for
+# source/SampleA/x.jpg, to the output:
+#   off    not at all
+#   label  as a line of the label under the number
+#   name   in front of the file name, e.g. SampleA_12.jpg, unless the template has {dir}
+#   both   on the label and in the file name
+prefix_from_dir = "off"
+# Write a json file next to every output photo, e.g. 12.json for 12.jpg, with the original
+# file name, the date, a summary of the EXIF data, these settings and the hashes
+sidecar = false
+# Write a 000_header.jpg into every output folder with the date, the run, the operator and the
+# number of photos, a cover sheet that prints in front of the photos of the day
+header_sheet = false
+# The person doing the run, recorded in the manifest, written into the EXIF Artist of the
+# outputs and printed on the cover sheets
+# operator = "J. Smith"
+# A target like s3://bucket/prefix is written into a staging folder first, which keeps the
+# numbering going from run to run, and what a run wrote is uploaded when it is over.
+# Defaults to the remote-targets folder in the data directory
+# remote_staging_dir = "D:/photo-staging"
+# How often a failed upload is tried again before it is left for the next run
+upload_retries = 3
+# Write the number, the original file name and the run into the EXIF (ImageDescription and
+# DocumentName) and XMP data of the output photos, so other programs can read the number
+embed_metadata = false
+# How finished photos are moved from the cache directory into the target:
+#   copy      copy the bytes, works between any two drives
+#   rename    rename the file
+#   hardlink  hard link the file
+#   reflink   share the data blocks on filesystems that support it (Btrfs, XFS, APFS, ReFS)
+# rename, hardlink and reflink fall back to a copy when the target is on another drive
+transfer = "copy"
+# Record every run and processed file in photo-bench-ledger.sqlite in the target.
+# Sources already in the ledger are skipped in later runs
+ledger = false
+# Continue a run that was interrupted, by a crash or a power loss, when it is started again with
+# the same sources: the photos it finished are skipped and the numbering continues after the
+# ones recorded in the manifest
+resume = true
+# With --watch a new file in the source is processed once it has not changed for this many
+# seconds, so photos still being copied or uploaded are not read half written
+watch_settle_secs = 2.0
+# The library the originals are decoded with:
+#   image      the decoders of the image crate, for every supported format
+#   zune-jpeg  zune-jpeg straight to RGB, faster for big jpegs. Other formats use image
+decoder = "image"
+# Send the originals removed by --delete-originals, or moved to another drive by
+# --archive-originals and dedupe --move-to, to the recycle bin instead of deleting them
+trash = false
+# Serve the processed and failed counts, stage latencies and queue depth for Prometheus
+# on http://<address>/metrics while running
+# metrics_addr = "127.0.0.1:9184"
+# Read this many originals ahead into memory while the workers are busy, so the latency of a
+# network share overlaps with the processing. 0 turns it off
+prefetch = 0
+# The most memory in MB the originals read ahead may take up
+prefetch_budget_mb = 512
+# Originals smaller than this many KB are handed to the workers in batches of small_file_batch,
+# which is faster for sources with thousands of thumbnails. A batch of 1 turns it off
+small_file_kb = 256
+small_file_batch = 16
+# Write outputs up to this many MB straight from memory into the target, instead of through
+# a file in the cache directory. Bigger outputs and 0 still go through the cache and the transfer
+in_memory_output_mb = 0
+
+# Smooth the noise of high ISO photos before they are scaled down.
+# Higher values smooth more, around 10 to 40 works well
+# denoise = 20.0
+
+# Crop the uniform borders scanners leave around a photo before it is scaled down, so scans
+# fill the print like digital photos. The lines along an edge that are within this much of the
+# color of the edge, from 0 to 255, are cropped, but never more than a third of the photo
+# trim_borders = 24
+
+# Straighten scans that went through the feeder crooked, by up to 5 degrees either way.
+# The skew is found from the edges in the photo, before the borders are trimmed
+deskew = false
+
+# Move the date and label to another corner when they would cover a face. The faces are found
+# with a face detection model built into the program. Needs a build with the face-detection
+# feature
+avoid_faces = false
+
+# Stamp colors for the photos from a folder directly under the source, or from a camera
+# matched like camera_filter, so the photos of every station are told apart on a mixed sheet.
+# The first matching rule is used. date defaults to the color of the date stamp
+# [[stamp_colors]]
+# folder = "Station A"
+# label = "#2979FF"
+# [[stamp_colors]]
+# camera = "Canon*"
+# label = "#00C853"
+# date = "#00C853"
+
+# Extra sizes made from the same decode as the print, e.g. a web preview and a thumbnail.
+# Every output is written into its own target tree with the same folders and file names,
+# by default next to the target, e.g. mytarget_web
+# [[outputs]]
+# name = "web"
+# The longest side in pixels
+# max_px = 1024
+# Stamp the date and label, scaled to the size of the output
+# stamp = true
+# Defaults to jpeg_quality
+# jpeg_quality = 85
+# Write the output here instead of next to the target
+# dir = "C:/web-previews"
+
+# Corrections applied to every photo right after it is decoded
+[adjust]
+# Added to every channel, from -255 to 255
+brightness = 0
+# Percentage to change the contrast by, negative values lower it
+contrast = 0.0
+# 0 is grayscale, 1 keeps the colors, above 1 makes them stronger
+saturation = 1.0
+# Above 1 lightens the mid tones, below 1 darkens them
+gamma = 1.0
+
+# Photos that look blurry or badly exposed are reported at the end of the run
+[quality]
+check = true
+# Photos with a lower variance of the Laplacian are reported as blurry
+min_sharpness = 100.0
+# Photos with more of their pixels clipped to black or white are reported
+max_clipped_percent = 5.0
+
+# A log file for support to see what happened in a run, also written by the UI
+[log]
+enabled = false
+# When a new log is started:
+#   daily   a log per day
+#   hourly  a log per hour
+rotation = "daily"
+# How many logs are kept, the oldest are removed
+keep = 7
+# Defaults to the logs folder in the data directory
+# dir = "C:/photo-logs"
+
+[budget]
+# A run above any of these has to be confirmed with --yes, or in the UI, before it starts.
+# The output size and time are estimated from the earlier runs in the ledger of the target,
+# or from seconds_per_mb and the print size when it has none. Leave a limit out to not check it
+max_photos = 5000
+# max_input_mb = 20000
+max_output_mb = 10240
+max_minutes = 60.0
+# Seconds one cpu takes per MB of originals, until the ledger has runs to go by
+seconds_per_mb = 0.3
+
+# Where the summary of every run is sent when it finishes or fails, for unattended runs
+[notify]
+# Post the summary as json, e.g. to the incoming webhook of a team chat
+# webhook = "https://chat.example.com/hooks/abc123"
+# Mail the summary through an SMTP server. With a user the connection has to be encrypted
+# [notify.smtp]
+# url = "smtps://smtp.example.com:465"
+# user = "bench@example.com"
+# password = "secret"
+# from = "bench@example.com"
+# to = ["team@example.com"]
+
+# How s3:// targets are reached, in builds with the s3 feature
+[s3]
+region = "us-east-1"
+# Another S3 compatible service instead of AWS, e.g. MinIO
+# endpoint = "http://localhost:9000"
+# Default to the AWS_ACCESS_KEY_ID and AWS_SECRET_ACCESS_KEY environment variables
+# access_key = "..."
+# secret_key = "..."
+
+# How sftp://user@host/path targets log in. Without a key or password the ssh agent and the keys
+# in ~/.ssh are tried. The host has to be in ~/.ssh/known_hosts
+[sftp]
+# key = "C:/Users/bench/.ssh/id_ed25519"
+# passphrase = "..."
+# password = "..."
+
+# How dav://host/path (http) and davs://host/path (https) WebDAV targets log in, e.g. a
+# Nextcloud share at davs://cloud.example.com/remote.php/dav/files/bench/Photos
+[webdav]
+# user = "bench"
+# password = "app-password"
+
+# The date stamped onto the photo
+[date]
+# One of the bundled fonts: arial-rounded-bold, arial-bold, arial-regular,
+# the name of an installed font, e.g. "DejaVu Sans Bold", or a path to a .ttf file.
+# Run `photo-date-bench fonts list` to see the available fonts.
+# Defaults to arial-rounded-bold, or arial-bold for right-to-left locales, as
+# arial-rounded-bold has no Arabic or Hebrew letters and digits
+# font = "arial-rounded-bold"
+size_pt = 10
+# Colors are written as #RRGGBB or #RRGGBBAA
+color = "#FF8C00"
+# One of: top-left, top-right, bottom-left, bottom-right.
+# Defaults to bottom-right, or bottom-left for right-to-left locales
+# position = "bottom-right"
+# Moves the stamp from its corner towards the middle of the photo, in addition to margin_mm.
+# Negative values move it towards the edge. In mm, so it stays in place when the size or dpi changes
+offset_x_mm = 0.0
+offset_y_mm = 0.0
+# strftime format of the date, e.g. "%Y-%m-%d", "%d.%m.%Y" or "%a %d %m %Y" for "Di 24 12 2025".
+# Month names (%B and %b) and weekday names (%A and %a) are in the language of the locale
+format = "%d %m %Y"
+# Stamp the time the photo was taken:
+#   off     only the date
+#   inline  after the date on the same line, e.g. 24 12 2025 14:30
+#   line    on a smaller second line under the date
+time = "off"
+# strftime format of the time
+time_format = "%H:%M"
+# Size of the time with time = "line"
+time_size_pt = 7
+
+# The photo number and the parts of the file name stamped onto the photo
+[label]
+font = "arial-bold"
+size_pt = 8
+color = "#FFFF54"
+# Defaults to top-left, or top-right for right-to-left locales
+# position = "top-left"
+offset_x_mm = 0.0
+offset_y_mm = 0.0
+# First line of the label. {number} is replaced with the number of the photo.
+# Defaults to the locale, e.g. "Foto Nr.: {number}" for de
+# template = "Foto Nr.: {number}"
+# What is stamped under the first line:
+#   chunks         the parts of the file name split on the delimiters, filtered by keep and drop
+#   full-filename  the whole file name of the original
+#   none           nothing
+#   custom         the custom lines below
+source = "chunks"
+# Lines stamped with source = "custom". {number}, {file_name} and {orig_stem} are replaced
+custom = []
+# The file name is split into chunks on every one of these
+delimiters = ["_"]
+# Only stamp the chunks matching this regex. Empty keeps all of them
+keep = ""
+# Do not stamp the chunks matching this regex. Empty drops none of them
+drop = "^[0-9]"
+# Add the make and model of the camera from the EXIF data as a line of the label
+camera = false
+# Add the operator of the run as a line of the label
+operator = false
+# Stamp the first 8 hex characters of the SHA-256 of the original after the number, or where
+# the template has {checksum}. The manifest gets the whole checksum
+checksum = false
+# The most lines the label may have, including the first line
+# max_lines = 3
+
+# Where the photo was taken, from the GPS data of the camera, e.g. as proof of the location for
+# field documentation. Photos without GPS data get no coordinates
+[gps]
+enabled = false
+font = "arial-bold"
+size_pt = 7
+color = "#FFFFFF"
+# Defaults to bottom-left, or bottom-right for right-to-left locales. In the corner of the date
+# or the label it is stamped as a line under it, in its font and color. On the caption strip
+# it is stamped under the stamp on its side
+# position = "bottom-left"
+offset_x_mm = 0.0
+offset_y_mm = 0.0
+# How the coordinates are written:
+#   dms      degrees, minutes and seconds, e.g. 33°55'29.5"S 18°25'26.6"E
+#   decimal  degrees, e.g. -33.92487, 18.42406
+format = "dms"
+# What is stamped:
+#   coordinates  the coordinates
+#   place        the nearest city, e.g. Cape Town, South Africa, looked up offline
+#   both         the place over the coordinates
+text = "coordinates"
+# Where the places are looked up: a csv with name,country_code,country,latitude,longitude or a
+# GeoNames dump like cities15000.txt. Defaults to the capitals and big cities bundled with the
+# program
+# gazetteer = "C:/Users/bench/cities15000.txt"
+# Photos taken further than this from every place get no place
+place_max_km = 50.0
+
+# A large text across every photo and its extra outputs, from the bottom-left to the top-right
+# corner, so draft prints are not mistaken for the final ones
+[proof]
+enabled = false
+text = "PROOF"
+font = "arial-bold"
+# The last two digits are how much the text covers the photo, from 00 to FF
+color = "#FF000050"
+"##;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Config {
+    pub width_cm: f32,
+    pub height_cm: f32,
+    pub dpi: f32,
+    pub margin_mm: f32,
+    pub safe_area_mm: f32,
+    pub bleed_mm: f32,
+    pub jpeg_quality: u8,
+    pub convert_to_srgb: bool,
+    pub embed_srgb_profile: bool,
+    pub background: Color,
+    pub fit_mode: FitMode,
+    pub resize: bool,
+    pub exif_orientation: bool,
+    pub exif_date: bool,
+    pub date_sources: Vec<DateSource>,
+    pub layout: StampLayout,
+    pub caption_color: Color,
+    pub stamp_scale: f32,
+    pub locale: Locale,
+    pub numbering: Numbering,
+    pub number_range: Option<NumberRange>,
+    pub min_resolution: Option<Resolution>,
+    pub camera_filter: Option<CameraFilter>,
+    pub counter: CounterStyle,
+    pub counter_prefix: String,
+    pub group_by: GroupBy,
+    pub max_per_folder: Option<NonZeroUsize>,
+    pub split_day_night: bool,
+    pub dropped_target: String,
+    pub name_template: Option<String>,
+    pub prefix_from_dir: DirPrefix,
+    pub sidecar: bool,
+    pub header_sheet: bool,
+    pub operator: Option<String>,
+    pub embed_metadata: bool,
+    pub transfer: Transfer,
+    pub ledger: bool,
+    pub resume: bool,
+    pub watch_settle_secs: f32,
+    pub decoder: DecodeBackend,
+    pub trash: bool,
+    pub metrics_addr: Option<SocketAddr>,
+    pub prefetch: usize,
+    pub prefetch_budget_mb: usize,
+    pub small_file_kb: u64,
+    pub small_file_batch: usize,
+    pub in_memory_output_mb: usize,
+    pub stamp_colors: Vec<StampColorRule>,
+    pub outputs: Vec<OutputVariant>,
+    pub trim_borders: Option<u8>,
+    pub deskew: bool,
+    pub avoid_faces: bool,
+    pub denoise: Option<f32>,
+    pub adjust: Adjustments,
+    pub quality: QualityThresholds,
+    pub log: LogFile,
+    pub budget: Budget,
+    pub notify: Notify,
+    pub remote_staging_dir: Option<PathBuf>,
+    pub upload_retries: u32,
+    pub s3: S3Settings,
+    pub sftp: SftpSettings,
+    pub webdav: WebDavSettings,
+    pub date: DateStamp,
+    pub label: LabelStamp,
+    pub gps: GpsStamp,
+    pub proof: ProofStamp,
+}
+
+/// Stamp colors for the photos from a folder under the source or from a camera, so the
+/// photos of every station are told apart on a mixed sheet.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct StampColorRule {
+    /// The folder directly under the source, e.g. `Station A` for source/Station A/x.jpg
+    pub folder: Option<String>,
+    /// The camera, matched like the camera filter, e.g. `Canon*`
+    pub camera: Option<CameraFilter>,
+    pub label: Color,
+    /// Defaults to the color of the date stamp
+    pub date: Option<Color>,
+}
+
+/// The colors the label and the date of one photo are stamped in.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct StampColors {
+    pub label: Color,
+    pub date: Color,
+}
+
+/// An extra size of every photo, written into its own target tree.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct OutputVariant {
+    pub name: String,
+    /// The longest side in pixels
+    pub max_px: u32,
+    #[serde(default)]
+    pub stamp: bool,
+    pub jpeg_quality: Option<u8>,
+    pub dir: Option<PathBuf>,
+}
+
+impl OutputVariant {
+    /// The target tree of the output. Defaults to a folder next to the target, e.g. `mytarget_web`.
+    pub fn dir(&self, target: &Path) -> PathBuf {
+        if let Some(dir) = &self.dir {
+            return dir.clone();
+        }
+        match target.file_name().and_then(|x| x.to_str()) {
+            Some(name) => target.with_file_name(format!("{name}_{}", self.name)),
+            None => target.join(&self.name),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct DateStamp {
+    /// Defaults to the font for the direction of the locale, see [`Config::date_font`]
+    pub font: Option<String>,
+    pub size_pt: usize,
+    pub color: Color,
+    pub position: Option<DrawPosition>,
+    pub offset_x_mm: f32,
+    pub offset_y_mm: f32,
+    pub format: String,
+    pub time: StampTime,
+    pub time_format: String,
+    pub time_size_pt: usize,
+}
+
+/// How a photo is fitted to the size of the print.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize, clap::ValueEnum)]
+#[serde(rename_all = "kebab-case")]
+pub enum FitMode {
+    /// Scaled to fit and centered on a canvas of the print size
+    #[default]
+    Letterbox,
+    /// Scaled to exactly the print size, ignoring the aspect ratio
+    Stretch,
+    /// No canvas, only scaled down to fit the longer side of the print
+    None,
+}
+
+/// Where the stamps are drawn.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize, clap::ValueEnum)]
+#[serde(rename_all = "kebab-case")]
+pub enum StampLayout {
+    /// On the photo
+    #[default]
+    Overlay,
+    /// On a strip added under the photo
+    CaptionStrip,
+}
+
+/// Whether and where the time of day is stamped with the date.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize, clap::ValueEnum)]
+#[serde(rename_all = "kebab-case")]
+pub enum StampTime {
+    #[default]
+    Off,
+    /// After the date on the same line
+    Inline,
+    /// On a smaller second line under the date
+    Line,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct LabelStamp {
+    pub font: String,
+    pub size_pt: usize,
+    pub color: Color,
+    pub position: Option<DrawPosition>,
+    pub offset_x_mm: f32,
+    pub offset_y_mm: f32,
+    pub template: Option<String>,
+    pub source: LabelSource,
+    pub custom: Vec<String>,
+    pub delimiters: Vec<String>,
+    pub keep: String,
+    pub drop: String,
+    pub camera: bool,
+    pub operator: bool,
+    pub checksum: bool,
+    pub max_lines: Option<usize>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct GpsStamp {
+    pub enabled: bool,
+    pub font: String,
+    pub size_pt: usize,
+    pub color: Color,
+    pub position: Option<DrawPosition>,
+    pub offset_x_mm: f32,
+    pub offset_y_mm: f32,
+    pub format: GpsFormat,
+    pub text: GpsText,
+    pub gazetteer: Option<PathBuf>,
+    pub place_max_km: f32,
+}
+
+/// The text stamped across the photos of a draft run.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct ProofStamp {
+    pub enabled: bool,
+    pub text: String,
+    pub font: String,
+    pub color: Color,
+}
+
+/// How the GPS coordinates are stamped.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize, clap::ValueEnum)]
+#[serde(rename_all = "kebab-case")]
+pub enum GpsFormat {
+    /// Degrees, minutes and seconds, e.g. 33°55'29.5"S 18°25'26.6"E
+    #[default]
+    Dms,
+    /// Degrees, e.g. -33.92487, 18.42406
+    Decimal,
+}
+
+/// What is stamped of where a photo was taken.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize, clap::ValueEnum)]
+#[serde(rename_all = "kebab-case")]
+pub enum GpsText {
+    /// The coordinates
+    #[default]
+    Coordinates,
+    /// The nearest city, e.g. Cape Town, South Africa
+    Place,
+    /// The place over the coordinates
+    Both,
+}
+
+impl GpsText {
+    pub fn has_place(self) -> bool {
+        matches!(self, GpsText::Place | GpsText::Both)
+    }
+
+    pub fn has_coordinates(self) -> bool {
+        matches!(self, GpsText::Coordinates | GpsText::Both)
+    }
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self {
+            width_cm: 8.0,
+            height_cm: 6.0,
+            dpi: 300.0,
+            margin_mm: 5.0,
+            safe_area_mm: 0.0,
+            bleed_mm: 0.0,
+            jpeg_quality: 95,
+            convert_to_srgb: true,
+            embed_srgb_profile: false,
+            background: Color([255, 255, 255, 255]),
+            fit_mode: FitMode::Letterbox,
+            resize: true,
+            exif_orientation: true,
+            exif_date: true,
+            date_sources: DEFAULT_DATE_SOURCES.to_vec(),
+            layout: StampLayout::Overlay,
+            caption_color: Color([255, 255, 255, 255]),
+            stamp_scale: 1.0,
+            locale: Locale::default(),
+            numbering: Numbering::Global,
+            number_range: None,
+            min_resolution: None,
+            camera_filter: None,
+            counter: CounterStyle::Numeric,
+            counter_prefix: String::new(),
+            group_by: GroupBy::Day,
+            max_per_folder: None,
+            split_day_night: false,
+            dropped_target: "{folder} processed".to_string(),
+            name_template: None,
+            prefix_from_dir: DirPrefix::Off,
+            sidecar: false,
+            header_sheet: false,
+            operator: None,
+            embed_metadata: false,
+            transfer: Transfer::Copy,
+            ledger: false,
+            resume: true,
+            watch_settle_secs: 2.0,
+            decoder: DecodeBackend::Image,
+            trash: false,
+            metrics_addr: None,
+            prefetch: 0,
+            prefetch_budget_mb: 512,
+            small_file_kb: 256,
+            small_file_batch: 16,
+            in_memory_output_mb: 0,
+            stamp_colors: vec![],
+            outputs: vec![],
+            trim_borders: None,
+            deskew: false,
+            avoid_faces: false,
+            denoise: None,
+            adjust: Adjustments::default(),
+            quality: QualityThresholds::default(),
+            log: LogFile::default(),
+            budget: Budget::default(),
+            notify: Notify::default(),
+            remote_staging_dir: None,
+            upload_retries: 3,
+            s3: S3Settings::default(),
+            sftp: SftpSettings::default(),
+            webdav: WebDavSettings::default(),
+            date: DateStamp::default(),
+            label: LabelStamp::default(),
+            gps: GpsStamp::default(),
+            proof: ProofStamp::default(),
+        }
+    }
+}
+
+impl Default for DateStamp {
+    fn default() -> Self {
+        Self {
+            font: None,
+            size_pt: 10,
+            // orange
+            color: Color([255, 140, 0, 255]),
+            position: None,
+            offset_x_mm: 0.0,
+            offset_y_mm: 0.0,
+            format: "%d %m %Y".to_string(),
+            time: StampTime::Off,
+            time_format: "%H:%M".to_string(),
+            time_size_pt: 7,
+        }
+    }
+}
+
+impl Default for LabelStamp {
+    fn default() -> Self {
+        Self {
+            font: "arial-bold".to_string(),
+            size_pt: 8,
+            // yellow
+            color: Color([255, 255, 84, 255]),
+            position: None,
+            offset_x_mm: 0.0,
+            offset_y_mm: 0.0,
+            template: None,
+            source: LabelSource::Chunks,
+            custom: vec![],
+            delimiters: vec!["_".to_string()],
+            keep: String::new(),
+            drop: "^[0-9]".to_string(),
+            camera: false,
+            operator: false,
+            checksum: false,
+            max_lines: None,
+        }
+    }
+}
+
+impl Default for GpsStamp {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            font: "arial-bold".to_string(),
+            size_pt: 7,
+            // white
+            color: Color([255, 255, 255, 255]),
+            position: None,
+            offset_x_mm: 0.0,
+            offset_y_mm: 0.0,
+            format: GpsFormat::Dms,
+            text: GpsText::Coordinates,
+            gazetteer: None,
+            place_max_km: 50.0,
+        }
+    }
+}
+
+impl Default for ProofStamp {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            text: "PROOF".to_string(),
+            font: "arial-bold".to_string(),
+            // red, a third covering
+            color: Color([255, 0, 0, 80]),
+        }
+    }
+}
+
+impl Config {
+    /// Loads the config from `path`, or when it is not given from the first config file found
+    /// in the current directory or the OS config directory. Falls back to the defaults.
+    pub fn load(path: Option<&Path>) -> Result<Self, AppError> {
+        let path = match path {
+            Some(p) => Some(p.to_path_buf()),
+            None => default_config_locations().into_iter().find(|p| p.exists()),
+        };
+        let config = match path {
+            Some(path) => {
+                info!("Using config {path:?}");
+                let text = fs::read_to_string(&path)?;
+                toml::from_str(&text).map_err(|e| AppError::Config(path, e.to_string()))?
+            }
+            None => Config::default(),
+        };
+        config.validate()?;
+        Ok(config)
+    }
+
+    /// Checks the values that can only fail once the images are processed.
+    pub fn validate(&self) -> Result<(), AppError> {
+        self.check_date_font()?;
+        fonts::load_font(self.date_font())?;
+        fonts::load_font(&self.label.font)?;
+        if self.gps.enabled {
+            fonts::load_font(&self.gps.font)?;
+        }
+        if self.proof.enabled {
+            fonts::load_font(&self.proof.font)?;
+        }
+        check_date_format(&self.date.format)?;
+        check_date_format(&self.date.time_format)?;
+        check_stamp_scale(self.stamp_scale)?;
+        check_print_size("dpi", self.dpi)?;
+        check_print_size("width_cm", self.width_cm)?;
+        check_print_size("height_cm", self.height_cm)?;
+        if let Some(range) = &self.number_range {
+            numbering::check_range(self.counter, range)?;
+        }
+        if Duration::try_from_secs_f32(self.watch_settle_secs).is_err() {
+            return Err(AppError::WatchSettle(self.watch_settle_secs));
+        }
+        self.name_template()?;
+        ChunkRules::new(&self.label)?;
+        self.adjust.validate()?;
+        self.decoder.decoder()?;
+        if self.date_sources.is_empty() {
+            return Err(AppError::NoDateSources);
+        }
+        if self.avoid_faces && !cfg!(feature = "face-detection") {
+            return Err(AppError::FaceDetectionNotBuilt);
+        }
+        let mut names = HashSet::new();
+        for output in &self.outputs {
+            if output.max_px == 0 {
+                return Err(AppError::Output(
+                    output.name.clone(),
+                    "max_px must be more than 0".into(),
+                ));
+            }
+            if output.name.is_empty() || !names.insert(&output.name) {
+                return Err(AppError::Output(
+                    output.name.clone(),
+                    "every output needs its own name".into(),
+                ));
+            }
+        }
+        Ok(())
+    }
+
+    /// How the numbers are written in the file names and on the label.
+    pub fn counter_format(&self) -> CounterFormat {
+        CounterFormat {
+            style: self.counter,
+            prefix: self.counter_prefix.clone(),
+        }
+    }
+
+    /// The template output files are named with.
+    pub fn name_template(&self) -> Result<NameTemplate, AppError> {
+        let template = match (&self.name_template, self.numbering) {
+            (Some(template), _) => template,
+            (None, Numbering::Global) => "{number}",
+            (None, Numbering::PerFolder) => "{number:04}",
+        };
+        match self.prefix_from_dir.in_name() && !template.contains("{dir}") {
+            true => NameTemplate::new(&format!("{{dir}}_{template}"), self.counter_format()),
+            false => NameTemplate::new(template, self.counter_format()),
+        }
+    }
+
+    /// The first line of the label, from the config or else the locale.
+    pub fn label_template(&self) -> &str {
+        self.label
+            .template
+            .as_deref()
+            .unwrap_or(self.locale.photo_number())
+    }
+
+    /// Whether the stamp colors depend on the camera, so the EXIF data has to be read for them.
+    pub fn stamp_colors_by_camera(&self) -> bool {
+        self.stamp_colors.iter().any(|rule| rule.camera.is_some())
+    }
+
+    /// The stamp colors of the first rule matching the folder under the source and the camera,
+    /// or else the colors of the label and date stamps.
+    pub(crate) fn stamp_colors(
+        &self,
+        source_dir: &str,
+        camera: Option<&ExifSummary>,
+    ) -> StampColors {
+        let rule = self.stamp_colors.iter().find(|rule| {
+            let folder = rule
+                .folder
+                .as_ref()
+                .is_none_or(|folder| folder == source_dir);
+            let camera = rule
+                .camera
+                .as_ref()
+                .is_none_or(|filter| camera.is_some_and(|camera| filter.matches_camera(camera)));
+            folder && camera
+        });
+        match rule {
+            Some(rule) => StampColors {
+                label: rule.label,
+                date: rule.date.unwrap_or(self.date.color),
+            },
+            None => StampColors {
+                label: self.label.color,
+                date: self.date.color,
+            },
+        }
+    }
+
+    /// The font of the date, from the config or else the one for the direction of the locale.
+    pub fn date_font(&self) -> &str {
+        match (&self.date.font, self.locale.is_rtl()) {
+            (Some(font), _) => font,
+            (None, true) => RTL_DATE_FONT,
+            (None, false) => DEFAULT_DATE_FONT,
+        }
+    }
+
+    /// Checks that the date font has the letters and digits of the locale.
+    pub fn check_date_font(&self) -> Result<(), AppError> {
+        if self.locale.is_rtl() && self.date_font() == DEFAULT_DATE_FONT {
+            return Err(AppError::FontLacksRtl(DEFAULT_DATE_FONT.to_string()));
+        }
+        Ok(())
+    }
+
+    /// Where the date is stamped, from the config or else the corner for the direction of the locale.
+    pub fn date_position(&self) -> DrawPosition {
+        self.date
+            .position
+            .unwrap_or_else(|| self.for_direction(DrawPosition::BottomRight))
+    }
+
+    /// Where the label is stamped, from the config or else the corner for the direction of the locale.
+    pub fn label_position(&self) -> DrawPosition {
+        self.label
+            .position
+            .unwrap_or_else(|| self.for_direction(DrawPosition::TopLeft))
+    }
+
+    /// Where the GPS coordinates are stamped, from the config or else the corner for the
+    /// direction of the locale. On the caption strip they are stamped under the stamp on their
+    /// side, as the corners of a side are too close to hold two stamps.
+    pub fn gps_position(&self) -> DrawPosition {
+        let position = self
+            .gps
+            .position
+            .unwrap_or_else(|| self.for_direction(DrawPosition::BottomLeft));
+        match self.layout {
+            StampLayout::Overlay => position,
+            StampLayout::CaptionStrip => [self.label_position(), self.date_position()]
+                .into_iter()
+                .find(|stamp| stamp.is_left() == position.is_left())
+                .unwrap_or(position),
+        }
+    }
+
+    /// Mirrors a left-to-right position for right-to-left locales.
+    pub fn for_direction(&self, position: DrawPosition) -> DrawPosition {
+        match self.locale.is_rtl() {
+            true => position.mirrored(),
+            false => position,
+        }
+    }
+
+    /// The date as stamped onto the photos.
+    pub fn format_date(&self, date: &DateTime) -> String {
+        self.locale.strftime(date, &self.date.format)
+    }
+
+    /// The GPS coordinates as stamped onto the photos.
+    pub fn format_gps(&self, gps: &GpsPosition) -> String {
+        match self.gps.format {
+            GpsFormat::Decimal => format!("{:.5}, {:.5}", gps.latitude, gps.longitude),
+            GpsFormat::Dms => {
+                let dms = |degrees: f64, positive: char, negative: char| {
+                    // In tenths of a second, so 59.96 seconds are not written as 60.0
+                    let tenths = (degrees.abs() * 36_000.0).round() as u64;
+                    let hemisphere = match degrees < 0.0 {
+                        true => negative,
+                        false => positive,
+                    };
+                    format!(
+                        "{}°{:02}'{:04.1}\"{hemisphere}",
+                        tenths / 36_000,
+                        tenths % 36_000 / 600,
+                        (tenths % 600) as f64 / 10.0
+                    )
+                };
+                format!(
+                    "{} {}",
+                    dms(gps.latitude, 'N', 'S'),
+                    dms(gps.longitude, 'E', 'W')
+                )
+            }
+        }
+    }
+
+    /// The lines of the date stamp with their size in points, the time included when enabled.
+    pub fn date_lines(&self, date: &DateTime) -> Vec<(String, usize)> {
+        let day = self.format_date(date);
+        let time = || self.locale.strftime(date, &self.date.time_format);
+        match self.date.time {
+            StampTime::Off => vec![(day, self.date.size_pt)],
+            StampTime::Inline => vec![(format!("{day} {}", time()), self.date.size_pt)],
+            StampTime::Line => vec![(day, self.date.size_pt), (time(), self.date.time_size_pt)],
+        }
+    }
+
+    pub fn mm_to_px(&self, mm: f32) -> u32 {
+        ((mm / 25.4) * self.dpi).round() as u32
+    }
+
+    pub fn cm_to_px(&self, cm: f32) -> u32 {
+        ((cm / 2.54) * self.dpi).round() as u32
+    }
+
+    pub fn target_width_px(&self) -> u32 {
+        self.cm_to_px(self.width_cm)
+    }
+
+    pub fn target_height_px(&self) -> u32 {
+        self.cm_to_px(self.height_cm)
+    }
+
+    /// The target for a folder given on its own, from `dropped_target`.
+    pub fn dropped_target(&self, folder: &Path) -> PathBuf {
+        let name = folder
+            .file_name()
+            .and_then(|x| x.to_str())
+            .unwrap_or_default();
+        let target = self.dropped_target.replace("{folder}", name);
+        folder.parent().unwrap_or(Path::new(".")).join(target)
+    }
+
+    /// Height of the caption strip for a label of `label_lines` lines: the label or the date,
+    /// whichever is higher, with the margin above and below.
+    pub fn caption_strip_px(&self, label_lines: usize) -> u32 {
+        let line = |pt: usize| pt_to_px(pt, self.dpi * self.stamp_scale).ceil() as u32;
+        let label = line(self.label.size_pt) * label_lines as u32;
+        let date: u32 = self
+            .date_lines(&DateTime::default())
+            .iter()
+            .map(|(_, pt)| line(*pt))
+            .sum();
+        // The coordinates are a line of their own, or under the stamp in their corner
+        let gps = match (self.gps.enabled, self.gps.text) {
+            (false, _) => 0,
+            (true, GpsText::Both) => 2 * line(self.gps.size_pt),
+            (true, _) => line(self.gps.size_pt),
+        };
+        let under = |position: DrawPosition| match self.gps_position() == position {
+            true => gps,
+            false => 0,
+        };
+        let label = label + under(self.label_position());
+        let date = date + under(self.date_position());
+        let margin = (self.margin_px() as f32 * self.stamp_scale).round() as u32;
+        label.max(date).max(gps) + 2 * margin.max(self.safe_area_px())
+    }
+
+    pub fn margin_px(&self) -> u32 {
+        self.mm_to_px(self.margin_mm)
+    }
+
+    /// The offset of a stamp from its corner in pixels.
+    pub fn offset_px(&self, x_mm: f32, y_mm: f32) -> StampOffset {
+        let px = |mm: f32| ((mm / 25.4) * self.dpi).round() as i32;
+        StampOffset {
+            x: px(x_mm),
+            y: px(y_mm),
+        }
+    }
+
+    /// How long a new file has to stay the same before `--watch` processes it.
+    pub fn watch_settle(&self) -> Duration {
+        Duration::try_from_secs_f32(self.watch_settle_secs).unwrap_or_default()
+    }
+
+    /// The distance the stamps are kept from the edges of the print, the bleed included.
+    pub fn safe_area_px(&self) -> u32 {
+        self.mm_to_px(self.safe_area_mm + self.bleed_mm)
+    }
+}
+
+/// Checks that `format` is a strftime format a date can be stamped with, e.g. `%d.%m.%Y`.
+/// Formats with a time zone, like `%Z`, are rejected, the dates of photos have none.
+pub fn check_date_format(format: &str) -> Result<(), AppError> {
+    jiff::fmt::strtime::format(format, DateTime::default())
+        .map(|_| ())
+        .map_err(|e| AppError::DateFormat(format.to_string(), e))
+}
+
+/// Checks that the stamps can be scaled by `scale`.
+pub fn check_stamp_scale(scale: f32) -> Result<(), AppError> {
+    match scale.is_finite() && scale > 0.0 {
+        true => Ok(()),
+        false => Err(AppError::StampScale(scale)),
+    }
+}
+
+/// Checks that the print size `value`, set as `name` in the config, gives the photos pixels.
+fn check_print_size(name: &'static str, value: f32) -> Result<(), AppError> {
+    match value.is_finite() && value > 0.0 {
+        true => Ok(()),
+        false => Err(AppError::PrintSize(name, value)),
+    }
+}
+
+/// The places a config file is looked for when none is given, in order.
+pub fn default_config_locations() -> Vec<PathBuf> {
+    let mut locations = vec![PathBuf::from(CONFIG_FILE_NAME)];
+    if let Some(dir) = global_config_dir() {
+        locations.push(dir.join(CONFIG_FILE_NAME));
+    }
+    locations
+}
+
+/// The OS config directory of the app, shared with the tauri app.
+pub fn global_config_dir() -> Option<PathBuf> {
+    directories::ProjectDirs::from("", "", "photo-bench-ui").map(|d| d.config_dir().to_path_buf())
+}
+
+/// Writes the documented default config into `dir`. Does not overwrite an existing config unless `force` is set.
+pub fn write_default_config(dir: &Path, force: bool) -> Result<PathBuf, AppError> {
+    let path = dir.join(CONFIG_FILE_NAME);
+    if path.exists() && !force {
+        return Err(AppError::ConfigExists(path));
+    }
+    fs::create_dir_all(dir)?;
+    fs::write(&path, DEFAULT_CONFIG_TOML)?;
+    Ok(path)
+}
+
+/// An RGBA color written as `#RRGGBB` or `#RRGGBBAA`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(try_from = "String", into = "String")]
+pub struct Color(pub [u8; 4]);
+
+impl Color {
+    pub fn rgb(&self) -> Rgb<u8> {
+        Rgb([self.0[0], self.0[1], self.0[2]])
+    }
+
+    pub fn rgba(&self) -> Rgba<u8> {
+        Rgba(self.0)
+    }
+}
+
+impl FromStr for Color {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let hex = s.trim().trim_start_matches('#');
+        if !hex.is_ascii() || (hex.len() != 6 && hex.len() != 8) {
+            return Err(format!("{s} is not a color like #RRGGBB or #RRGGBBAA"));
+        }
+        let mut rgba = [255u8; 4];
+        for (i, chunk) in hex.as_bytes().chunks(2).enumerate() {
+            let chunk = std::str::from_utf8(chunk).map_err(|e| e.to_string())?;
+            rgba[i] = u8::from_str_radix(chunk, 16).map_err(|e| format!("{e}. Invalid color {s}"))?;
+        }
+        Ok(Color(rgba))
+    }
+}
+
+impl TryFrom<String> for Color {
+    type Error = String;
+
+    fn try_from(value: String) -> Result<Self, Self::Error> {
+        value.parse()
+    }
+}
+
+impl From<Color> for String {
+    fn from(value: Color) -> Self {
+        value.to_string()
+    }
+}
+
+impl fmt::Display for Color {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let [r, g, b, a] = self.0;
+        if a == 255 {
+            write!(f, "#{r:02X}{g:02X}{b:02X}")
+        } else {
+            write!(f, "#{r:02X}{g:02X}{b:02X}{a:02X}")
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn right_to_left_dates_are_stamped_in_a_font_with_their_letters() {
+        let mut config = Config {
+            locale: Locale::Ar,
+            ..Config::default()
+        };
+        assert_eq!(config.date_font(), "arial-bold");
+        assert!(config.validate().is_ok());
+        let font = fonts::load_font(config.date_font()).unwrap();
+        for c in ['\u{0662}', '\u{FE91}', '\u{FEFB}', 'א'] {
+            assert_ne!(ab_glyph::Font::glyph_id(&font, c).0, 0, "{c}");
+        }
+
+        config.date.font = Some("arial-rounded-bold".to_string());
+        assert!(matches!(config.validate(), Err(AppError::FontLacksRtl(_))));
+        config.locale = Locale::En;
+        assert!(config.validate().is_ok());
+        assert_eq!(Config::default().date_font(), "arial-rounded-bold");
+    }
+
+    #[test]
+    fn the_print_size_must_be_more_than_0() {
+        assert!(Config::default().validate().is_ok());
+        for value in [0.0, -1.0, f32::NAN, f32::INFINITY] {
+            for (name, set) in [
+                ("dpi", (|c, v| c.dpi = v) as fn(&mut Config, f32)),
+                ("width_cm", |c, v| c.width_cm = v),
+                ("height_cm", |c, v| c.height_cm = v),
+            ] {
+                let mut config = Config::default();
+                set(&mut config, value);
+                assert!(
+                    matches!(config.validate(), Err(AppError::PrintSize(n, _)) if n == name),
+                    "{name} = {value}"
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn colors_parse_with_or_without_alpha() {
+        assert_eq!("#FF8000".parse(), Ok(Color([255, 128, 0, 255])));
+        assert_eq!("ff800080".parse(), Ok(Color([255, 128, 0, 128])));
+        assert_eq!(" #00000000 ".parse(), Ok(Color([0, 0, 0, 0])));
+        assert_eq!(Color([255, 128, 0, 255]).to_string(), "#FF8000");
+        assert_eq!(Color([255, 128, 0, 128]).to_string(), "#FF800080");
+
+        for bad in [
+            "",
+            "#",
+            "#FFF",
+            "#FF80",
+            "#FF80001",
+            "#GG8000",
+            "#FF80€0",
+            "#FF8000FF00",
+        ] {
+            assert!(bad.parse::<Color>().is_err(), "{bad}");
+        }
+    }
+}
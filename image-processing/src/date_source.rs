@@ -0,0 +1,79 @@
+use std::fs;
+use std::path::Path;
+
+use exif::Tag;
+use jiff::civil::DateTime;
+use jiff::tz::TimeZone;
+use serde::{Deserialize, Serialize};
+use tracing::debug;
+
+use crate::error::AppError;
+use crate::{image_ops, metadata};
+
+/// Where the date of a photo is taken from. The sources are tried in the order of the config
+/// until one has a date.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, clap::ValueEnum)]
+#[serde(rename_all = "kebab-case")]
+pub enum DateSource {
+    /// The EXIF DateTimeOriginal, when the photo was taken
+    ExifOriginal,
+    /// The EXIF DateTimeDigitized, when the photo was stored or scanned
+    ExifDigitized,
+    /// The XMP data of jpegs and the text chunks of PNGs, e.g. of screenshots
+    Xmp,
+    /// A date in the file name, e.g. IMG_20251224_1015.jpg
+    FileName,
+    /// When the file was last changed, in the local time zone
+    Modified,
+    /// Photos none of the sources before it had a date for are skipped and listed, so they can
+    /// be given one
+    Ask,
+}
+
+/// The sources of the date before they could be chosen.
+pub const DEFAULT_DATE_SOURCES: [DateSource; 3] = [
+    DateSource::ExifOriginal,
+    DateSource::Xmp,
+    DateSource::FileName,
+];
+
+impl DateSource {
+    /// The date of the photo at `path` from this source, if it has one.
+    fn date(self, path: &Path) -> Result<Option<DateTime>, AppError> {
+        match self {
+            DateSource::ExifOriginal => {
+                metadata::get_exif_date(path, Tag::DateTimeOriginal, Tag::SubSecTimeOriginal)
+            }
+            DateSource::ExifDigitized => {
+                metadata::get_exif_date(path, Tag::DateTimeDigitized, Tag::SubSecTimeDigitized)
+            }
+            DateSource::Xmp => match metadata::get_png_text_date(path)? {
+                Some(date) => Ok(Some(date)),
+                None => metadata::get_jpeg_xmp_date(path),
+            },
+            DateSource::FileName => Ok(image_ops::date_from_filename(path)),
+            DateSource::Modified => {
+                let modified = jiff::Timestamp::try_from(fs::metadata(path)?.modified()?)?;
+                Ok(Some(modified.to_zoned(TimeZone::system()).datetime()))
+            }
+            DateSource::Ask => Ok(None),
+        }
+    }
+}
+
+/// The date of the photo at `path` from the first of `sources` that has one. `None` when the
+/// photo reached [`DateSource::Ask`], an error when no source had a date.
+pub fn resolve(path: &Path, sources: &[DateSource]) -> Result<Option<DateTime>, AppError> {
+    for source in sources {
+        if *source == DateSource::Ask {
+            debug!("No date for {path:?}, asking for one");
+            return Ok(None);
+        }
+        if let Some(date) = source.date(path)? {
+            debug!("Took the date of {path:?} from {source:?}");
+            return Ok(Some(date));
+        }
+    }
+    debug!("Could not extract date from file: {path:?}");
+    Err(AppError::NoParsibleDate(path.to_path_buf()))
+}
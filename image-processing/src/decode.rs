@@ -0,0 +1,160 @@
+use std::io::{BufRead, Cursor, Seek};
+use std::path::Path;
+
+use image::{DynamicImage, ImageDecoder, ImageReader, RgbImage};
+use serde::{Deserialize, Serialize};
+
+use crate::error::AppError;
+use crate::raw;
+
+/// Decodes an original to 8 bit RGB, with its embedded color profile when `icc` is set.
+pub trait Decode: Sync {
+    fn decode(&self, path: &Path, icc: bool) -> Result<(RgbImage, Option<Vec<u8>>), AppError>;
+}
+
+/// The library the originals are decoded with.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize, clap::ValueEnum)]
+#[serde(rename_all = "kebab-case")]
+pub enum DecodeBackend {
+    /// The decoders of the image crate, for every supported format
+    #[default]
+    Image,
+    /// zune-jpeg straight to RGB, faster for big jpegs. Other formats use the image crate
+    ZuneJpeg,
+}
+
+impl DecodeBackend {
+    pub fn decoder(self) -> Result<&'static dyn Decode, AppError> {
+        match self {
+            DecodeBackend::Image => Ok(&ImageCrate),
+            #[cfg(feature = "zune-jpeg")]
+            DecodeBackend::ZuneJpeg => Ok(&zune::ZuneJpeg),
+            #[cfg(not(feature = "zune-jpeg"))]
+            DecodeBackend::ZuneJpeg => Err(AppError::DecoderNotBuilt("zune-jpeg")),
+        }
+    }
+}
+
+/// Decodes with the image crate, raws by their preview.
+pub struct ImageCrate;
+
+impl Decode for ImageCrate {
+    fn decode(&self, path: &Path, icc: bool) -> Result<(RgbImage, Option<Vec<u8>>), AppError> {
+        match raw::read_preview(path)? {
+            Some(jpeg) => decode_reader(ImageReader::new(Cursor::new(jpeg)), icc),
+            None => decode_reader(ImageReader::open(path)?, icc),
+        }
+    }
+}
+
+fn decode_reader<R: BufRead + Seek>(
+    reader: ImageReader<R>,
+    icc: bool,
+) -> Result<(RgbImage, Option<Vec<u8>>), AppError> {
+    let mut decoder = reader.with_guessed_format()?.into_decoder()?;
+    let icc = match icc {
+        true => decoder.icc_profile()?,
+        false => None,
+    };
+    Ok((DynamicImage::from_decoder(decoder)?.to_rgb8(), icc))
+}
+
+#[cfg(feature = "zune-jpeg")]
+mod zune {
+    use std::fs;
+    use std::path::Path;
+
+    use image::RgbImage;
+    use zune_core::bytestream::ZCursor;
+    use zune_core::colorspace::ColorSpace;
+    use zune_core::options::DecoderOptions;
+    use zune_jpeg::JpegDecoder;
+
+    use super::{Decode, ImageCrate};
+    use crate::error::AppError;
+    use crate::raw;
+
+    /// Decodes jpegs with zune-jpeg, straight into the RGB buffer of the image.
+    pub struct ZuneJpeg;
+
+    impl Decode for ZuneJpeg {
+        fn decode(&self, path: &Path, icc: bool) -> Result<(RgbImage, Option<Vec<u8>>), AppError> {
+            let mut data = fs::read(path)?;
+            if raw::is_tiff(&data) {
+                data = raw::preview(&data)
+                    .ok_or_else(|| AppError::RawPreview(path.to_path_buf()))?
+                    .to_vec();
+            }
+            if !data.starts_with(&[0xFF, 0xD8]) {
+                return ImageCrate.decode(path, icc);
+            }
+            let options = DecoderOptions::default().jpeg_set_out_colorspace(ColorSpace::RGB);
+            let mut decoder = JpegDecoder::new_with_options(ZCursor::new(&data), options);
+            let pixels = decoder.decode()?;
+            let info = decoder.info().expect("Headers are decoded with the pixels");
+            let img = RgbImage::from_raw(info.width.into(), info.height.into(), pixels)
+                .ok_or_else(|| AppError::Jpeg(format!("{path:?} decoded to the wrong size")))?;
+            let icc = icc.then(|| decoder.icc_profile()).flatten();
+            Ok((img, icc))
+        }
+    }
+
+    impl From<zune_jpeg::errors::DecodeErrors> for AppError {
+        fn from(e: zune_jpeg::errors::DecodeErrors) -> Self {
+            AppError::Jpeg(e.to_string())
+        }
+    }
+}
+
+#[cfg(all(test, feature = "zune-jpeg"))]
+mod tests {
+    use std::fs;
+
+    use image::codecs::jpeg::JpegEncoder;
+    use image::{Rgb, RgbImage};
+
+    use super::*;
+    use crate::test_util::temp_dir;
+
+    #[test]
+    fn both_decoders_decode_the_same_pixels() {
+        // Odd sizes and color edges, so the chroma upsampling of the two is compared too
+        let original = RgbImage::from_fn(173, 91, |x, y| {
+            Rgb([
+                (x * 255 / 173) as u8,
+                (y * 255 / 91) as u8,
+                ((x / 16 + y / 16) % 2 * 200) as u8,
+            ])
+        });
+        let dir = temp_dir("decode");
+        let path = dir.path().join("photo.jpg");
+        let mut jpeg = vec![];
+        JpegEncoder::new_with_quality(&mut jpeg, 90)
+            .encode_image(&original)
+            .unwrap();
+        fs::write(&path, jpeg).unwrap();
+
+        let (image, _) = DecodeBackend::Image
+            .decoder()
+            .unwrap()
+            .decode(&path, false)
+            .unwrap();
+        let (zune, _) = DecodeBackend::ZuneJpeg
+            .decoder()
+            .unwrap()
+            .decode(&path, false)
+            .unwrap();
+
+        assert_eq!(image.dimensions(), zune.dimensions());
+        let diffs: Vec<u8> = image
+            .as_raw()
+            .iter()
+            .zip(zune.as_raw())
+            .map(|(a, b)| a.abs_diff(*b))
+            .collect();
+        let max = diffs.iter().max().copied().unwrap_or_default();
+        let mean = diffs.iter().map(|&d| f64::from(d)).sum::<f64>() / diffs.len() as f64;
+        assert!(max <= 4, "The decoders differ by up to {max}");
+        assert!(mean <= 0.5, "The decoders differ by {mean:.2} on average");
+    }
+}
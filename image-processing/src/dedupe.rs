@@ -0,0 +1,196 @@
+use std::collections::{BTreeMap, HashSet};
+use std::fmt;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use tracing::{info, warn};
+
+use crate::config::Config;
+use crate::error::AppError;
+use crate::{collect_source_images, hashing, image_ops, raw};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DuplicateKind {
+    /// The files have exactly the same bytes
+    Identical,
+    /// The images look the same, e.g. a resized or recompressed copy
+    Similar,
+}
+
+/// A set of images that are duplicates of each other.
+#[derive(Debug)]
+pub struct DuplicateGroup {
+    pub kind: DuplicateKind,
+    /// The image that stays in the source
+    pub keep: PathBuf,
+    pub duplicates: Vec<PathBuf>,
+}
+
+/// Finds byte identical and perceptually similar images in the source folder.
+/// - `max_distance`: the maximum amount of differing bits between two perceptual hashes for the images to count as similar
+pub fn find_duplicates(
+    source: &Path,
+    config: &Config,
+    max_distance: u32,
+) -> Result<Vec<DuplicateGroup>, AppError> {
+    let mut images = collect_source_images(source, &config.name_template()?)?;
+    images.sort();
+    info!("Looking for duplicates in {} images", images.len());
+
+    let mut groups = vec![];
+
+    // Only files with the same size can be identical, so only hash those
+    let mut by_size: BTreeMap<u64, Vec<&PathBuf>> = BTreeMap::new();
+    for path in &images {
+        by_size.entry(fs::metadata(path)?.len()).or_default().push(path);
+    }
+    let mut by_hash: BTreeMap<String, Vec<PathBuf>> = BTreeMap::new();
+    for paths in by_size.into_values().filter(|x| x.len() > 1) {
+        for path in paths {
+            by_hash
+                .entry(hashing::sha256_file(path)?)
+                .or_default()
+                .push(path.clone());
+        }
+    }
+    let mut identical: HashSet<PathBuf> = HashSet::new();
+    for mut paths in by_hash.into_values().filter(|x| x.len() > 1) {
+        paths.sort();
+        let keep = paths.remove(0);
+        identical.extend(paths.iter().cloned());
+        groups.push(DuplicateGroup {
+            kind: DuplicateKind::Identical,
+            keep,
+            duplicates: paths,
+        });
+    }
+
+    let mut hashes = vec![];
+    for path in images.into_iter().filter(|x| !identical.contains(x)) {
+        match raw::open(&path) {
+            Ok(img) => hashes.push((path, image_ops::dhash(&img))),
+            Err(e) => warn!("{e}. Could not decode {path:?}, skipping it."),
+        }
+    }
+
+    let mut grouped = vec![false; hashes.len()];
+    for i in 0..hashes.len() {
+        if grouped[i] {
+            continue;
+        }
+        let mut duplicates = vec![];
+        for j in (i + 1)..hashes.len() {
+            if !grouped[j] && (hashes[i].1 ^ hashes[j].1).count_ones() <= max_distance {
+                grouped[j] = true;
+                duplicates.push(hashes[j].0.clone());
+            }
+        }
+        if !duplicates.is_empty() {
+            groups.push(DuplicateGroup {
+                kind: DuplicateKind::Similar,
+                keep: hashes[i].0.clone(),
+                duplicates,
+            });
+        }
+    }
+
+    Ok(groups)
+}
+
+/// Moves the duplicates out of the source folder into `destination`, keeping their folder structure.
+/// With `trash` duplicates copied to another drive are sent to the recycle bin instead of deleted.
+pub fn move_duplicates(
+    groups: &[DuplicateGroup],
+    source: &Path,
+    destination: &Path,
+    trash: bool,
+) -> Result<(), AppError> {
+    for path in groups.iter().flat_map(|g| &g.duplicates) {
+        let relative = path.strip_prefix(source).unwrap_or(path);
+        let out_path = destination.join(relative);
+        if out_path.exists() {
+            warn!("{out_path:?} already exists. Not moving {path:?}");
+            continue;
+        }
+        if let Some(parent) = out_path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        image_ops::move_file(path, &out_path, trash)?;
+        info!("Moved {path:?} → {out_path:?}");
+    }
+    Ok(())
+}
+
+impl fmt::Display for DuplicateGroup {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let kind = match self.kind {
+            DuplicateKind::Identical => "Identical",
+            DuplicateKind::Similar => "Similar",
+        };
+        writeln!(f, "{kind}: {}", self.keep.display())?;
+        for dup in &self.duplicates {
+            writeln!(f, "  {}", dup.display())?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::f32::consts::TAU;
+
+    use image::{GrayImage, Luma};
+
+    use super::*;
+    use crate::test_util::temp_dir;
+
+    /// A photo of soft waves, `waves` across, brightened by `light`.
+    fn waves(width: u32, height: u32, waves: f32, light: f32) -> GrayImage {
+        GrayImage::from_fn(width, height, |x, y| {
+            let (x, y) = (x as f32 / width as f32, y as f32 / height as f32);
+            let wave = (x * waves * TAU).sin() * (y * 1.5 * TAU).cos();
+            Luma([(120.0 + 90.0 * wave + light).clamp(0.0, 255.0) as u8])
+        })
+    }
+
+    #[test]
+    fn finds_identical_and_similar_images_but_not_distinct_ones() {
+        let source = temp_dir("dedupe");
+        let path = |name: &str| source.path().join(name);
+        waves(300, 200, 2.0, 0.0).save(path("beach.png")).unwrap();
+        fs::copy(path("beach.png"), path("beach copy.png")).unwrap();
+        // Resized and a little lighter, as a copy sent through a messenger
+        waves(150, 100, 2.0, 8.0)
+            .save(path("beach small.png"))
+            .unwrap();
+        waves(300, 200, 3.0, 0.0).save(path("forest.png")).unwrap();
+
+        let groups = find_duplicates(source.path(), &Config::default(), 10).unwrap();
+        let found: Vec<_> = groups
+            .iter()
+            .map(|group| (group.kind, group.keep.clone(), group.duplicates.clone()))
+            .collect();
+        assert_eq!(
+            found,
+            [
+                (
+                    DuplicateKind::Identical,
+                    path("beach copy.png"),
+                    vec![path("beach.png")]
+                ),
+                (
+                    DuplicateKind::Similar,
+                    path("beach copy.png"),
+                    vec![path("beach small.png")]
+                ),
+            ]
+        );
+
+        // Every hash is within 64 bits of every other
+        let groups = find_duplicates(source.path(), &Config::default(), 64).unwrap();
+        assert_eq!(
+            groups[1].duplicates,
+            [path("beach small.png"), path("forest.png")]
+        );
+    }
+}
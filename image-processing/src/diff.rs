@@ -0,0 +1,198 @@
+use std::collections::{BTreeMap, HashSet};
+use std::fmt;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use serde::Serialize;
+
+use crate::config::Config;
+use crate::error::AppError;
+use crate::{collect_source_images, ledger, manifest};
+
+/// Machine readable result of comparing a source folder with a target folder.
+#[derive(Debug, Default, Serialize)]
+pub struct DiffReport {
+    /// Images in the source with no output in the target
+    pub unprocessed: Vec<PathBuf>,
+    /// Outputs in the target whose original is gone
+    pub orphaned: Vec<Orphan>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct Orphan {
+    /// Path of the output relative to the target
+    pub output: PathBuf,
+    /// The original as it was recorded when it was processed
+    pub source: PathBuf,
+}
+
+impl DiffReport {
+    pub fn len(&self) -> usize {
+        self.unprocessed.len() + self.orphaned.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+/// A run over the same source folder that was recorded in the ledger of the target before with
+/// the same options, or in its manifest.
+#[derive(Debug, Serialize)]
+pub struct PreviousRun {
+    /// When the previous run started
+    pub started_at: String,
+    /// Images in the source that are in the target already
+    pub processed: usize,
+    /// Whether those images are skipped, otherwise they are processed again under new numbers
+    pub skipped: bool,
+}
+
+impl fmt::Display for PreviousRun {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let date = match self.started_at.parse::<jiff::Zoned>() {
+            Ok(started_at) => started_at.strftime("%Y-%m-%d %H:%M").to_string(),
+            Err(_) => self.started_at.clone(),
+        };
+        let outcome = match self.skipped {
+            true => "skipped",
+            false => "duplicated",
+        };
+        write!(
+            f,
+            "This folder appears already processed on {date}, {} file(s) will be {outcome}",
+            self.processed
+        )
+    }
+}
+
+/// Lists the images in the source that are not in the manifest or ledger of the target yet,
+/// and the outputs in the target whose originals are not there anymore.
+pub fn diff(source: &Path, target: &Path, config: &Config) -> Result<DiffReport, AppError> {
+    let template = config.name_template()?;
+
+    // The outputs per original, from the manifest and the ledger. The originals are recorded as
+    // the runs were given them, e.g. relative to where a run was started, so one original can
+    // be recorded under more than one path
+    let mut processed: BTreeMap<PathBuf, HashSet<PathBuf>> = BTreeMap::new();
+    let recorded = manifest::read_manifest(target)?
+        .into_iter()
+        .map(|e| (e.source, e.output))
+        .chain(ledger::read_processed(target)?);
+    for (source, output) in recorded {
+        processed
+            .entry(canonical(&source))
+            .or_default()
+            .insert(output);
+    }
+
+    let mut report = DiffReport::default();
+    for image in collect_source_images(source, &template)? {
+        if !processed.contains_key(&canonical(&image)) {
+            report.unprocessed.push(image);
+        }
+    }
+    report.unprocessed.sort();
+
+    for (source, outputs) in processed {
+        if source.exists() {
+            continue;
+        }
+        let mut outputs: Vec<_> = outputs
+            .into_iter()
+            .filter(|output| target.join(output).exists())
+            .collect();
+        outputs.sort();
+        for output in outputs {
+            report.orphaned.push(Orphan {
+                output,
+                source: source.clone(),
+            });
+        }
+    }
+    Ok(report)
+}
+
+/// Checks the ledger of the target, or else its manifest, for an earlier run over the source,
+/// to warn before the photos are numbered a second time. `skipping` are the sources the run
+/// skips as processed before, see [`crate::processed_before`].
+pub fn previous_run(
+    sources: &[PathBuf],
+    target: &Path,
+    config: &Config,
+    skipping: Option<&HashSet<PathBuf>>,
+) -> Result<Option<PreviousRun>, AppError> {
+    let template = config.name_template()?;
+    let mut images = vec![];
+    for source in sources {
+        images.extend(collect_source_images(source, &template)?);
+    }
+    let (started_at, processed_sources): (String, HashSet<PathBuf>) =
+        match ledger::last_run(target, sources, config)? {
+            Some(started_at) => (
+                started_at,
+                ledger::read_processed(target)?
+                    .into_iter()
+                    .map(|(source, _)| canonical(&source))
+                    .collect(),
+            ),
+            // The ledger is only kept with --ledger, the manifest is always written
+            None => {
+                let wanted: HashSet<PathBuf> = images.iter().map(|i| canonical(i)).collect();
+                let recorded: Vec<_> = manifest::read_manifest(target)?
+                    .into_iter()
+                    .map(|entry| (canonical(&entry.source), entry.run_id))
+                    .filter(|(source, _)| wanted.contains(source))
+                    .collect();
+                if recorded.is_empty() {
+                    return Ok(None);
+                }
+                let last_run = recorded.iter().filter_map(|(_, id)| id.as_deref()).max();
+                let started_at = match last_run.and_then(run_started_at) {
+                    Some(started_at) => started_at,
+                    None => manifest_written_at(target)?,
+                };
+                let sources = recorded.into_iter().map(|(source, _)| source).collect();
+                (started_at, sources)
+            }
+        };
+    let processed: Vec<&PathBuf> = images
+        .iter()
+        .filter(|image| processed_sources.contains(&canonical(image)))
+        .collect();
+    if processed.is_empty() {
+        return Ok(None);
+    }
+    // The run skips the sources as they are recorded, not their canonical paths
+    let skipped =
+        skipping.is_some_and(|skipping| processed.iter().all(|image| skipping.contains(*image)));
+    Ok(Some(PreviousRun {
+        started_at,
+        processed: processed.len(),
+        skipped,
+    }))
+}
+
+/// When the run with the id started, the ids start with its start time in UTC.
+fn run_started_at(run_id: &str) -> Option<String> {
+    let time = run_id.split_once('-').map_or(run_id, |(time, _)| time);
+    let started_at = jiff::civil::DateTime::strptime("%Y%m%dT%H%M%SZ", time).ok()?;
+    let started_at = started_at.to_zoned(jiff::tz::TimeZone::UTC).ok()?;
+    Some(
+        started_at
+            .with_time_zone(jiff::tz::TimeZone::system())
+            .to_string(),
+    )
+}
+
+/// When the manifest was last written, for manifests of runs that did not record their id.
+fn manifest_written_at(target: &Path) -> Result<String, AppError> {
+    let modified = fs::metadata(target.join(manifest::MANIFEST_FILE_NAME))?.modified()?;
+    let modified = jiff::Timestamp::try_from(modified)?;
+    Ok(modified.to_zoned(jiff::tz::TimeZone::system()).to_string())
+}
+
+/// Compares paths the same way whether they were recorded relative or absolute.
+fn canonical(path: &Path) -> PathBuf {
+    path.canonicalize().unwrap_or_else(|_| path.to_path_buf())
+}
@@ -1,16 +1,40 @@
+use std::borrow::Cow;
+use std::cell::RefCell;
+
 use crate::image_ops::overlay_premul_rgba_on_rgb;
-use ab_glyph::{FontRef, PxScale};
+use ab_glyph::{Font, PxScale};
 use image::imageops;
 use image::{RgbImage, Rgba, RgbaImage};
-use imageproc::drawing::draw_text_mut;
+use imageproc::drawing::{draw_text_mut, text_size};
+use imageproc::geometric_transformations::{rotate_about_center, Interpolation};
+use unicode_bidi::{BidiInfo, Level};
 
-#[derive(Debug, Clone, Copy, Default)]
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "kebab-case")]
 pub enum DrawPosition {
     #[default]
     TopLeft,
+    TopRight,
+    BottomLeft,
     BottomRight,
 }
 
+impl DrawPosition {
+    /// The corner on the other side, for right-to-left text.
+    pub fn mirrored(self) -> Self {
+        match self {
+            DrawPosition::TopLeft => DrawPosition::TopRight,
+            DrawPosition::TopRight => DrawPosition::TopLeft,
+            DrawPosition::BottomLeft => DrawPosition::BottomRight,
+            DrawPosition::BottomRight => DrawPosition::BottomLeft,
+        }
+    }
+
+    pub fn is_left(self) -> bool {
+        matches!(self, DrawPosition::TopLeft | DrawPosition::BottomLeft)
+    }
+}
+
 #[derive(Debug, Clone, Copy)]
 pub struct PhotoSize {
     pub width: u32,
@@ -23,6 +47,13 @@ pub struct PhotoOffset {
     pub y: u32,
 }
 
+/// Moves a stamp from its corner towards the middle of the photo, negative values towards the edge.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct StampOffset {
+    pub x: i32,
+    pub y: i32,
+}
+
 #[derive(Debug)]
 pub struct MultilineDraw<'a> {
     /// - `photo_size`: (width,height) of the photo
@@ -31,11 +62,22 @@ pub struct MultilineDraw<'a> {
     pub photo_offset: PhotoOffset,
     /// - `margin_px`: margin from photo edges
     pub margin_px: u32,
+    /// - `safe_area_px`: distance kept from the edges of the canvas, which printers clip
+    pub safe_area_px: u32,
+    /// - `offset_px`: moves the next lines drawn from their corner, in addition to the margin
+    pub offset_px: StampOffset,
     /// - `dst`: final RGB image (full canvas)
     pub destination: &'a mut RgbImage,
+    /// - `rtl`: lay the lines out right-to-left and align them to the right, e.g. for Arabic and Hebrew
+    pub rtl: bool,
 }
 
-const fn pt_to_px(pt: usize, dpi: f32) -> f32 {
+thread_local! {
+    /// The pixels of the transparent layer the lines are drawn on, reused by every photo of a thread
+    static TEXT_LAYER: RefCell<Vec<u8>> = const { RefCell::new(Vec::new()) };
+}
+
+pub const fn pt_to_px(pt: usize, dpi: f32) -> f32 {
     pt as f32 * (dpi / 72.)
 }
 
@@ -57,85 +99,359 @@ impl<'a> MultilineDraw<'a> {
     /// - `lines`: exactly 3 lines of text
     /// - `font`: loaded TTF font
     /// - `color`: text color (RGBA)
-    pub fn draw_multiline_text<S: AsRef<str>>(
+    pub fn draw_multiline_text<S: AsRef<str>, F: Font>(
         &mut self,
         lines: &[S],
-        font: &FontRef,
+        font: &F,
         font_size: FontSize,
         color: Rgba<u8>,
         position: DrawPosition,
     ) {
+        let lines: Vec<(&str, FontSize)> = lines.iter().map(|l| (l.as_ref(), font_size)).collect();
+        self.draw_sized_lines(&lines, font, color, position);
+    }
+
+    /// Like [`Self::draw_multiline_text`], but every line has its own size.
+    pub fn draw_sized_lines<S: AsRef<str>, F: Font>(
+        &mut self,
+        lines: &[(S, FontSize)],
+        font: &F,
+        color: Rgba<u8>,
+        position: DrawPosition,
+    ) {
+        let Some((_, first_size)) = lines.first() else {
+            return;
+        };
         let &mut Self {
             ref photo_size,
             ref photo_offset,
             ref margin_px,
+            safe_area_px,
+            offset_px,
             ref mut destination,
+            rtl,
         } = self;
         // Text height ~4% of photo height (same scale logic as date)
         let line_height_px = (photo_size.height as f32 * 0.04).max(12.0);
-        let scale = font_size.as_px_scale();
+        let first_px = pt_to_px(first_size.pt, first_size.dpi).max(1.0);
 
         // Line spacing: 120% of font size
         let line_spacing = (line_height_px * 1.2).round() as u32;
+        // Smaller lines take up less space, but lines never overlap
+        let spacing_of = |size: &FontSize| {
+            let px = pt_to_px(size.pt, size.dpi);
+            ((line_spacing as f32 * px / first_px).round() as u32).max(px.ceil() as u32)
+        };
+        let block_h: u32 = lines.iter().map(|(_, size)| spacing_of(size)).sum();
 
         // Create a temporary RGBA canvas large enough for 3 lines
         let tmp_w = 2000u32;
-        let tmp_h = line_spacing * 3 + 10;
-        let mut tmp: RgbaImage = RgbaImage::from_pixel(tmp_w, tmp_h, Rgba([0, 0, 0, 0]));
+        let tmp_h = (line_spacing * 3).max(block_h) + 10;
+        let mut layer = TEXT_LAYER.take();
+        layer.clear();
+        layer.resize((tmp_w * tmp_h * 4) as usize, 0);
+        let mut tmp = RgbaImage::from_raw(tmp_w, tmp_h, layer).expect("Layer of the right size");
 
-        // Draw each line
-        for (i, text) in lines.iter().enumerate() {
-            let y = i as u32 * line_spacing;
-            draw_text_mut(&mut tmp, color, 0, y as i32, scale, font, text.as_ref());
-        }
+        let lines: Vec<(Cow<str>, FontSize)> = lines
+            .iter()
+            .map(|(text, size)| match rtl {
+                true => (
+                    Cow::Owned(visual_order(&join_arabic(text.as_ref())).into_owned()),
+                    *size,
+                ),
+                false => (Cow::Borrowed(text.as_ref()), *size),
+            })
+            .collect();
+        let widths: Vec<u32> = lines
+            .iter()
+            .map(|(text, size)| text_size(size.as_px_scale(), font, text).0)
+            .collect();
+        let block_w = widths.iter().copied().max().unwrap_or_default();
 
-        // Crop to bounding box of non-transparent pixels
-        let mut min_x = tmp_w;
-        let mut min_y = tmp_h;
-        let mut max_x = 0u32;
-        let mut max_y = 0u32;
-        let mut found = false;
-
-        for y in 0..tmp.height() {
-            for x in 0..tmp.width() {
-                if tmp.get_pixel(x, y)[3] != 0 {
-                    found = true;
-                    min_x = min_x.min(x);
-                    min_y = min_y.min(y);
-                    max_x = max_x.max(x);
-                    max_y = max_y.max(y);
-                }
-            }
+        // Draw each line
+        let mut y = 0;
+        for ((text, size), width) in lines.iter().zip(widths) {
+            let x = if rtl { block_w - width } else { 0 };
+            let scale = size.as_px_scale();
+            draw_text_mut(&mut tmp, color, x as i32, y as i32, scale, font, text);
+            y += spacing_of(size);
         }
 
-        if !found {
+        let text_img = crop_to_ink(&tmp);
+        TEXT_LAYER.set(tmp.into_raw());
+        let Some(text_img) = text_img else {
             return;
+        };
+
+        // Distance of the stamp from the edges of its corner
+        let inset_x = *margin_px as i64 + offset_px.x as i64;
+        let inset_y = *margin_px as i64 + offset_px.y as i64;
+        // From the start of the photo area, or from its end for the right and bottom corners
+        let near = |start: u32, inset: i64| (start as i64 + inset).max(0) as u32;
+        let far = |start: u32, extent: u32, size: u32, inset: i64| {
+            start + (extent as i64 - size as i64 - inset).max(0) as u32
+        };
+        let (x, y) = match position {
+            // Final position: top-left of photo area + margin
+            DrawPosition::TopLeft => (near(photo_offset.x, inset_x), near(photo_offset.y, inset_y)),
+            DrawPosition::TopRight => (
+                far(photo_offset.x, photo_size.width, text_img.width(), inset_x),
+                near(photo_offset.y, inset_y),
+            ),
+            DrawPosition::BottomLeft => (
+                near(photo_offset.x, inset_x),
+                far(
+                    photo_offset.y,
+                    photo_size.height,
+                    text_img.height(),
+                    inset_y,
+                ),
+            ),
+            // Paste bottom-right relative to the photo area (not the full canvas)
+            DrawPosition::BottomRight => (
+                far(photo_offset.x, photo_size.width, text_img.width(), inset_x),
+                far(
+                    photo_offset.y,
+                    photo_size.height,
+                    text_img.height(),
+                    inset_y,
+                ),
+            ),
+        };
+
+        // Move the stamp inside the safe area of the canvas, whatever the margin
+        let max_x = destination
+            .width()
+            .saturating_sub(text_img.width() + safe_area_px);
+        let max_y = destination
+            .height()
+            .saturating_sub(text_img.height() + safe_area_px);
+        let x = x.clamp(safe_area_px.min(max_x), max_x);
+        let y = y.clamp(safe_area_px.min(max_y), max_y);
+        overlay_premul_rgba_on_rgb(destination, &text_img, x, y);
+    }
+}
+
+/// Draws `text` as large as fits from the bottom-left to the top-right corner of the photo
+/// area, covering the photo by the alpha of `color`.
+pub fn draw_diagonal_text<F: Font>(
+    destination: &mut RgbImage,
+    photo_size: PhotoSize,
+    photo_offset: PhotoOffset,
+    text: &str,
+    font: &F,
+    color: Rgba<u8>,
+) {
+    let PhotoSize { width, height } = photo_size;
+    let text = text.trim();
+    let (text_w, _) = text_size(PxScale::from(100.0), font, text);
+    if text_w == 0 || width == 0 || height == 0 {
+        return;
+    }
+    // 80% of the diagonal long, but short texts are kept lower than a third of the photo
+    let diagonal = (width as f32).hypot(height as f32);
+    let px = (80.0 * diagonal / text_w as f32).min(width.min(height) as f32 / 3.0);
+    let (text_w, _) = text_size(PxScale::from(px), font, text);
+    let opaque = Rgba([color[0], color[1], color[2], 255]);
+    let mut line = RgbaImage::new(text_w + px as u32, px as u32 * 2);
+    let pad = (px / 2.0) as i32;
+    draw_text_mut(&mut line, opaque, pad, pad, PxScale::from(px), font, text);
+    let Some(line) = crop_to_ink(&line) else {
+        return;
+    };
+
+    // Turned around the middle of a square the photo area fits in, then cut to the photo area
+    let side = (diagonal.ceil() as u32).max(line.width());
+    let mut square = RgbaImage::new(side, side);
+    imageops::overlay(
+        &mut square,
+        &line,
+        ((side - line.width()) / 2).into(),
+        ((side - line.height()) / 2).into(),
+    );
+    let angle = -(height as f32).atan2(width as f32);
+    let mut turned =
+        rotate_about_center(&square, angle, Interpolation::Bilinear, Rgba([0, 0, 0, 0]));
+    // The text is drawn premultiplied, so every channel is scaled by the alpha of the color
+    let alpha = color[3] as f32 / 255.0;
+    for channel in turned.iter_mut() {
+        *channel = (*channel as f32 * alpha).round() as u8;
+    }
+    let turned = imageops::crop_imm(
+        &turned,
+        (side - width) / 2,
+        (side - height) / 2,
+        width,
+        height,
+    )
+    .to_image();
+    overlay_premul_rgba_on_rgb(destination, &turned, photo_offset.x, photo_offset.y);
+}
+
+/// The part of `layer` with text on it, or `None` when nothing was drawn.
+fn crop_to_ink(layer: &RgbaImage) -> Option<RgbaImage> {
+    let mut min_x = layer.width();
+    let mut min_y = layer.height();
+    let mut max_x = 0u32;
+    let mut max_y = 0u32;
+    let mut found = false;
+
+    for (x, y, pixel) in layer.enumerate_pixels() {
+        if pixel[3] != 0 {
+            found = true;
+            min_x = min_x.min(x);
+            min_y = min_y.min(y);
+            max_x = max_x.max(x);
+            max_y = max_y.max(y);
         }
+    }
 
+    found.then(|| {
         let crop_w = max_x - min_x + 1;
         let crop_h = max_y - min_y + 1;
-        let text_img = imageops::crop_imm(&tmp, min_x, min_y, crop_w, crop_h).to_image();
+        imageops::crop_imm(layer, min_x, min_y, crop_w, crop_h).to_image()
+    })
+}
+
+/// Reorders a line of right-to-left text into the left-to-right order its glyphs are drawn in.
+/// Runs of numbers and latin text keep their own order.
+fn visual_order(text: &str) -> Cow<'_, str> {
+    let bidi = BidiInfo::new(text, Some(Level::rtl()));
+    match bidi.paragraphs.first() {
+        Some(paragraph) => bidi.reorder_line(paragraph, paragraph.range.clone()),
+        None => Cow::Borrowed(text),
+    }
+}
+
+/// The isolated form of an Arabic letter in the presentation forms block, followed by its
+/// final form and, for letters that join on both sides, its initial and medial forms.
+/// Hamza never joins and is left out.
+fn arabic_forms(c: char) -> Option<(u32, bool)> {
+    let forms = match c {
+        'آ' => (0xFE81, false),
+        'أ' => (0xFE83, false),
+        'ؤ' => (0xFE85, false),
+        'إ' => (0xFE87, false),
+        'ئ' => (0xFE89, true),
+        'ا' => (0xFE8D, false),
+        'ب' => (0xFE8F, true),
+        'ة' => (0xFE93, false),
+        'ت' => (0xFE95, true),
+        'ث' => (0xFE99, true),
+        'ج' => (0xFE9D, true),
+        'ح' => (0xFEA1, true),
+        'خ' => (0xFEA5, true),
+        'د' => (0xFEA9, false),
+        'ذ' => (0xFEAB, false),
+        'ر' => (0xFEAD, false),
+        'ز' => (0xFEAF, false),
+        'س' => (0xFEB1, true),
+        'ش' => (0xFEB5, true),
+        'ص' => (0xFEB9, true),
+        'ض' => (0xFEBD, true),
+        'ط' => (0xFEC1, true),
+        'ظ' => (0xFEC5, true),
+        'ع' => (0xFEC9, true),
+        'غ' => (0xFECD, true),
+        'ف' => (0xFED1, true),
+        'ق' => (0xFED5, true),
+        'ك' => (0xFED9, true),
+        'ل' => (0xFEDD, true),
+        'م' => (0xFEE1, true),
+        'ن' => (0xFEE5, true),
+        'ه' => (0xFEE9, true),
+        'و' => (0xFEED, false),
+        'ى' => (0xFEEF, false),
+        'ي' => (0xFEF1, true),
+        _ => return None,
+    };
+    Some(forms)
+}
 
-        match position {
-            DrawPosition::TopLeft => {
-                // Final position: top-left of photo area + margin
-                let x = photo_offset.x + margin_px;
-                let y = photo_offset.y + margin_px;
+/// The isolated form of the ligature of lam with an alef.
+fn lam_alef(alef: char) -> Option<u32> {
+    match alef {
+        'آ' => Some(0xFEF5),
+        'أ' => Some(0xFEF7),
+        'إ' => Some(0xFEF9),
+        'ا' => Some(0xFEFB),
+        _ => None,
+    }
+}
 
-                overlay_premul_rgba_on_rgb(destination, &text_img, x, y);
+/// Replaces Arabic letters with the form that joins them to their neighbours, as fonts
+/// are drawn glyph by glyph without shaping. Other text is left as it is.
+fn join_arabic(text: &str) -> String {
+    let chars: Vec<char> = text.chars().collect();
+    let joins_next = |c: char| arabic_forms(c).is_some_and(|(_, dual)| dual);
+    let mut joined = String::with_capacity(text.len());
+    let mut i = 0;
+    while i < chars.len() {
+        let c = chars[i];
+        let after_joining = i > 0 && joins_next(chars[i - 1]);
+        let Some((isolated, dual)) = arabic_forms(c) else {
+            joined.push(c);
+            i += 1;
+            continue;
+        };
+        let ligature = (c == 'ل')
+            .then(|| chars.get(i + 1).and_then(|&next| lam_alef(next)))
+            .flatten();
+        let code = match ligature {
+            Some(ligature) => {
+                i += 1;
+                ligature + after_joining as u32
             }
-            DrawPosition::BottomRight => {
-                // Paste bottom-right relative to the photo area (not the full canvas)
-                let x = photo_offset.x
-                    + photo_size
-                        .width
-                        .saturating_sub(text_img.width() + margin_px);
-                let y = photo_offset.y
-                    + photo_size
-                        .height
-                        .saturating_sub(text_img.height() + margin_px);
-                overlay_premul_rgba_on_rgb(destination, &text_img, x, y);
+            None => {
+                let before_joined = dual
+                    && chars
+                        .get(i + 1)
+                        .is_some_and(|&next| arabic_forms(next).is_some());
+                match (after_joining, before_joined) {
+                    (false, false) => isolated,
+                    (true, false) => isolated + 1,
+                    (false, true) => isolated + 2,
+                    (true, true) => isolated + 3,
+                }
             }
-        }
+        };
+        joined.push(char::from_u32(code).unwrap_or(c));
+        i += 1;
+    }
+    joined
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn codes(text: &str) -> Vec<u32> {
+        text.chars().map(|c| c as u32).collect()
+    }
+
+    #[test]
+    fn arabic_letters_take_the_form_joining_their_neighbours() {
+        // Initial, medial and final beh
+        assert_eq!(codes(&join_arabic("ببب")), [0xFE91, 0xFE92, 0xFE90]);
+        assert_eq!(codes(&join_arabic("ب")), [0xFE8F]);
+        // Dal does not join the letter after it
+        assert_eq!(codes(&join_arabic("دب")), [0xFEA9, 0xFE8F]);
+        // Waw only joins the letter before it
+        assert_eq!(codes(&join_arabic("صور")), [0xFEBB, 0xFEEE, 0xFEAD]);
+    }
+
+    #[test]
+    fn lam_and_alef_are_joined_into_a_ligature() {
+        assert_eq!(codes(&join_arabic("لا")), [0xFEFB]);
+        assert_eq!(codes(&join_arabic("بلا")), [0xFE91, 0xFEFC]);
+        assert_eq!(codes(&join_arabic("لأ")), [0xFEF7]);
+    }
+
+    #[test]
+    fn digits_and_latin_text_keep_their_order_in_right_to_left_lines() {
+        assert_eq!(join_arabic("ب 12 ab"), "\u{FE8F} 12 ab");
+        assert_eq!(visual_order("אבג 12"), "12 גבא");
+        assert_eq!(visual_order("אב abc"), "abc בא");
+        assert_eq!(visual_order("١٢ ב"), "ב ١٢");
     }
 }
@@ -3,6 +3,8 @@ use std::{io, path::PathBuf, string::FromUtf8Error};
 use ab_glyph::InvalidFont;
 use image::ImageError;
 
+use crate::numbering::NumberRange;
+
 #[derive(Debug, thiserror::Error)]
 pub enum AppError {
     #[error(transparent)]
@@ -19,8 +21,128 @@ pub enum AppError {
     Utf8Parse(#[from] FromUtf8Error),
     #[error(transparent)]
     DateTimeParse(#[from] jiff::Error),
+    #[error(transparent)]
+    Json(#[from] serde_json::Error),
+    #[error(transparent)]
+    ColorProfile(#[from] lcms2::Error),
+    #[error(transparent)]
+    Sqlite(#[from] rusqlite::Error),
+    #[error("Could not move the file to the recycle bin: {0}")]
+    Trash(#[from] trash::Error),
+    #[error("Could not decode the jpeg: {0}")]
+    Jpeg(String),
+    #[error("Could not process the pasted image: {0}")]
+    Pasted(String),
+    #[error("Could not read the zip {0:?}: {1}")]
+    Zip(PathBuf, zip::result::ZipError),
+    #[error("Could not read the places in {0:?}: {1}")]
+    Gazetteer(PathBuf, String),
+    #[error("No preview the jpeg decoders can read in the raw file {0:?}")]
+    RawPreview(PathBuf),
+    #[error("The {0} decoder is not built in. Build with the {0} feature")]
+    DecoderNotBuilt(&'static str),
+    #[error("Face detection is not built in. Build with the face-detection feature")]
+    FaceDetectionNotBuilt,
+    #[error("OCR is not built in. Build with the ocr feature")]
+    OcrNotBuilt,
+    #[error("Could not read the stamp with OCR: {0}")]
+    Ocr(String),
+    #[error("Could not open the log file: {0}")]
+    LogFile(#[from] tracing_appender::rolling::InitError),
+    #[error("Could not serve the metrics: {0}")]
+    Metrics(#[from] metrics_exporter_prometheus::BuildError),
     #[error("The file {0} could not be processed onto {1} as the numbered file already exists.")]
     OutNumberExists(PathBuf, PathBuf),
     #[error("Could not get a date from the file {0:?}")]
     NoParsibleDate(PathBuf),
+    #[error("Give at least one date source")]
+    NoDateSources,
+    #[error("Invalid config {0:?}: {1}")]
+    Config(PathBuf, String),
+    #[error("The config {0:?} already exists. Use --force to overwrite it.")]
+    ConfigExists(PathBuf),
+    #[error("Could not find the OS config directory")]
+    NoConfigDir,
+    #[error("Could not find path to temp directories")]
+    NoCacheDir,
+    #[error("Could not find the OS data directory")]
+    NoDataDir,
+    #[error("The alpha counter has no letters for 0. Start the number range {0} at 1")]
+    AlphaZero(NumberRange),
+    #[error("All the numbers in the range {0} are used. Use a new --number-range")]
+    NumberRangeExhausted(NumberRange),
+    #[error(
+        "The range {0} has {1} free number(s) left for {2} photo(s). Use a new --number-range"
+    )]
+    NumberRangeTooSmall(NumberRange, usize, usize),
+    #[error("Invalid captions file {0:?}: {1}")]
+    Captions(PathBuf, String),
+    #[error("Invalid date format {0:?}: {1}")]
+    DateFormat(String, jiff::Error),
+    #[error("Invalid name template: {0}")]
+    NameTemplate(String),
+    #[error("Invalid adjustment: {0}")]
+    Adjustment(String),
+    #[error("Invalid output {0:?}: {1}")]
+    Output(String, String),
+    #[error("Unknown font {0}")]
+    UnknownFont(String),
+    #[error(
+        "The font {0} has no Arabic or Hebrew letters and digits, pick another date font for right-to-left locales"
+    )]
+    FontLacksRtl(String),
+    #[error("The stamp scale must be more than 0, not {0}")]
+    StampScale(f32),
+    #[error("The {0} of the print must be more than 0, not {1}")]
+    PrintSize(&'static str, f32),
+    #[error("The settle time of --watch must be 0 or more seconds, not {0}")]
+    WatchSettle(f32),
+    #[error("Could not watch the sources: {0}")]
+    Watch(#[from] notify::Error),
+    #[error("Unknown preset {0}, use one of: {1}")]
+    UnknownPreset(String, String),
+    #[error("Give the source and then the target, or the target with --source")]
+    MissingPaths,
+    #[error("Could not find the Send to folder, it is only there on Windows")]
+    NoSendToDir,
+    #[error("Could not send the notification: {0}")]
+    Notify(String),
+    #[error("The {0} targets are not built in. Build with the {0} feature")]
+    TargetNotBuilt(&'static str),
+    #[error(
+        "Unknown target {0}, give a folder, an s3://bucket/prefix, an sftp://user@host/path or a davs://host/path"
+    )]
+    UnknownTarget(String),
+    #[error("Could not upload: {0}")]
+    Upload(String),
+    #[error("{0} is already stored in the target, another bench used its number")]
+    AlreadyStored(String),
+    #[error("Could not update: {0}")]
+    SelfUpdate(String),
+    #[error("The run is over the budget of the config: {0}. Use --yes to process it anyway")]
+    OverBudget(String),
+    #[error("Another run is writing the run file {0:?}")]
+    RunFileLocked(PathBuf),
+    #[error("Aborted")]
+    Aborted,
+    #[error("Verification found {0} problem(s) in the target folder")]
+    VerifyFailed(usize),
+    #[error("The source and target differ in {0} file(s)")]
+    Differences(usize),
+}
+
+impl AppError {
+    /// Whether the error is not down to the input, the config or the command line but points to
+    /// a bug in the program, so a crash report is written for it.
+    pub fn is_internal(&self) -> bool {
+        matches!(
+            self,
+            Self::Utf8Parse(_)
+                | Self::Json(_)
+                | Self::ColorProfile(_)
+                | Self::Sqlite(_)
+                | Self::Image(_)
+                | Self::Jpeg(_)
+        )
+    }
 }
@@ -0,0 +1,134 @@
+use std::cell::RefCell;
+
+use image::{imageops, DynamicImage, RgbImage};
+use rustface::{Detector, ImageData};
+
+use crate::draw_text::DrawPosition;
+
+/// A face found in a photo, in pixels of the photo.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FaceBox {
+    pub x: u32,
+    pub y: u32,
+    pub width: u32,
+    pub height: u32,
+}
+
+/// Share of the width and height of the photo a stamp is taken to cover in its corner.
+const STAMP_AREA_W: f32 = 0.45;
+const STAMP_AREA_H: f32 = 0.3;
+/// The long side faces are searched on, larger photos are scaled down for it.
+const DETECT_PX: u32 = 800;
+/// The smallest face found on the scaled down photo, smaller ones are not worth moving a stamp.
+const MIN_FACE_PX: u32 = 20;
+/// The SeetaFace frontal face model, BSD licensed, see `seeta_fd_frontal_v1.0.LICENSE`.
+const MODEL: &[u8] = include_bytes!("../assets/seeta_fd_frontal_v1.0.bin");
+
+thread_local! {
+    /// The detector of this worker, it is not shared between threads
+    static DETECTOR: RefCell<Option<Box<dyn Detector>>> = const { RefCell::new(None) };
+}
+
+/// Moves the `(date, label)` corners that would cover a face on `photo` to the nearest corner
+/// without one.
+pub fn corners_off_faces(
+    photo: &RgbImage,
+    wanted: (DrawPosition, DrawPosition),
+) -> (DrawPosition, DrawPosition) {
+    let faces = detect_faces(photo);
+    if faces.is_empty() {
+        return wanted;
+    }
+    let (width, height) = photo.dimensions();
+    let free = |corner: DrawPosition| {
+        let area = corner_area(corner, width, height);
+        !faces.iter().any(|face| overlaps(&area, face))
+    };
+    let date = alternatives(wanted.0)
+        .into_iter()
+        .find(|&c| free(c))
+        .unwrap_or(wanted.0);
+    let label = alternatives(wanted.1)
+        .into_iter()
+        .find(|&c| c != date && free(c))
+        .unwrap_or(wanted.1);
+    if (date, label) != wanted {
+        tracing::debug!("Moved the stamps to {date:?} and {label:?} to keep them off faces");
+    }
+    (date, label)
+}
+
+/// The corner itself first, then the other corners from the nearest to the furthest.
+fn alternatives(corner: DrawPosition) -> [DrawPosition; 4] {
+    use DrawPosition::*;
+    match corner {
+        TopLeft => [TopLeft, TopRight, BottomLeft, BottomRight],
+        TopRight => [TopRight, TopLeft, BottomRight, BottomLeft],
+        BottomLeft => [BottomLeft, BottomRight, TopLeft, TopRight],
+        BottomRight => [BottomRight, BottomLeft, TopRight, TopLeft],
+    }
+}
+
+/// The part of the photo a stamp in `corner` is taken to cover.
+fn corner_area(corner: DrawPosition, width: u32, height: u32) -> FaceBox {
+    let w = (width as f32 * STAMP_AREA_W) as u32;
+    let h = (height as f32 * STAMP_AREA_H) as u32;
+    let x = match corner {
+        DrawPosition::TopLeft | DrawPosition::BottomLeft => 0,
+        DrawPosition::TopRight | DrawPosition::BottomRight => width - w,
+    };
+    let y = match corner {
+        DrawPosition::TopLeft | DrawPosition::TopRight => 0,
+        DrawPosition::BottomLeft | DrawPosition::BottomRight => height - h,
+    };
+    FaceBox {
+        x,
+        y,
+        width: w,
+        height: h,
+    }
+}
+
+fn overlaps(a: &FaceBox, b: &FaceBox) -> bool {
+    a.x < b.x + b.width && b.x < a.x + a.width && a.y < b.y + b.height && b.y < a.y + a.height
+}
+
+/// Finds the faces in a photo with the frontal face model of SeetaFace, built into the program.
+/// Faces seen from the side or smaller than [`MIN_FACE_PX`] on the scaled down photo are missed.
+pub fn detect_faces(img: &RgbImage) -> Vec<FaceBox> {
+    let (w, h) = img.dimensions();
+    let scale = (DETECT_PX as f32 / w.max(h) as f32).min(1.0);
+    let (sw, sh) = (
+        ((w as f32 * scale) as u32).max(1),
+        ((h as f32 * scale) as u32).max(1),
+    );
+    let small = imageops::resize(img, sw, sh, imageops::FilterType::Triangle);
+    let gray = DynamicImage::ImageRgb8(small).to_luma8();
+
+    let faces = DETECTOR.with_borrow_mut(|detector| {
+        let detector = detector.get_or_insert_with(|| {
+            let model = rustface::read_model(MODEL).expect("The built in face model to be valid");
+            let mut detector = rustface::create_detector_with_model(model);
+            detector.set_min_face_size(MIN_FACE_PX);
+            detector.set_score_thresh(2.0);
+            detector.set_pyramid_scale_factor(0.8);
+            detector.set_slide_window_step(4, 4);
+            detector
+        });
+        detector.detect(&ImageData::new(gray.as_raw(), sw, sh))
+    });
+    faces
+        .iter()
+        .map(|face| {
+            let bbox = face.bbox();
+            // A face at the edge can reach out of the photo
+            let (x, y) = (bbox.x().max(0) as u32, bbox.y().max(0) as u32);
+            FaceBox {
+                x: (x as f32 / scale) as u32,
+                y: (y as f32 / scale) as u32,
+                width: (bbox.width() as f32 / scale) as u32,
+                height: (bbox.height() as f32 / scale) as u32,
+            }
+        })
+        .collect()
+}
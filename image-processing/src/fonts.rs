@@ -0,0 +1,182 @@
+use std::fmt;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use ab_glyph::FontArc;
+use image::{Rgb, RgbImage, Rgba};
+use tracing::debug;
+use walkdir::WalkDir;
+
+use crate::draw_text::{
+    DrawPosition, FontSize, MultilineDraw, PhotoOffset, PhotoSize, StampOffset, pt_to_px,
+};
+use crate::error::AppError;
+
+/// Names of the fonts bundled with the program.
+pub const BUNDLED_FONTS: [&str; 3] = ["arial-rounded-bold", "arial-bold", "arial-regular"];
+
+fn bundled_font_data(name: &str) -> Option<&'static [u8]> {
+    // Bundle the fonts with the program so it works the same on Ubuntu + Windows.
+    match name {
+        "arial-rounded-bold" => Some(include_bytes!("../assets/arialroundedmtbold.ttf")),
+        "arial-bold" => Some(include_bytes!("../assets/ARIALBD.TTF")),
+        "arial-regular" => Some(include_bytes!("../assets/Arial Regular.ttf")),
+        _ => None,
+    }
+}
+
+/// A font installed on the system.
+#[derive(Debug, Clone)]
+pub struct SystemFont {
+    pub family: String,
+    /// e.g. Regular or Bold Italic
+    pub style: String,
+    pub path: PathBuf,
+}
+
+impl SystemFont {
+    /// The name to use in the config to select this font.
+    pub fn full_name(&self) -> String {
+        format!("{} {}", self.family, self.style)
+    }
+}
+
+impl fmt::Display for SystemFont {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} ({})", self.full_name(), self.path.display())
+    }
+}
+
+/// Loads a font by name. The name can be one of the `BUNDLED_FONTS`, a path to a font file
+/// or the name of a system font, e.g. `DejaVu Sans` or `DejaVu Sans Bold`.
+pub fn load_font(name: &str) -> Result<FontArc, AppError> {
+    if let Some(data) = bundled_font_data(name) {
+        return Ok(FontArc::try_from_slice(data)?);
+    }
+
+    let path = Path::new(name);
+    if path.is_file() {
+        return Ok(FontArc::try_from_vec(fs::read(path)?)?);
+    }
+
+    let Some(font) = find_system_font(name) else {
+        return Err(AppError::UnknownFont(name.to_string()));
+    };
+    debug!("Using system font {font}");
+    Ok(FontArc::try_from_vec(fs::read(&font.path)?)?)
+}
+
+/// Finds a system font by family name, preferring the regular style, or by family and style.
+fn find_system_font(name: &str) -> Option<SystemFont> {
+    let fonts = system_fonts();
+    let family: Vec<&SystemFont> = fonts
+        .iter()
+        .filter(|f| f.family.eq_ignore_ascii_case(name))
+        .collect();
+    let regular = family
+        .iter()
+        .find(|f| f.style.eq_ignore_ascii_case("regular"));
+    if let Some(font) = regular.or(family.first()) {
+        return Some((*font).clone());
+    }
+    fonts
+        .into_iter()
+        .find(|f| f.full_name().eq_ignore_ascii_case(name))
+}
+
+fn system_font_dirs() -> Vec<PathBuf> {
+    let mut dirs = vec![];
+    if cfg!(target_os = "windows") {
+        let windir = std::env::var_os("WINDIR").unwrap_or_else(|| "C:\\Windows".into());
+        dirs.push(PathBuf::from(windir).join("Fonts"));
+        if let Some(local) = std::env::var_os("LOCALAPPDATA") {
+            dirs.push(PathBuf::from(local).join("Microsoft\\Windows\\Fonts"));
+        }
+    } else if cfg!(target_os = "macos") {
+        dirs.push("/System/Library/Fonts".into());
+        dirs.push("/Library/Fonts".into());
+    } else {
+        dirs.push("/usr/share/fonts".into());
+        dirs.push("/usr/local/share/fonts".into());
+    }
+    if let Some(home) = directories::BaseDirs::new().map(|d| d.home_dir().to_path_buf()) {
+        if cfg!(target_os = "macos") {
+            dirs.push(home.join("Library/Fonts"));
+        } else if !cfg!(target_os = "windows") {
+            dirs.push(home.join(".local/share/fonts"));
+            dirs.push(home.join(".fonts"));
+        }
+    }
+    dirs
+}
+
+/// Lists the fonts installed on the system that the text renderer can use.
+pub fn system_fonts() -> Vec<SystemFont> {
+    let mut fonts = vec![];
+    for dir in system_font_dirs() {
+        for entry in WalkDir::new(dir).into_iter().filter_map(|e| e.ok()) {
+            let path = entry.path();
+            let is_font = path
+                .extension()
+                .and_then(|x| x.to_str())
+                .is_some_and(|x| x.eq_ignore_ascii_case("ttf") || x.eq_ignore_ascii_case("otf"));
+            if !entry.file_type().is_file() || !is_font {
+                continue;
+            }
+            match read_font_names(path) {
+                Some((family, style)) => fonts.push(SystemFont {
+                    family,
+                    style,
+                    path: path.to_path_buf(),
+                }),
+                None => debug!("Could not read the font names of {path:?}"),
+            }
+        }
+    }
+    fonts.sort_by_key(|f| f.full_name());
+    fonts
+}
+
+fn read_font_names(path: &Path) -> Option<(String, String)> {
+    let data = fs::read(path).ok()?;
+    let face = ttf_parser::Face::parse(&data, 0).ok()?;
+    let name = |id: u16| {
+        face.names()
+            .into_iter()
+            .filter(|n| n.name_id == id)
+            .find_map(|n| n.to_string())
+    };
+    let family = name(ttf_parser::name_id::FAMILY)?;
+    let style = name(ttf_parser::name_id::SUBFAMILY).unwrap_or_else(|| "Regular".to_string());
+    Some((family, style))
+}
+
+/// Renders `text` in the font at its printed size onto a white image and saves it as a png.
+pub fn render_preview(
+    font_name: &str,
+    text: &str,
+    font_size: FontSize,
+    color: Rgba<u8>,
+    out: &Path,
+) -> Result<(), AppError> {
+    let font = load_font(font_name)?;
+    let margin_px = 20;
+    let line_px = pt_to_px(font_size.pt, font_size.dpi).ceil() as u32;
+    let photo_size = PhotoSize {
+        width: 2000,
+        height: line_px * 2 + margin_px * 2,
+    };
+    let mut img = RgbImage::from_pixel(photo_size.width, photo_size.height, Rgb([255, 255, 255]));
+    let mut draw = MultilineDraw {
+        photo_size,
+        photo_offset: PhotoOffset { x: 0, y: 0 },
+        margin_px,
+        safe_area_px: 0,
+        offset_px: StampOffset::default(),
+        destination: &mut img,
+        rtl: false,
+    };
+    draw.draw_multiline_text(&[text], &font, font_size, color, DrawPosition::TopLeft);
+    img.save(out)?;
+    Ok(())
+}
@@ -0,0 +1,186 @@
+use std::collections::HashMap;
+use std::fs;
+use std::path::Path;
+
+use crate::error::AppError;
+use crate::metadata::GpsPosition;
+
+/// Cities bundled with the program, so places are found without network access.
+const BUNDLED: &str = include_str!("../assets/gazetteer.csv");
+/// Radius of the earth in km.
+const EARTH_KM: f64 = 6371.0;
+
+/// Turns where a photo was taken into the name of the place, e.g. `Cape Town, South Africa`.
+pub trait Geocoder: Send + Sync {
+    fn place(&self, position: &GpsPosition) -> Option<String>;
+}
+
+struct Place {
+    name: String,
+    country: String,
+    latitude: f64,
+    longitude: f64,
+}
+
+/// A list of places the nearest one is looked up in, offline.
+pub struct Gazetteer {
+    places: Vec<Place>,
+    /// Photos further than this from every place get no name
+    max_km: f64,
+}
+
+impl Gazetteer {
+    /// The cities bundled with the program, the capitals and the biggest cities of most
+    /// countries.
+    pub fn bundled(max_km: f64) -> Self {
+        let (places, _) = parse_bundled(BUNDLED);
+        Self { places, max_km }
+    }
+
+    /// The places in `path`: a csv with `name,country_code,country,latitude,longitude` like the
+    /// bundled one, or a GeoNames dump like `cities15000.txt`, whose countries are named after
+    /// the bundled ones.
+    pub fn load(path: &Path, max_km: f64) -> Result<Self, AppError> {
+        let error = |reason: String| AppError::Gazetteer(path.to_path_buf(), reason);
+        let text = fs::read_to_string(path).map_err(|e| error(e.to_string()))?;
+        let places = match text.lines().next().is_some_and(|line| line.contains('\t')) {
+            true => {
+                let (_, countries) = parse_bundled(BUNDLED);
+                parse_geonames(&text, &countries)
+            }
+            false => parse_bundled(&text).0,
+        };
+        if places.is_empty() {
+            return Err(error("it has no places".into()));
+        }
+        Ok(Self { places, max_km })
+    }
+}
+
+impl Geocoder for Gazetteer {
+    fn place(&self, position: &GpsPosition) -> Option<String> {
+        let (place, km) = self
+            .places
+            .iter()
+            .map(|place| (place, distance_km(position, place)))
+            .min_by(|a, b| a.1.total_cmp(&b.1))?;
+        (km <= self.max_km).then(|| format!("{}, {}", place.name, place.country))
+    }
+}
+
+/// The places of a csv like the bundled one, and the country names by their code.
+fn parse_bundled(text: &str) -> (Vec<Place>, HashMap<String, String>) {
+    let mut places = vec![];
+    let mut countries = HashMap::new();
+    for line in text.lines().skip(1) {
+        let fields: Vec<&str> = line.split(',').map(str::trim).collect();
+        let [name, code, country, latitude, longitude] = fields[..] else {
+            continue;
+        };
+        let (Ok(latitude), Ok(longitude)) = (latitude.parse(), longitude.parse()) else {
+            continue;
+        };
+        countries.insert(code.to_string(), country.to_string());
+        places.push(Place {
+            name: name.to_string(),
+            country: country.to_string(),
+            latitude,
+            longitude,
+        });
+    }
+    (places, countries)
+}
+
+/// The places of a GeoNames dump: the name is the 2nd column, the latitude and longitude the
+/// 5th and 6th and the country code the 9th.
+fn parse_geonames(text: &str, countries: &HashMap<String, String>) -> Vec<Place> {
+    text.lines()
+        .filter_map(|line| {
+            let fields: Vec<&str> = line.split('\t').collect();
+            let code = *fields.get(8)?;
+            Some(Place {
+                name: fields.get(1)?.to_string(),
+                country: countries
+                    .get(code)
+                    .cloned()
+                    .unwrap_or_else(|| code.to_string()),
+                latitude: fields.get(4)?.parse().ok()?,
+                longitude: fields.get(5)?.parse().ok()?,
+            })
+        })
+        .collect()
+}
+
+/// The great circle distance between a photo and a place.
+fn distance_km(position: &GpsPosition, place: &Place) -> f64 {
+    let (lat1, lat2) = (position.latitude.to_radians(), place.latitude.to_radians());
+    let dlat = lat2 - lat1;
+    let dlon = (place.longitude - position.longitude).to_radians();
+    let a = (dlat / 2.0).sin().powi(2) + lat1.cos() * lat2.cos() * (dlon / 2.0).sin().powi(2);
+    2.0 * EARTH_KM * a.sqrt().asin()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::temp_dir;
+
+    /// The V&A Waterfront, a few km from the centre of Cape Town.
+    const WATERFRONT: GpsPosition = GpsPosition {
+        latitude: -33.903,
+        longitude: 18.420,
+    };
+
+    #[test]
+    fn photos_get_the_name_of_the_nearest_city() {
+        let gazetteer = Gazetteer::bundled(50.0);
+        assert_eq!(
+            gazetteer.place(&WATERFRONT).as_deref(),
+            Some("Cape Town, South Africa")
+        );
+        // In the South Atlantic, far from every city
+        let ocean = GpsPosition {
+            latitude: -40.0,
+            longitude: -10.0,
+        };
+        assert_eq!(gazetteer.place(&ocean), None);
+        assert_eq!(Gazetteer::bundled(1.0).place(&WATERFRONT), None);
+    }
+
+    #[test]
+    fn distances_are_along_the_earth() {
+        let johannesburg = Place {
+            name: "Johannesburg".into(),
+            country: "South Africa".into(),
+            latitude: -26.204,
+            longitude: 28.047,
+        };
+        let km = distance_km(&WATERFRONT, &johannesburg);
+        assert!((1250.0..1280.0).contains(&km), "{km}");
+    }
+
+    #[test]
+    fn geonames_dumps_are_read_with_the_bundled_country_names() {
+        let dir = temp_dir("geonames");
+        let path = dir.path().join("cities15000.txt");
+        fs::write(
+            &path,
+            "3369157\tCape Town\tCape Town\tKaapstad\t-33.92584\t18.42322\tP\tPPLA\tZA\t\t11\n\
+             2643743\tLondon\tLondon\t\t51.50853\t-0.12574\tP\tPPLC\tXX\t\tENG\n",
+        )
+        .unwrap();
+
+        let gazetteer = Gazetteer::load(&path, 50.0).unwrap();
+        assert_eq!(
+            gazetteer.place(&WATERFRONT).as_deref(),
+            Some("Cape Town, South Africa")
+        );
+        let london = GpsPosition {
+            latitude: 51.5,
+            longitude: -0.1,
+        };
+        assert_eq!(gazetteer.place(&london).as_deref(), Some("London, XX"));
+        fs::write(&path, "").unwrap();
+        assert!(Gazetteer::load(&path, 50.0).is_err());
+    }
+}
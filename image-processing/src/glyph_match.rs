@@ -0,0 +1,373 @@
+use ab_glyph::{FontArc, PxScale};
+use image::{Rgb, RgbImage};
+use imageproc::drawing::draw_text_mut;
+
+use crate::draw_text::DrawPosition;
+
+/// Every glyph is compared on a grid of this many cells across and down.
+const GRID_W: usize = 10;
+const GRID_H: usize = 14;
+/// How far, in RGB, a pixel may be from the stamp color and still count as part of the text.
+/// Only the middle of the strokes has to match, the anti-aliased edges are left out.
+const INK_DISTANCE: i32 = 48;
+/// How many lines high the strip along the edge is that the stamps are searched in.
+const STAMP_LINES: f32 = 8.0;
+/// Glyphs further than this from every template are not text.
+const MAX_DISTANCE: f32 = 0.4;
+
+/// Reads the text of stamps drawn in a known font and color, by comparing every glyph with
+/// the same characters rendered in the font. This is not OCR: only glyphs shaped like the ones
+/// of the font are read, and text of another size or color is left out.
+pub struct GlyphMatcher {
+    templates: Vec<(char, Glyph)>,
+    color: Rgb<u8>,
+    line_px: f32,
+}
+
+/// A glyph scaled onto the comparison grid, with how much ink every cell has.
+struct Glyph {
+    cells: [f32; GRID_W * GRID_H],
+    /// Width over height, tells `1` from `7` when the grid looks alike
+    aspect: f32,
+}
+
+impl GlyphMatcher {
+    /// A reader for the characters in `chars`, drawn in `font` and `color` with a size of
+    /// `line_px`. The templates are drawn the same way as the stamps, so the anti-aliased
+    /// edges come out the same.
+    pub fn new(font: &FontArc, chars: &str, line_px: f32, color: Rgb<u8>) -> Self {
+        // On black or white, whichever the color stands out more on
+        let background = match color.0.iter().map(|&c| c as u32).sum::<u32>() > 382 {
+            true => Rgb([0, 0, 0]),
+            false => Rgb([255, 255, 255]),
+        };
+        let size = (line_px * 2.0).ceil() as u32;
+        let mut templates = vec![];
+        for c in chars.chars().filter(|c| !c.is_whitespace()) {
+            let mut img = RgbImage::from_pixel(size, size, background);
+            let text = c.to_string();
+            draw_text_mut(&mut img, color, 0, 0, PxScale::from(line_px), font, &text);
+            let points: Vec<(u32, u32)> = img
+                .enumerate_pixels()
+                .filter(|(_, _, p)| is_ink(p, color))
+                .map(|(x, y, _)| (x, y))
+                .collect();
+            if let Some(glyph) = Glyph::from_points(&points) {
+                templates.push((c, glyph));
+            }
+        }
+        Self {
+            templates,
+            color,
+            line_px,
+        }
+    }
+
+    /// Reads the lines of the stamp in the `corner` of the photo from the top down, without
+    /// spaces. Other things in the photo in the
+    /// same color can come out as extra lines, so the caller has to check what it reads.
+    pub fn read_corner(&self, img: &RgbImage, corner: DrawPosition) -> Vec<String> {
+        let line_px = self.line_px;
+        let (w, h) = img.dimensions();
+        // The stamps are a few lines near the edge, in the half of the photo at their corner
+        let strip = ((line_px * STAMP_LINES) as u32).min(h);
+        let x0 = match corner {
+            DrawPosition::TopLeft | DrawPosition::BottomLeft => 0,
+            DrawPosition::TopRight | DrawPosition::BottomRight => w / 2,
+        };
+        let y0 = match corner {
+            DrawPosition::TopLeft | DrawPosition::TopRight => 0,
+            DrawPosition::BottomLeft | DrawPosition::BottomRight => h - strip,
+        };
+        let (width, height) = (w - w / 2, strip);
+        let mut ink = vec![false; (width * height) as usize];
+        for y in 0..height {
+            for x in 0..width {
+                ink[(y * width + x) as usize] = is_ink(img.get_pixel(x0 + x, y0 + y), self.color);
+            }
+        }
+
+        // Patches of the color much larger or smaller than a letter are not part of the text
+        let blobs = Blob::find(&mut ink, width, height);
+        let (letters, dots): (Vec<Blob>, Vec<Blob>) = blobs
+            .into_iter()
+            .filter(|b| b.height() as f32 <= line_px && b.width() as f32 <= line_px * 1.2)
+            .partition(|b| b.height() as f32 >= line_px * 0.3);
+        let mut dots: Vec<Option<Blob>> = dots.into_iter().map(Some).collect();
+
+        // Letters next to each other, with the dots between them, make a line
+        let mut letters: Vec<Option<Blob>> = letters.into_iter().map(Some).collect();
+        letters.sort_by_key(|b| b.as_ref().map(|b| b.top));
+        let mut lines = vec![];
+        while let Some(first) = letters.iter_mut().find_map(Option::take) {
+            let middle = (first.top + first.bottom) / 2;
+            let mut line = vec![first];
+            for slot in letters.iter_mut() {
+                if slot
+                    .as_ref()
+                    .is_some_and(|b| b.top <= middle && b.bottom >= middle)
+                {
+                    line.extend(slot.take());
+                }
+            }
+            let top = line.iter().map(|b| b.top).min().unwrap_or_default();
+            let bottom = line.iter().map(|b| b.bottom).max().unwrap_or_default();
+            for slot in dots.iter_mut() {
+                if slot
+                    .as_ref()
+                    .is_some_and(|b| b.top >= top && b.bottom <= bottom)
+                {
+                    line.extend(slot.take());
+                }
+            }
+            let text = self.read_line(line);
+            if !text.is_empty() {
+                lines.push(text);
+            }
+        }
+        lines
+    }
+
+    /// Reads the letters of a line from left to right. Blobs above each other, like the dots
+    /// of a colon, are one letter.
+    fn read_line(&self, mut blobs: Vec<Blob>) -> String {
+        blobs.sort_by_key(|b| b.left);
+        let mut letters: Vec<Blob> = vec![];
+        for blob in blobs {
+            match letters.last_mut() {
+                Some(last) if blob.left <= last.right => last.merge(blob),
+                _ => letters.push(blob),
+            }
+        }
+        letters
+            .iter()
+            .filter_map(|letter| self.closest(&Glyph::from_points(&letter.points)?))
+            .collect()
+    }
+
+    fn closest(&self, glyph: &Glyph) -> Option<char> {
+        self.templates
+            .iter()
+            .map(|(c, template)| (*c, template.distance(glyph)))
+            .filter(|(_, distance)| *distance <= MAX_DISTANCE)
+            .min_by(|a, b| a.1.total_cmp(&b.1))
+            .map(|(c, _)| c)
+    }
+}
+
+impl Glyph {
+    /// Scales the ink onto the grid, cropped to the ink.
+    fn from_points(points: &[(u32, u32)]) -> Option<Self> {
+        let min_x = points.iter().map(|p| p.0).min()?;
+        let max_x = points.iter().map(|p| p.0).max()?;
+        let min_y = points.iter().map(|p| p.1).min()?;
+        let max_y = points.iter().map(|p| p.1).max()?;
+        let (w, h) = (max_x - min_x + 1, max_y - min_y + 1);
+
+        let mut cells = [0f32; GRID_W * GRID_H];
+        let mut counts = [0f32; GRID_W * GRID_H];
+        for y in 0..h {
+            for x in 0..w {
+                let cell =
+                    (y as usize * GRID_H / h as usize) * GRID_W + x as usize * GRID_W / w as usize;
+                counts[cell] += 1.0;
+            }
+        }
+        for &(x, y) in points {
+            let (x, y) = (x - min_x, y - min_y);
+            let cell =
+                (y as usize * GRID_H / h as usize) * GRID_W + x as usize * GRID_W / w as usize;
+            cells[cell] += 1.0;
+        }
+        for (cell, count) in cells.iter_mut().zip(counts) {
+            if count > 0.0 {
+                *cell /= count;
+            }
+        }
+        Some(Self {
+            cells,
+            aspect: w as f32 / h as f32,
+        })
+    }
+
+    fn distance(&self, other: &Glyph) -> f32 {
+        let cells: f32 = self
+            .cells
+            .iter()
+            .zip(other.cells)
+            .map(|(a, b)| (a - b).abs())
+            .sum::<f32>()
+            / self.cells.len() as f32;
+        cells + (self.aspect.ln() - other.aspect.ln()).abs() * 0.25
+    }
+}
+
+/// Whether the pixel is close enough to the stamp color to be part of the text.
+fn is_ink(pixel: &Rgb<u8>, color: Rgb<u8>) -> bool {
+    let distance: i32 = (0..3)
+        .map(|i| (pixel[i] as i32 - color[i] as i32).pow(2))
+        .sum();
+    distance < INK_DISTANCE * INK_DISTANCE
+}
+
+/// Pixels of the stamp color that touch each other.
+struct Blob {
+    points: Vec<(u32, u32)>,
+    left: u32,
+    right: u32,
+    top: u32,
+    bottom: u32,
+}
+
+impl Blob {
+    /// Splits the ink into blobs, clearing it on the way.
+    fn find(ink: &mut [bool], width: u32, height: u32) -> Vec<Blob> {
+        let mut blobs = vec![];
+        for start in 0..ink.len() {
+            if !ink[start] {
+                continue;
+            }
+            ink[start] = false;
+            let mut stack = vec![start];
+            let mut points = vec![];
+            while let Some(i) = stack.pop() {
+                let (x, y) = (i as u32 % width, i as u32 / width);
+                points.push((x, y));
+                for ny in y.saturating_sub(1)..(y + 2).min(height) {
+                    for nx in x.saturating_sub(1)..(x + 2).min(width) {
+                        let n = (ny * width + nx) as usize;
+                        if ink[n] {
+                            ink[n] = false;
+                            stack.push(n);
+                        }
+                    }
+                }
+            }
+            let mut blob = Blob {
+                left: u32::MAX,
+                right: 0,
+                top: u32::MAX,
+                bottom: 0,
+                points: vec![],
+            };
+            for &(x, y) in &points {
+                blob.left = blob.left.min(x);
+                blob.right = blob.right.max(x);
+                blob.top = blob.top.min(y);
+                blob.bottom = blob.bottom.max(y);
+            }
+            blob.points = points;
+            blobs.push(blob);
+        }
+        blobs
+    }
+
+    fn width(&self) -> u32 {
+        self.right - self.left + 1
+    }
+
+    fn height(&self) -> u32 {
+        self.bottom - self.top + 1
+    }
+
+    fn merge(&mut self, other: Blob) {
+        self.left = self.left.min(other.left);
+        self.right = self.right.max(other.right);
+        self.top = self.top.min(other.top);
+        self.bottom = self.bottom.max(other.bottom);
+        self.points.extend(other.points);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fonts::load_font;
+
+    const LINE_PX: f32 = 40.0;
+    const RED: Rgb<u8> = Rgb([220, 30, 30]);
+
+    /// A grey photo with `lines` drawn in `font` and `color` near the `corner`.
+    fn stamped(font: &FontArc, lines: &[&str], corner: DrawPosition, color: Rgb<u8>) -> RgbImage {
+        let (w, h) = (800, 600);
+        let mut img = RgbImage::from_pixel(w, h, Rgb([128, 128, 128]));
+        let scale = PxScale::from(LINE_PX);
+        let top = match corner {
+            DrawPosition::TopLeft | DrawPosition::TopRight => 10,
+            DrawPosition::BottomLeft | DrawPosition::BottomRight => {
+                h as i32 - 10 - (lines.len() as f32 * LINE_PX * 1.5) as i32
+            }
+        };
+        for (i, line) in lines.iter().enumerate() {
+            let x = match corner {
+                DrawPosition::TopLeft | DrawPosition::BottomLeft => 10,
+                DrawPosition::TopRight | DrawPosition::BottomRight => w as i32 - 300,
+            };
+            let y = top + (i as f32 * LINE_PX * 1.5) as i32;
+            draw_text_mut(&mut img, color, x, y, scale, font, line);
+        }
+        img
+    }
+
+    #[test]
+    fn reads_the_lines_of_a_stamp_in_its_font() {
+        let font = load_font("arial-bold").unwrap();
+        let reader = GlyphMatcher::new(&font, "FotoNr.0123456789", LINE_PX, RED);
+        let img = stamped(&font, &["Foto Nr. 1207"], DrawPosition::BottomRight, RED);
+
+        let lines = reader.read_corner(&img, DrawPosition::BottomRight);
+        assert_eq!(lines, ["FotoNr.1207"]);
+    }
+
+    #[test]
+    fn reads_the_lines_from_the_top_down() {
+        let font = load_font("arial-regular").unwrap();
+        let reader = GlyphMatcher::new(&font, "0123456789.:", LINE_PX, RED);
+        let img = stamped(&font, &["24.12.2025", "18:30"], DrawPosition::TopLeft, RED);
+
+        let lines = reader.read_corner(&img, DrawPosition::TopLeft);
+        assert_eq!(lines, ["24.12.2025", "18:30"]);
+    }
+
+    #[test]
+    fn only_reads_the_corner_it_is_given() {
+        let font = load_font("arial-bold").unwrap();
+        let reader = GlyphMatcher::new(&font, "0123456789", LINE_PX, RED);
+        let img = stamped(&font, &["42"], DrawPosition::TopRight, RED);
+
+        assert_eq!(reader.read_corner(&img, DrawPosition::TopRight), ["42"]);
+        assert!(reader.read_corner(&img, DrawPosition::TopLeft).is_empty());
+        assert!(reader
+            .read_corner(&img, DrawPosition::BottomRight)
+            .is_empty());
+    }
+
+    #[test]
+    fn does_not_read_a_stamp_of_another_size() {
+        let font = load_font("arial-bold").unwrap();
+        let reader = GlyphMatcher::new(&font, "0123456789", LINE_PX / 2.0, RED);
+        let img = stamped(&font, &["42"], DrawPosition::TopLeft, RED);
+
+        assert!(reader.read_corner(&img, DrawPosition::TopLeft).is_empty());
+    }
+
+    #[test]
+    fn does_not_read_text_in_another_color() {
+        let font = load_font("arial-bold").unwrap();
+        let reader = GlyphMatcher::new(&font, "0123456789", LINE_PX, RED);
+        let img = stamped(&font, &["42"], DrawPosition::TopLeft, Rgb([255, 255, 255]));
+
+        assert!(reader.read_corner(&img, DrawPosition::TopLeft).is_empty());
+    }
+
+    #[test]
+    fn does_not_read_a_photo_smaller_than_the_stamp() {
+        let font = load_font("arial-bold").unwrap();
+        let reader = GlyphMatcher::new(&font, "0123456789", LINE_PX, RED);
+        let img = RgbImage::from_pixel(3, 2, RED);
+
+        assert!(reader
+            .read_corner(&img, DrawPosition::BottomRight)
+            .is_empty());
+    }
+}
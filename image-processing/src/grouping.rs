@@ -0,0 +1,249 @@
+use std::collections::HashMap;
+use std::fs;
+use std::num::NonZeroUsize;
+use std::path::{Path, PathBuf};
+
+use jiff::civil::DateTime;
+use serde::{Deserialize, Serialize};
+use tracing::debug;
+
+use crate::error::AppError;
+use crate::{image_ops, metadata};
+
+/// How the output photos are grouped into folders in the target.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize, clap::ValueEnum)]
+#[serde(rename_all = "kebab-case")]
+pub enum GroupBy {
+    /// A folder per day, e.g. `20251224`
+    #[default]
+    Day,
+    /// A folder per month, e.g. `202512`
+    Month,
+    /// A folder per year, e.g. `2025`
+    Year,
+    /// A folder per camera model from the EXIF data of the original
+    Camera,
+    /// All photos directly in the target
+    None,
+}
+
+/// Folder name used for photos without a camera model in their EXIF data.
+const UNKNOWN_CAMERA: &str = "unknown-camera";
+
+impl GroupBy {
+    /// The folder in the target the output of `source` goes into.
+    pub fn out_dir(&self, target: &Path, date: &DateTime, source: &Path) -> PathBuf {
+        match self {
+            GroupBy::Day => target.join(date.strftime("%Y%m%d").to_string()),
+            GroupBy::Month => target.join(date.strftime("%Y%m").to_string()),
+            GroupBy::Year => target.join(date.strftime("%Y").to_string()),
+            GroupBy::Camera => {
+                let camera = match metadata::get_camera_model(source) {
+                    Ok(camera) => camera,
+                    Err(e) => {
+                        debug!("{e}. Could not read the camera model of {source:?}");
+                        None
+                    }
+                };
+                let folder = camera
+                    .map(|c| sanitize_folder_name(&c))
+                    .filter(|c| !c.is_empty())
+                    .unwrap_or_else(|| UNKNOWN_CAMERA.to_string());
+                target.join(folder)
+            }
+            GroupBy::None => target.to_path_buf(),
+        }
+    }
+}
+
+/// Part of the day a photo was taken in, for the subfolders of `split_day_night`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DayPeriod {
+    /// 05:00 to 12:00
+    Morning,
+    /// 12:00 to 17:00
+    Afternoon,
+    /// 17:00 to 22:00
+    Evening,
+    /// 22:00 to 05:00
+    Night,
+}
+
+/// Folder name used for photos without a time in their EXIF data.
+const UNKNOWN_TIME: &str = "unknown-time";
+
+impl DayPeriod {
+    pub fn of(date: &DateTime) -> Self {
+        match date.hour() {
+            5..12 => DayPeriod::Morning,
+            12..17 => DayPeriod::Afternoon,
+            17..22 => DayPeriod::Evening,
+            _ => DayPeriod::Night,
+        }
+    }
+
+    pub fn folder_name(&self) -> &'static str {
+        match self {
+            DayPeriod::Morning => "morning",
+            DayPeriod::Afternoon => "afternoon",
+            DayPeriod::Evening => "evening",
+            DayPeriod::Night => "night",
+        }
+    }
+
+    /// The subfolder of `out_dir` for the part of the day `source` was taken in, by the time in
+    /// its EXIF data. Dates from file names have no time, so those photos go into a folder of
+    /// their own.
+    pub fn out_dir(out_dir: &Path, source: &Path) -> PathBuf {
+        let date = match metadata::get_image_date(source) {
+            Ok(date) => date,
+            Err(e) => {
+                debug!("{e}. Could not read the time of {source:?}");
+                None
+            }
+        };
+        let folder = date
+            .map(|date| DayPeriod::of(&date).folder_name())
+            .unwrap_or(UNKNOWN_TIME);
+        out_dir.join(folder)
+    }
+}
+
+/// Splits output folders into parts of at most `max` photos,
+/// e.g. `20251224_part1`, `20251224_part2`.
+#[derive(Debug)]
+pub struct FolderSplitter {
+    max: NonZeroUsize,
+    /// The current part and the number of photos in it per output folder
+    parts: HashMap<PathBuf, (usize, usize)>,
+}
+
+impl FolderSplitter {
+    pub fn new(max: NonZeroUsize) -> Self {
+        Self {
+            max,
+            parts: HashMap::new(),
+        }
+    }
+
+    /// The part of `out_dir` the next photo goes into.
+    pub fn next(&mut self, target: &Path, out_dir: &Path) -> Result<PathBuf, AppError> {
+        let max = self.max.get();
+        let (part, count) = match self.parts.get(out_dir) {
+            Some(&(part, count)) if count < max => (part, count),
+            Some(&(part, _)) => (part + 1, 0),
+            // Continue filling the parts of an earlier run
+            None => {
+                let mut part = 1;
+                loop {
+                    let count = count_images(&part_dir(target, out_dir, part))?;
+                    if count < max {
+                        break (part, count);
+                    }
+                    part += 1;
+                }
+            }
+        };
+        self.parts.insert(out_dir.to_path_buf(), (part, count + 1));
+        Ok(part_dir(target, out_dir, part))
+    }
+}
+
+/// The folder of a part. Without date folders the parts are folders in the target.
+fn part_dir(target: &Path, out_dir: &Path, part: usize) -> PathBuf {
+    match out_dir.file_name().and_then(|x| x.to_str()) {
+        Some(name) if out_dir != target => out_dir.with_file_name(format!("{name}_part{part}")),
+        _ => out_dir.join(format!("part{part}")),
+    }
+}
+
+fn count_images(dir: &Path) -> Result<usize, AppError> {
+    if !dir.exists() {
+        return Ok(0);
+    }
+    let mut count = 0;
+    for entry in fs::read_dir(dir)? {
+        let path = entry?.path();
+        if path.is_file() && image_ops::is_image_file(&path) {
+            count += 1;
+        }
+    }
+    Ok(count)
+}
+
+/// Replaces the characters that are not allowed in folder names on Windows.
+fn sanitize_folder_name(name: &str) -> String {
+    name.chars()
+        .map(|c| match c {
+            '<' | '>' | ':' | '"' | '/' | '\\' | '|' | '?' | '*' => '_',
+            c if c.is_control() => '_',
+            c => c,
+        })
+        .collect::<String>()
+        .trim_end_matches(['.', ' '])
+        .to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::temp_dir;
+
+    #[test]
+    fn folders_are_split_into_parts_of_at_most_max_photos() {
+        let dir = temp_dir("split-parts");
+        let target = dir.path();
+        let day = target.join("20251224");
+        let mut splitter = FolderSplitter::new(NonZeroUsize::new(2).unwrap());
+
+        let parts: Vec<PathBuf> = (0..3)
+            .map(|_| splitter.next(target, &day).unwrap())
+            .collect();
+        let (part1, part2) = (target.join("20251224_part1"), target.join("20251224_part2"));
+        assert_eq!(parts, [part1.clone(), part1, part2]);
+    }
+
+    #[test]
+    fn parts_of_an_earlier_run_are_filled_up() {
+        let dir = temp_dir("split-earlier");
+        let target = dir.path();
+        let part1 = target.join("20251224_part1");
+        let part2 = target.join("20251224_part2");
+        fs::create_dir_all(&part1).unwrap();
+        fs::create_dir_all(&part2).unwrap();
+        fs::write(part1.join("1.jpg"), b"").unwrap();
+        fs::write(part1.join("2.jpg"), b"").unwrap();
+        fs::write(part2.join("3.jpg"), b"").unwrap();
+        // Not a photo, so it leaves room in the part
+        fs::write(part2.join("3.json"), b"").unwrap();
+        let mut splitter = FolderSplitter::new(NonZeroUsize::new(2).unwrap());
+
+        let day = target.join("20251224");
+        assert_eq!(splitter.next(target, &day).unwrap(), part2);
+        assert_eq!(
+            splitter.next(target, &day).unwrap(),
+            target.join("20251224_part3")
+        );
+    }
+
+    #[test]
+    fn without_date_folders_the_parts_are_in_the_target() {
+        let dir = temp_dir("split-no-group");
+        let target = dir.path();
+        let out_dir = GroupBy::None.out_dir(
+            target,
+            &jiff::civil::date(2025, 12, 24).at(10, 15, 0, 0),
+            Path::new("IMG_0001.jpg"),
+        );
+        let mut splitter = FolderSplitter::new(NonZeroUsize::new(1).unwrap());
+
+        assert_eq!(
+            splitter.next(target, &out_dir).unwrap(),
+            target.join("part1")
+        );
+        assert_eq!(
+            splitter.next(target, &out_dir).unwrap(),
+            target.join("part2")
+        );
+    }
+}
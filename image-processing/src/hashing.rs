@@ -0,0 +1,32 @@
+use std::fs::File;
+use std::io::{self, BufReader};
+use std::path::Path;
+
+use sha2::{Digest, Sha256};
+
+use crate::error::AppError;
+
+/// Hex characters of the checksum stamped with the number, 32 bits.
+const SHORT_ID_LEN: usize = 8;
+
+/// SHA-256 of the file contents as a lowercase hex string.
+pub fn sha256_file(path: &Path) -> Result<String, AppError> {
+    let mut reader = BufReader::new(File::open(path)?);
+    let mut hasher = Sha256::new();
+    io::copy(&mut reader, &mut hasher)?;
+    Ok(to_hex(&hasher.finalize()))
+}
+
+/// SHA-256 of bytes in memory as a lowercase hex string.
+pub fn sha256_bytes(bytes: &[u8]) -> String {
+    to_hex(&Sha256::digest(bytes))
+}
+
+/// The first characters of a SHA-256 hex string, short enough to stamp on a print.
+pub fn short_id(sha256: &str) -> &str {
+    &sha256[..SHORT_ID_LEN.min(sha256.len())]
+}
+
+fn to_hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| format!("{b:02x}")).collect()
+}
@@ -0,0 +1,87 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use ab_glyph::FontArc;
+use image::{Rgb, RgbImage};
+use jiff::civil::{Date, DateTime, Time};
+
+use crate::config::Config;
+use crate::draw_text::{
+    DrawPosition, FontSize, MultilineDraw, PhotoOffset, PhotoSize, StampOffset,
+};
+use crate::error::AppError;
+use crate::sheet;
+
+/// Name of the cover sheet in every output folder, sorting before the numbered photos.
+pub const HEADER_FILE_NAME: &str = "000_header.jpg";
+const DATE_PT: usize = 20;
+const LINE_PT: usize = 10;
+
+/// What is printed on the cover sheet of one output folder.
+#[derive(Debug)]
+pub struct HeaderSheet<'a> {
+    /// The dates of the photos processed into the folder in this run
+    pub dates: &'a [DateTime],
+    pub run_id: &'a str,
+    pub operator: Option<&'a str>,
+}
+
+impl HeaderSheet<'_> {
+    /// Writes the cover sheet into `folder` at the size of a print, replacing the one of an
+    /// earlier run. Returns where it was written.
+    pub fn write(
+        &self,
+        folder: &Path,
+        config: &Config,
+        font: &FontArc,
+    ) -> Result<PathBuf, AppError> {
+        let (width, height) = (config.target_width_px(), config.target_height_px());
+        let mut page = RgbImage::from_pixel(width, height, Rgb([255, 255, 255]));
+        let locale = config.locale;
+        let size = |pt| FontSize {
+            pt,
+            dpi: config.dpi,
+        };
+        let mut lines = vec![(self.date_text(config), size(DATE_PT))];
+        lines.push((locale.header_run(self.run_id), size(LINE_PT)));
+        if let Some(operator) = self.operator {
+            lines.push((locale.header_operator(operator), size(LINE_PT)));
+        }
+        let count = locale.shape_digits(&self.dates.len().to_string());
+        lines.push((locale.header_photos(&count), size(LINE_PT)));
+
+        let mut draw = MultilineDraw {
+            photo_size: PhotoSize { width, height },
+            photo_offset: PhotoOffset { x: 0, y: 0 },
+            margin_px: config.margin_px(),
+            safe_area_px: config.safe_area_px(),
+            offset_px: StampOffset::default(),
+            destination: &mut page,
+            rtl: locale.is_rtl(),
+        };
+        draw.draw_sized_lines(
+            &lines,
+            font,
+            image::Rgba([0, 0, 0, 255]),
+            config.for_direction(DrawPosition::TopLeft),
+        );
+
+        let path = folder.join(HEADER_FILE_NAME);
+        fs::write(&path, sheet::encode_jpeg(&page, config)?)?;
+        Ok(path)
+    }
+
+    /// The day of the photos, or the first and last day when they span more than one.
+    fn date_text(&self, config: &Config) -> String {
+        let first = self.dates.iter().min().map(|d| d.date());
+        let last = self.dates.iter().max().map(|d| d.date());
+        let format = |d: Date| config.format_date(&d.to_datetime(Time::midnight()));
+        match (first, last) {
+            (Some(first), Some(last)) if first != last => {
+                config.locale.date_range(&format(first), &format(last))
+            }
+            (Some(day), _) => format(day),
+            _ => String::new(),
+        }
+    }
+}
@@ -0,0 +1,160 @@
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+
+use ignore::gitignore::{Gitignore, GitignoreBuilder};
+use ignore::Match;
+use tracing::warn;
+
+/// Name of the files in the source tree with patterns of files and folders to leave out.
+pub const IGNORE_FILE_NAME: &str = ".photobenchignore";
+
+/// The patterns of the ignore files in the source tree, with the gitignore rules: `#` starts a
+/// comment, `!` negates, a trailing `/` only matches folders, a pattern with a `/` in it is
+/// relative to the folder of the ignore file and one without matches at any depth. `*`, `?`
+/// and `[abc]` do not match a `/`, `**` does. The last matching pattern wins, and ignore files
+/// in subfolders add to the ones above them.
+#[derive(Debug, Default)]
+pub struct IgnoreRules {
+    /// The rules of every folder read so far, empty when it has no ignore file
+    dirs: HashMap<PathBuf, Gitignore>,
+}
+
+impl IgnoreRules {
+    /// Whether `path` under `root` is left out by the ignore files in `root` and the folders
+    /// between it and `path`.
+    pub fn is_ignored(&mut self, root: &Path, path: &Path, is_dir: bool) -> bool {
+        let Ok(relative) = path.strip_prefix(root) else {
+            return false;
+        };
+        let mut ignored = false;
+        let mut dir = root.to_path_buf();
+        for component in relative.parent().into_iter().flat_map(Path::components) {
+            ignored = self.check(&dir, path, is_dir).unwrap_or(ignored);
+            dir.push(component);
+        }
+        self.check(&dir, path, is_dir).unwrap_or(ignored)
+    }
+
+    /// Whether the last rule of `dir` matching `path` leaves it out, `None` when none match.
+    fn check(&mut self, dir: &Path, path: &Path, is_dir: bool) -> Option<bool> {
+        let rules = self
+            .dirs
+            .entry(dir.to_path_buf())
+            .or_insert_with(|| read_rules(dir));
+        match rules.matched(path, is_dir) {
+            Match::None => None,
+            Match::Ignore(_) => Some(true),
+            Match::Whitelist(_) => Some(false),
+        }
+    }
+}
+
+/// The rules of the ignore file in `dir`. Lines that are not a valid pattern are left out.
+fn read_rules(dir: &Path) -> Gitignore {
+    let file = dir.join(IGNORE_FILE_NAME);
+    if !file.is_file() {
+        return Gitignore::empty();
+    }
+    let mut builder = GitignoreBuilder::new(dir);
+    // Like the file system on Windows
+    let _ = builder.case_insensitive(cfg!(target_os = "windows"));
+    if let Some(e) = builder.add(&file) {
+        warn!("{e}. Ignoring those lines of {file:?}");
+    }
+    builder.build().unwrap_or_else(|e| {
+        warn!("{e}. Ignoring {file:?}");
+        Gitignore::empty()
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+
+    use super::*;
+    use crate::test_util::temp_dir;
+
+    /// Whether the rules of `lines` in the root leave out the file or folder at `path`.
+    fn ignores(lines: &[&str], path: &str, is_dir: bool) -> bool {
+        let root = temp_dir("ignore");
+        fs::write(root.path().join(IGNORE_FILE_NAME), lines.join("\n")).unwrap();
+        IgnoreRules::default().is_ignored(root.path(), &root.path().join(path), is_dir)
+    }
+
+    #[test]
+    fn patterns_without_a_slash_match_at_any_depth() {
+        assert!(ignores(&["*.tmp"], "a.tmp", false));
+        assert!(ignores(&["*.tmp"], "2024/trip/a.tmp", false));
+        assert!(!ignores(&["*.tmp"], "a.jpg", false));
+        assert!(!ignores(&["# *.jpg"], "a.jpg", false));
+    }
+
+    #[test]
+    fn negated_patterns_take_files_back_in() {
+        let lines = ["*.jpg", "!keep.jpg"];
+        assert!(ignores(&lines, "drop.jpg", false));
+        assert!(!ignores(&lines, "keep.jpg", false));
+        assert!(!ignores(&lines, "sub/keep.jpg", false));
+        // The last matching pattern wins
+        assert!(ignores(&["!keep.jpg", "*.jpg"], "keep.jpg", false));
+    }
+
+    #[test]
+    fn double_stars_cross_folders() {
+        assert!(ignores(&["**/private"], "private", true));
+        assert!(ignores(&["**/private"], "2024/private", true));
+        assert!(ignores(&["raw/**"], "raw/2024/a.jpg", false));
+        assert!(ignores(&["a/**/b.jpg"], "a/b.jpg", false));
+        assert!(ignores(&["a/**/b.jpg"], "a/x/y/b.jpg", false));
+        assert!(!ignores(&["a/*/b.jpg"], "a/x/y/b.jpg", false));
+    }
+
+    #[test]
+    fn patterns_with_a_slash_are_anchored_to_their_folder() {
+        assert!(ignores(&["/calibration.jpg"], "calibration.jpg", false));
+        assert!(!ignores(
+            &["/calibration.jpg"],
+            "day/calibration.jpg",
+            false
+        ));
+        assert!(ignores(&["day/a.jpg"], "day/a.jpg", false));
+        assert!(!ignores(&["day/a.jpg"], "trip/day/a.jpg", false));
+    }
+
+    #[test]
+    fn patterns_ending_in_a_slash_only_match_folders() {
+        assert!(ignores(&["private/"], "private", true));
+        assert!(ignores(&["private/"], "2024/private", true));
+        assert!(!ignores(&["private/"], "private", false));
+    }
+
+    #[test]
+    fn escaped_characters_are_matched_as_they_are() {
+        assert!(ignores(&["\\#1.jpg"], "#1.jpg", false));
+        assert!(ignores(&["\\!1.jpg"], "!1.jpg", false));
+        assert!(ignores(&["\\*.jpg"], "*.jpg", false));
+        assert!(!ignores(&["\\*.jpg"], "a.jpg", false));
+        assert!(ignores(&["a\\?.jpg"], "a?.jpg", false));
+        assert!(!ignores(&["a\\?.jpg"], "ab.jpg", false));
+    }
+
+    #[test]
+    fn ignore_files_in_subfolders_add_to_the_ones_above() {
+        let root = temp_dir("ignore");
+        let root = root.path();
+        fs::create_dir(root.join("trip")).unwrap();
+        fs::write(root.join(IGNORE_FILE_NAME), "*.png\n").unwrap();
+        fs::write(
+            root.join("trip").join(IGNORE_FILE_NAME),
+            "!keep.png\n/b.jpg\n",
+        )
+        .unwrap();
+
+        let mut rules = IgnoreRules::default();
+        assert!(rules.is_ignored(root, &root.join("trip/a.png"), false));
+        assert!(!rules.is_ignored(root, &root.join("trip/keep.png"), false));
+        assert!(rules.is_ignored(root, &root.join("keep.png"), false));
+        assert!(rules.is_ignored(root, &root.join("trip/b.jpg"), false));
+        assert!(!rules.is_ignored(root, &root.join("b.jpg"), false));
+    }
+}
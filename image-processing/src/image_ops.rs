@@ -1,14 +1,24 @@
 
-use ab_glyph::FontRef;
 use jiff::civil::{Date, DateTime, Time};
 use tracing::debug;
 use crate::error::AppError;
+use crate::manifest::KnownOutputs;
+use crate::metadata;
+use crate::naming::NameTemplate;
+use crate::numbering::NumberRange;
+use crate::raw;
+use image::error::{ParameterError, ParameterErrorKind};
+use image::metadata::Orientation;
 use image::{
-    imageops, DynamicImage, GenericImageView, Rgb, RgbImage,
+    imageops, DynamicImage, GenericImageView, ImageError, Rgb, RgbImage,
     RgbaImage,
 };
+use rayon::prelude::*;
 use regex::Regex;
 use std::ffi::OsStr;
+use std::fs;
+use std::fs::File;
+use std::io::Read;
 use std::path::Path;
 use walkdir::WalkDir;
 
@@ -49,44 +59,88 @@ fn string_date_from_filename<P: AsRef<Path>>(path: P) -> Option<String> {
     None
 }
 
-pub fn load_bold_font() -> Result<FontRef<'static>, AppError> {
-    // Bundle the font with the program so it works the same on Ubuntu + Windows.
-    let font_data: &[u8] = include_bytes!("../assets/arialroundedmtbold.ttf");
-    
-    Ok(FontRef::try_from_slice(font_data)?)
-}
-
-pub fn load_arial_bold() -> Result<FontRef<'static>, AppError> {
-    // Bundle the font with the program so it works the same on Ubuntu + Windows.
-    let font_data: &[u8] = include_bytes!("../assets/ARIALBD.TTF");
-    
-    Ok(FontRef::try_from_slice(font_data)?)
-}
-
-
-/// Find the maximum N in filenames matching `N.jpg` anywhere under SOURCE_FOLDER.
-pub fn find_max_number_jpg(root: &Path) -> Result<usize, AppError> {
-    let re = Regex::new(r"^(\d+)\.jpg$")?;
+/// Find the maximum N in the range in jpg filenames matching the name template anywhere under SOURCE_FOLDER.
+pub fn find_max_number_jpg(
+    root: &Path,
+    template: &NameTemplate,
+    range: &NumberRange,
+) -> Result<usize, AppError> {
     let mut max_num = 0;
+    let mut outputs = KnownOutputs::default();
 
     for entry in WalkDir::new(root).into_iter().filter_map(|e| e.ok()) {
-        if !entry.file_type().is_file() {
+        if !entry.file_type().is_file()
+            || !is_jpg(entry.path())
+            || !outputs.is_output(template, entry.path())
+        {
             continue;
         }
-        let name = entry.file_name().to_string_lossy();
-        if let Some(c) = re.captures(&name)
-            && let Ok(n) = c[1].parse::<usize>() {
-                max_num = max_num.max(n);
-            }
+        if let Some(n) = template.number_of(entry.path())
+            && range.contains(n)
+        {
+            max_num = max_num.max(n);
+        }
     }
     Ok(max_num)
 }
 
+/// Whether the file has the extension of the outputs, `.jpg` in any case, as a copy of the
+/// target can come back with `.JPG`.
+pub(crate) fn is_jpg(path: &Path) -> bool {
+    path.extension()
+        .and_then(OsStr::to_str)
+        .is_some_and(|ext| ext.eq_ignore_ascii_case("jpg"))
+}
+
+/// Moves a file, falling back to a copy and delete when renaming is not possible, e.g. across drives.
+/// With `trash` the copied file is sent to the recycle bin instead of deleted.
+pub fn move_file(from: &Path, to: &Path, trash: bool) -> Result<(), AppError> {
+    if fs::rename(from, to).is_err() {
+        fs::copy(from, to)?;
+        remove_file(from, trash)?;
+    }
+    Ok(())
+}
+
+/// Deletes a file, or with `trash` sends it to the recycle bin of the OS so it can be restored.
+pub fn remove_file(path: &Path, trash: bool) -> Result<(), AppError> {
+    match trash {
+        true => trash::delete(path)?,
+        false => fs::remove_file(path)?,
+    }
+    Ok(())
+}
+
 pub fn is_image_file(path: &Path) -> bool {
     matches!(path
         .extension()
         .and_then(OsStr::to_str)
         .map(|s| s.to_lowercase()), Some(ext) if ext == "jpg" || ext == "jpeg" || ext == "png")
+        || raw::is_raw_file(path)
+}
+
+/// Turns and flips a photo decoded from `path` upright by its EXIF Orientation, as cameras
+/// store portrait photos sideways with a tag saying so. Done before anything else, so the
+/// resize and stamps see the photo as it is meant to be seen.
+pub fn orient(img: RgbImage, path: &Path) -> RgbImage {
+    let orientation = match metadata::get_orientation(path) {
+        Ok(orientation) => orientation,
+        Err(e) => {
+            debug!("{e}. Could not read the orientation of {path:?}");
+            None
+        }
+    };
+    let Some(orientation) = orientation
+        .and_then(|o| u8::try_from(o).ok())
+        .and_then(Orientation::from_exif)
+        .filter(|o| *o != Orientation::NoTransforms)
+    else {
+        return img;
+    };
+    debug!("Turning {path:?} upright by its EXIF orientation {orientation:?}");
+    let mut img = DynamicImage::ImageRgb8(img);
+    img.apply_orientation(orientation);
+    img.into_rgb8()
 }
 
 /// Resize to fit within (target_w, target_h) preserving aspect ratio (like PIL thumbnail).
@@ -98,6 +152,28 @@ pub fn resize_to_fit(img: &DynamicImage, target_w: u32, target_h: u32) -> Dynami
     img.resize(target_w, target_h, imageops::FilterType::Lanczos3)
 }
 
+/// Copy `src` into `dst` at (x,y) like `GenericImage::copy_from`, but one memcpy per row
+/// instead of a get and put per pixel.
+pub fn blit_rgb(dst: &mut RgbImage, src: &RgbImage, x: u32, y: u32) -> Result<(), ImageError> {
+    if dst.width() < src.width() + x || dst.height() < src.height() + y {
+        return Err(ImageError::Parameter(ParameterError::from_kind(
+            ParameterErrorKind::DimensionMismatch,
+        )));
+    }
+    let row_len = src.width() as usize * 3;
+    if row_len == 0 {
+        return Ok(());
+    }
+    let stride = dst.width() as usize * 3;
+    let dst: &mut [u8] = dst;
+    let start = y as usize * stride + x as usize * 3;
+    for (i, row) in src.as_raw().chunks_exact(row_len).enumerate() {
+        let offset = start + i * stride;
+        dst[offset..offset + row_len].copy_from_slice(row);
+    }
+    Ok(())
+}
+
 
 /// Overlay premultiplied-alpha RGBA src onto RGB dst at (x,y).
 pub fn overlay_premul_rgba_on_rgb(dst: &mut RgbImage, src: &RgbaImage, x: u32, y: u32) {
@@ -124,3 +200,243 @@ pub fn overlay_premul_rgba_on_rgb(dst: &mut RgbImage, src: &RgbaImage, x: u32, y
         }
     }
 }
+
+/// Reads the pixel density in dots per inch from the JFIF header of a jpeg file.
+/// Returns `None` when the file has no JFIF header or the density is not in dpi.
+pub fn read_jpeg_dpi(path: &Path) -> Result<Option<(u16, u16)>, AppError> {
+    let mut header = [0u8; 18];
+    let mut file = File::open(path)?;
+    if file.read_exact(&mut header).is_err() {
+        return Ok(None);
+    }
+    // SOI marker followed by an APP0 segment with the "JFIF\0" identifier
+    if header[0..4] != [0xFF, 0xD8, 0xFF, 0xE0] || &header[6..11] != b"JFIF\0" {
+        return Ok(None);
+    }
+    // Density unit 1 means dots per inch
+    if header[13] != 1 {
+        return Ok(None);
+    }
+    let x = u16::from_be_bytes([header[14], header[15]]);
+    let y = u16::from_be_bytes([header[16], header[17]]);
+    Ok(Some((x, y)))
+}
+
+/// Perceptual difference hash of an image. Similar looking images have hashes with a small
+/// hamming distance, even after resizing or recompression.
+pub fn dhash(img: &DynamicImage) -> u64 {
+    let small = imageops::thumbnail(&img.to_luma8(), 9, 8);
+    let mut hash = 0u64;
+    for y in 0..8 {
+        for x in 0..8 {
+            hash <<= 1;
+            if small.get_pixel(x, y)[0] < small.get_pixel(x + 1, y)[0] {
+                hash |= 1;
+            }
+        }
+    }
+    hash
+}
+
+/// Edge preserving bilateral filter for noisy high ISO photos. `strength` is how different
+/// two neighbouring colors may be and still be smoothed together, around 10 to 40 works well.
+pub fn denoise(img: &RgbImage, strength: f32) -> RgbImage {
+    const RADIUS: i64 = 2;
+    const SIGMA_SPACE: f32 = 1.5;
+
+    let (w, h) = img.dimensions();
+    let spatial: Vec<f32> = (-RADIUS..=RADIUS)
+        .flat_map(|dy| (-RADIUS..=RADIUS).map(move |dx| (dx, dy)))
+        .map(|(dx, dy)| (-((dx * dx + dy * dy) as f32) / (2.0 * SIGMA_SPACE * SIGMA_SPACE)).exp())
+        .collect();
+    // Weight by the mean absolute difference of the channels
+    let range: Vec<f32> = (0..=255)
+        .map(|d| (-((d * d) as f32) / (2.0 * strength * strength)).exp())
+        .collect();
+
+    let mut out = RgbImage::new(w, h);
+    out.par_chunks_mut(w as usize * 3)
+        .enumerate()
+        .for_each(|(y, row)| {
+            let y = y as i64;
+            for x in 0..w as i64 {
+                let center = img.get_pixel(x as u32, y as u32).0;
+                let mut sum = [0f32; 3];
+                let mut total = 0f32;
+                for (i, (dx, dy)) in (-RADIUS..=RADIUS)
+                    .flat_map(|dy| (-RADIUS..=RADIUS).map(move |dx| (dx, dy)))
+                    .enumerate()
+                {
+                    let nx = (x + dx).clamp(0, w as i64 - 1) as u32;
+                    let ny = (y + dy).clamp(0, h as i64 - 1) as u32;
+                    let p = img.get_pixel(nx, ny).0;
+                    let diff = (0..3).map(|c| center[c].abs_diff(p[c]) as usize).sum::<usize>() / 3;
+                    let weight = spatial[i] * range[diff];
+                    for c in 0..3 {
+                        sum[c] += p[c] as f32 * weight;
+                    }
+                    total += weight;
+                }
+                let px = x as usize * 3;
+                for c in 0..3 {
+                    row[px + c] = (sum[c] / total).round().clamp(0.0, 255.0) as u8;
+                }
+            }
+        });
+    out
+}
+
+/// Share of a line that has to be within the tolerance of the border color for the line to
+/// count as border, so dust and scanner noise do not stop the trim.
+const BORDER_LINE_SHARE: f32 = 0.95;
+
+/// Crops the uniform borders scanners leave around a photo. Every side is trimmed on its own
+/// while the lines along it stay within `tolerance` of the color of its outermost line, but
+/// never more than a third of the photo, so a photo of a clear sky is not eaten away.
+/// `None` when there is no border, or no photo.
+pub fn trim_borders(img: &RgbImage, tolerance: u8) -> Option<RgbImage> {
+    let (w, h) = img.dimensions();
+    if w == 0 || h == 0 {
+        return None;
+    }
+    // The pixels of line `i` from the given side, 0 being the outermost
+    let row = |y: u32| (0..w).map(move |x| *img.get_pixel(x, y));
+    let col = |x: u32| (0..h).map(move |y| *img.get_pixel(x, y));
+    let depth = |line: &dyn Fn(u32) -> Vec<Rgb<u8>>, max: u32| -> u32 {
+        let reference = mean_color(&line(0));
+        (0..max)
+            .take_while(|&i| is_uniform(&line(i), reference, tolerance))
+            .count() as u32
+    };
+
+    let top = depth(&|i| row(i).collect(), h / 3);
+    let bottom = depth(&|i| row(h - 1 - i).collect(), h / 3);
+    let left = depth(&|i| col(i).collect(), w / 3);
+    let right = depth(&|i| col(w - 1 - i).collect(), w / 3);
+    if top + bottom + left + right == 0 {
+        return None;
+    }
+    debug!("Trimming borders of {top} top, {bottom} bottom, {left} left and {right} right");
+    let cropped = imageops::crop_imm(img, left, top, w - left - right, h - top - bottom);
+    Some(cropped.to_image())
+}
+
+fn mean_color(pixels: &[Rgb<u8>]) -> Rgb<u8> {
+    let mut sum = [0u64; 3];
+    for p in pixels {
+        for c in 0..3 {
+            sum[c] += p[c] as u64;
+        }
+    }
+    let n = pixels.len().max(1) as u64;
+    Rgb(sum.map(|s| (s / n) as u8))
+}
+
+/// Whether nearly all the pixels are within `tolerance` of `color` in every channel.
+fn is_uniform(pixels: &[Rgb<u8>], color: Rgb<u8>, tolerance: u8) -> bool {
+    let close = pixels
+        .iter()
+        .filter(|p| (0..3).all(|c| p[c].abs_diff(color[c]) <= tolerance))
+        .count();
+    close as f32 >= pixels.len() as f32 * BORDER_LINE_SHARE
+}
+
+/// The most a crooked scan is straightened by, in degrees either way.
+const MAX_SKEW_DEGREES: f32 = 5.0;
+/// The steps the angles are tried in, in degrees.
+const SKEW_STEP_DEGREES: f32 = 0.1;
+/// Skews below this are left alone, rotating blurs the photo a little.
+const MIN_SKEW_DEGREES: f32 = 0.2;
+/// The long side the edges are found on, larger photos are scaled down for it.
+const SKEW_DETECT_PX: u32 = 1000;
+
+/// How many degrees clockwise the photo is rotated from straight, found with a Hough transform
+/// of its edges over the angles within [`MAX_SKEW_DEGREES`]. The angle whose lines, near
+/// horizontal and near vertical together, line up the most edge pixels is the skew. `None`
+/// when the photo is straight or has no edges to go by.
+pub fn skew_angle(img: &RgbImage) -> Option<f32> {
+    let (w, h) = img.dimensions();
+    let scale = (SKEW_DETECT_PX as f32 / w.max(h) as f32).min(1.0);
+    let (sw, sh) = (((w as f32 * scale) as u32).max(1), ((h as f32 * scale) as u32).max(1));
+    let gray = DynamicImage::ImageRgb8(img.clone())
+        .resize_exact(sw, sh, imageops::FilterType::Triangle)
+        .to_luma8();
+    let edges = imageproc::edges::canny(&gray, 20.0, 60.0);
+    let points: Vec<(f32, f32)> = edges
+        .enumerate_pixels()
+        .filter(|(_, _, p)| p[0] > 0)
+        .map(|(x, y, _)| (x as f32, y as f32))
+        .collect();
+    if points.is_empty() {
+        return None;
+    }
+
+    let diagonal = ((sw * sw + sh * sh) as f32).sqrt().ceil() as usize;
+    let steps = (MAX_SKEW_DEGREES / SKEW_STEP_DEGREES).round() as i32;
+    let (best, _) = (-steps..=steps)
+        .into_par_iter()
+        .map(|step| {
+            let theta = (step as f32 * SKEW_STEP_DEGREES).to_radians();
+            let (sin, cos) = theta.sin_cos();
+            // The distance of every edge pixel from the origin along the normal of the lines
+            // at this angle. Lines of edges pile up in few bins, which squares to a high score
+            let mut rows = vec![0u32; diagonal * 2 + 1];
+            let mut cols = vec![0u32; diagonal * 2 + 1];
+            for &(x, y) in &points {
+                let row = y * cos - x * sin;
+                let col = x * cos + y * sin;
+                rows[(row.round() as isize + diagonal as isize) as usize] += 1;
+                cols[(col.round() as isize + diagonal as isize) as usize] += 1;
+            }
+            let score: u64 = rows.iter().chain(&cols).map(|&n| n as u64 * n as u64).sum();
+            (step, score)
+        })
+        .max_by_key(|&(step, score)| (score, -step.abs()))?;
+    let degrees = best as f32 * SKEW_STEP_DEGREES;
+    (degrees.abs() >= MIN_SKEW_DEGREES).then_some(degrees)
+}
+
+/// Straightens a crooked scan, see [`skew_angle`]. The corners the rotation uncovers are filled
+/// with the color along the edges of the photo, so they blend in with a scan border.
+/// `None` when the photo is straight.
+pub fn deskew(img: &RgbImage) -> Option<RgbImage> {
+    let degrees = skew_angle(img)?;
+    debug!("Straightening a skew of {degrees:.1} degrees");
+    let (w, h) = img.dimensions();
+    let edge: Vec<Rgb<u8>> = (0..w)
+        .flat_map(|x| [*img.get_pixel(x, 0), *img.get_pixel(x, h - 1)])
+        .chain((0..h).flat_map(|y| [*img.get_pixel(0, y), *img.get_pixel(w - 1, y)]))
+        .collect();
+    Some(imageproc::geometric_transformations::rotate_about_center(
+        img,
+        -degrees.to_radians(),
+        imageproc::geometric_transformations::Interpolation::Bilinear,
+        mean_color(&edge),
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn trim_borders_leaves_empty_photos_alone() {
+        for (w, h) in [(0, 0), (0, 40), (40, 0)] {
+            assert!(trim_borders(&RgbImage::new(w, h), 10).is_none(), "{w}x{h}");
+        }
+    }
+
+    #[test]
+    fn trim_borders_crops_a_uniform_frame() {
+        let mut img = RgbImage::from_pixel(60, 40, Rgb([255, 255, 255]));
+        for (x, y, p) in img.enumerate_pixels_mut() {
+            if (5..55).contains(&x) && (3..37).contains(&y) {
+                *p = Rgb([(x * 4) as u8, (y * 6) as u8, 90]);
+            }
+        }
+
+        let trimmed = trim_borders(&img, 10).unwrap();
+        assert_eq!(trimmed.dimensions(), (50, 34));
+        assert!(trim_borders(&trimmed, 10).is_none());
+    }
+}
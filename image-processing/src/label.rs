@@ -0,0 +1,124 @@
+use std::path::Path;
+
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+
+use crate::config::{Config, LabelStamp};
+use crate::error::AppError;
+
+/// What is stamped under the first line of the label.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize, clap::ValueEnum)]
+#[serde(rename_all = "kebab-case")]
+pub enum LabelSource {
+    /// The parts of the file name split on the delimiters, filtered by the keep and drop rules
+    #[default]
+    Chunks,
+    /// The whole file name of the original
+    FullFilename,
+    /// Only the first line
+    None,
+    /// The `custom` lines of the label config
+    Custom,
+}
+
+/// Splits a file name into the chunks stamped with [`LabelSource::Chunks`].
+#[derive(Debug)]
+pub struct ChunkRules {
+    delimiters: Vec<String>,
+    keep: Option<Regex>,
+    drop: Option<Regex>,
+}
+
+impl ChunkRules {
+    pub fn new(label: &LabelStamp) -> Result<Self, AppError> {
+        let regex = |r: &str| match r {
+            "" => Ok(None),
+            r => Regex::new(r).map(Some),
+        };
+        Ok(Self {
+            delimiters: label.delimiters.clone(),
+            keep: regex(&label.keep)?,
+            drop: regex(&label.drop)?,
+        })
+    }
+
+    pub fn chunks<'a>(&self, file_name: &'a str) -> Vec<&'a str> {
+        let mut chunks = vec![file_name];
+        for delimiter in self.delimiters.iter().filter(|d| !d.is_empty()) {
+            chunks = chunks
+                .into_iter()
+                .flat_map(|c| c.split(delimiter.as_str()))
+                .collect();
+        }
+        chunks
+            .into_iter()
+            .filter(|c| !c.is_empty())
+            .filter(|c| self.keep.as_ref().is_none_or(|r| r.is_match(c)))
+            .filter(|c| self.drop.as_ref().is_none_or(|r| !r.is_match(c)))
+            .collect()
+    }
+}
+
+/// The lines of the label stamped onto the photo of `path`. `source_dir` is the folder of
+/// the photo under the source, `camera` the camera it was taken with. The `captions` of the
+/// photo are added at the end and are not counted in `max_lines`. `checksum` is stamped after
+/// the number, or in place of `{checksum}` in the template.
+pub fn label_lines(
+    path: &Path,
+    config: &Config,
+    number: usize,
+    source_dir: &str,
+    camera: Option<&str>,
+    captions: &[String],
+    checksum: Option<&str>,
+) -> Result<Vec<String>, AppError> {
+    let label = &config.label;
+    let file_name = path
+        .file_name()
+        .and_then(|x| x.to_str())
+        .unwrap_or_default();
+    let orig_stem = path
+        .file_stem()
+        .and_then(|x| x.to_str())
+        .unwrap_or_default();
+
+    let counter = config.counter_format().format(number, 0);
+    let template = config.label_template();
+    let mut first = template.replace("{number}", &config.locale.shape_digits(&counter));
+    if let Some(checksum) = checksum {
+        match template.contains("{checksum}") {
+            true => first = first.replace("{checksum}", checksum),
+            false => first = format!("{first} · {checksum}"),
+        }
+    }
+    let mut lines = vec![first];
+    if config.prefix_from_dir.in_label() && !source_dir.is_empty() {
+        lines.push(source_dir.to_string());
+    }
+    if let Some(camera) = camera.filter(|_| label.camera) {
+        lines.push(camera.to_string());
+    }
+    if let Some(operator) = config.operator.as_deref().filter(|_| label.operator) {
+        lines.push(operator.to_string());
+    }
+    match label.source {
+        LabelSource::Chunks => lines.extend(
+            ChunkRules::new(label)?
+                .chunks(file_name)
+                .into_iter()
+                .map(ToString::to_string),
+        ),
+        LabelSource::FullFilename if !file_name.is_empty() => lines.push(file_name.to_string()),
+        LabelSource::FullFilename | LabelSource::None => {}
+        LabelSource::Custom => lines.extend(label.custom.iter().map(|line| {
+            line.replace("{number}", &counter)
+                .replace("{file_name}", file_name)
+                .replace("{orig_stem}", orig_stem)
+        })),
+    }
+    if let Some(max_lines) = label.max_lines {
+        lines.truncate(max_lines);
+    }
+    lines.extend(captions.iter().cloned());
+    Ok(lines)
+}
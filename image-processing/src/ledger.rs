@@ -0,0 +1,221 @@
+use std::collections::HashSet;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+
+use rusqlite::{params, Connection, OpenFlags};
+
+use crate::config::Config;
+use crate::error::AppError;
+use crate::manifest::ManifestEntry;
+
+/// Name of the ledger database kept in the root of the target folder.
+pub const LEDGER_FILE_NAME: &str = "photo-bench-ledger.sqlite";
+
+const SCHEMA: &str = "
+CREATE TABLE IF NOT EXISTS runs (
+    id INTEGER PRIMARY KEY,
+    started_at TEXT NOT NULL,
+    source TEXT NOT NULL,
+    config TEXT NOT NULL
+);
+CREATE TABLE IF NOT EXISTS files (
+    id INTEGER PRIMARY KEY,
+    run_id INTEGER NOT NULL REFERENCES runs(id),
+    number INTEGER NOT NULL,
+    source TEXT NOT NULL,
+    output TEXT NOT NULL,
+    date TEXT NOT NULL,
+    sha256 TEXT NOT NULL,
+    number_range TEXT
+);
+CREATE TABLE IF NOT EXISTS run_totals (
+    run_id INTEGER PRIMARY KEY REFERENCES runs(id),
+    photos INTEGER NOT NULL,
+    input_bytes INTEGER NOT NULL,
+    output_bytes INTEGER NOT NULL,
+    seconds REAL NOT NULL,
+    cpus INTEGER NOT NULL
+);
+CREATE INDEX IF NOT EXISTS files_source ON files(source);
+CREATE INDEX IF NOT EXISTS files_number ON files(number);
+";
+
+/// SQLite ledger of every run and every processed file in a target folder,
+/// shared between the worker threads.
+#[derive(Debug)]
+pub struct Ledger {
+    conn: Mutex<Connection>,
+    run_id: i64,
+}
+
+impl Ledger {
+    /// Opens the ledger of the target and records the start of a run over `sources`.
+    pub fn open(target: &Path, sources: &[PathBuf], config: &Config) -> Result<Self, AppError> {
+        let conn = Connection::open(target.join(LEDGER_FILE_NAME))?;
+        conn.execute_batch(SCHEMA)?;
+        conn.execute(
+            "INSERT INTO runs (started_at, source, config) VALUES (?1, ?2, ?3)",
+            params![
+                jiff::Zoned::now().to_string(),
+                sources_text(sources),
+                serde_json::to_string(config)?
+            ],
+        )?;
+        let run_id = conn.last_insert_rowid();
+        Ok(Self {
+            conn: Mutex::new(conn),
+            run_id,
+        })
+    }
+
+    pub fn record(&self, entry: &ManifestEntry) -> Result<(), AppError> {
+        let conn = self.conn.lock().unwrap_or_else(|e| e.into_inner());
+        conn.execute(
+            "INSERT INTO files (run_id, number, source, output, date, sha256, number_range)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)",
+            params![
+                self.run_id,
+                entry.number as i64,
+                entry.source.to_string_lossy(),
+                entry.output.to_string_lossy(),
+                entry.date.to_string(),
+                entry.sha256,
+                entry.number_range.map(|r| r.to_string()),
+            ],
+        )?;
+        Ok(())
+    }
+
+    /// Records how much the run processed and how long it took, to estimate the next runs.
+    pub fn record_totals(&self, totals: &RunTotals) -> Result<(), AppError> {
+        let conn = self.conn.lock().unwrap_or_else(|e| e.into_inner());
+        conn.execute(
+            "INSERT OR REPLACE INTO run_totals (run_id, photos, input_bytes, output_bytes, seconds, cpus)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
+            params![
+                self.run_id,
+                totals.photos as i64,
+                totals.input_bytes as i64,
+                totals.output_bytes as i64,
+                totals.seconds,
+                totals.cpus as i64,
+            ],
+        )?;
+        Ok(())
+    }
+}
+
+/// The source and output of every file in the ledger of the target, without starting a run.
+/// Empty when the target has no ledger.
+pub fn read_processed(target: &Path) -> Result<Vec<(PathBuf, PathBuf)>, AppError> {
+    let path = target.join(LEDGER_FILE_NAME);
+    if !path.exists() {
+        return Ok(vec![]);
+    }
+    let conn = Connection::open_with_flags(path, OpenFlags::SQLITE_OPEN_READ_ONLY)?;
+    let mut stmt = conn.prepare("SELECT source, output FROM files")?;
+    let files = stmt
+        .query_map([], |row| {
+            Ok((
+                PathBuf::from(row.get::<_, String>(0)?),
+                PathBuf::from(row.get::<_, String>(1)?),
+            ))
+        })?
+        .collect::<Result<_, _>>()?;
+    Ok(files)
+}
+
+/// How much a run processed and how long it took.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct RunTotals {
+    pub photos: usize,
+    pub input_bytes: u64,
+    pub output_bytes: u64,
+    pub seconds: f64,
+    pub cpus: usize,
+}
+
+/// The totals of the earlier runs into the target that processed photos, `None` when the
+/// target has no ledger or no runs recorded with their totals. The seconds are cpu seconds,
+/// the time of every run times the cpus it used.
+pub fn run_history(target: &Path) -> Result<Option<RunTotals>, AppError> {
+    let path = target.join(LEDGER_FILE_NAME);
+    if !path.exists() {
+        return Ok(None);
+    }
+    let conn = Connection::open_with_flags(path, OpenFlags::SQLITE_OPEN_READ_ONLY)?;
+    let has_totals: bool = conn.query_row(
+        "SELECT EXISTS (SELECT 1 FROM sqlite_master WHERE type = 'table' AND name = 'run_totals')",
+        [],
+        |row| row.get(0),
+    )?;
+    if !has_totals {
+        return Ok(None);
+    }
+    let (runs, totals) = conn.query_row(
+        "SELECT COUNT(*), TOTAL(photos), TOTAL(input_bytes), TOTAL(output_bytes), TOTAL(seconds * cpus)
+         FROM run_totals WHERE photos > 0",
+        [],
+        |row| {
+            let totals = RunTotals {
+                photos: row.get::<_, f64>(1)? as usize,
+                input_bytes: row.get::<_, f64>(2)? as u64,
+                output_bytes: row.get::<_, f64>(3)? as u64,
+                seconds: row.get(4)?,
+                cpus: 1,
+            };
+            Ok((row.get::<_, i64>(0)?, totals))
+        },
+    )?;
+    Ok((runs > 0).then_some(totals))
+}
+
+/// How the sources of a run are recorded, one path per line.
+fn sources_text(sources: &[PathBuf]) -> String {
+    let sources: Vec<_> = sources.iter().map(|s| s.to_string_lossy()).collect();
+    sources.join("\n")
+}
+
+/// The sources compared the same way whether they were given relative or absolute.
+fn canonical_sources<'a>(sources: impl IntoIterator<Item = &'a Path>) -> HashSet<PathBuf> {
+    sources
+        .into_iter()
+        .map(|s| s.canonicalize().unwrap_or_else(|_| s.to_path_buf()))
+        .collect()
+}
+
+/// When the last run over `sources` with the same options as `config` started, as it was
+/// recorded in the ledger of the target. Whether the ledger is on is not counted as an option.
+pub fn last_run(
+    target: &Path,
+    sources: &[PathBuf],
+    config: &Config,
+) -> Result<Option<String>, AppError> {
+    let path = target.join(LEDGER_FILE_NAME);
+    if !path.exists() {
+        return Ok(None);
+    }
+    let options = |config: serde_json::Value| match config {
+        serde_json::Value::Object(mut options) => {
+            options.remove("ledger");
+            Some(options)
+        }
+        _ => None,
+    };
+    let wanted = options(serde_json::to_value(config)?);
+    let sources = canonical_sources(sources.iter().map(PathBuf::as_path));
+
+    let conn = Connection::open_with_flags(path, OpenFlags::SQLITE_OPEN_READ_ONLY)?;
+    let mut stmt = conn.prepare("SELECT started_at, source, config FROM runs ORDER BY id DESC")?;
+    let mut rows = stmt.query([])?;
+    while let Some(row) = rows.next()? {
+        let run_sources = row.get::<_, String>(1)?;
+        let run_sources = canonical_sources(run_sources.lines().map(Path::new));
+        let run_config = serde_json::from_str(&row.get::<_, String>(2)?).ok();
+        if run_sources != sources || run_config.and_then(options) != wanted {
+            continue;
+        }
+        return Ok(Some(row.get(0)?));
+    }
+    Ok(None)
+}
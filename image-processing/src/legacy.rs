@@ -0,0 +1,339 @@
+use std::collections::HashSet;
+use std::fmt;
+use std::path::{Path, PathBuf};
+
+use image::RgbImage;
+use jiff::civil::DateTime;
+use jiff::fmt::strtime;
+use regex::Regex;
+use tracing::{debug, info, warn};
+use walkdir::WalkDir;
+
+use crate::color_profile;
+use crate::config::{Config, StampTime};
+use crate::draw_text::{pt_to_px, DrawPosition};
+use crate::error::AppError;
+use crate::glyph_match::GlyphMatcher;
+use crate::ledger::{self, Ledger};
+use crate::manifest::ManifestEntry;
+use crate::numbering::{self, CounterStyle, Numbering};
+#[cfg(feature = "ocr")]
+use crate::ocr::StampOcr;
+use crate::{fonts, hashing, image_ops};
+
+/// A stamped photo whose number could be read.
+#[derive(Debug)]
+pub struct LegacyPhoto {
+    pub path: PathBuf,
+    pub number: usize,
+    pub date: DateTime,
+}
+
+/// What [`import_legacy`] found in the folder of stamped photos.
+#[derive(Debug, Default)]
+pub struct LegacyImport {
+    pub imported: Vec<LegacyPhoto>,
+    /// Photos already in the ledger of the target
+    pub already_imported: usize,
+    /// Photos the number or the date could not be read from
+    pub unreadable: Vec<PathBuf>,
+}
+
+impl LegacyImport {
+    pub fn last_number(&self) -> Option<usize> {
+        self.imported.iter().map(|photo| photo.number).max()
+    }
+}
+
+/// Reads the numbers and dates off the photos in `legacy` that were stamped by the old tool,
+/// or an earlier version of this one, records them in the ledger of `target` and continues the
+/// numbering of the target after the highest number. With `ocr` the stamps are read with
+/// Tesseract in any font, size and color. Without it they are matched with the fonts, sizes
+/// and colors of the config, so only photos stamped with exactly those are read.
+/// Photos without a readable date stamp get the date of their `YYYYMMDD` folder.
+pub fn import_legacy(
+    legacy: &Path,
+    target: &Path,
+    config: &Config,
+    ocr: bool,
+) -> Result<LegacyImport, AppError> {
+    let reader = StampReader::new(config, ocr)?;
+    std::fs::create_dir_all(target)?;
+    let ledger = Ledger::open(target, &[legacy.to_path_buf()], config)?;
+    let done: HashSet<PathBuf> = ledger::read_processed(target)?
+        .into_iter()
+        .map(|(source, _)| source)
+        .collect();
+    let range = config.number_range.unwrap_or_default();
+
+    let mut import = LegacyImport::default();
+    for entry in WalkDir::new(legacy)
+        .sort_by_file_name()
+        .into_iter()
+        .filter_map(|e| e.ok())
+    {
+        let path = entry.path();
+        if !entry.file_type().is_file() || !image_ops::is_image_file(path) {
+            continue;
+        }
+        let source = path.canonicalize()?;
+        if done.contains(&source) {
+            import.already_imported += 1;
+            continue;
+        }
+        let img = match color_profile::open_rgb8(path, false, config.decoder) {
+            Ok(img) => img,
+            Err(e) => {
+                warn!("{e}. Could not open {path:?}");
+                import.unreadable.push(path.to_path_buf());
+                continue;
+            }
+        };
+        let number = reader.number(&img).filter(|n| range.contains(*n));
+        let date = reader
+            .date(&img)
+            .or_else(|| path.parent().and_then(image_ops::date_from_filename));
+        let (Some(number), Some(date)) = (number, date) else {
+            warn!("Could not read the stamp of {path:?}");
+            import.unreadable.push(path.to_path_buf());
+            continue;
+        };
+        debug!("Read number {number} and date {date} from {path:?}");
+
+        let output = path
+            .strip_prefix(target)
+            .map(Path::to_path_buf)
+            .unwrap_or_else(|_| source.clone());
+        ledger.record(&ManifestEntry {
+            number,
+            source,
+            output,
+            date,
+            sha256: hashing::sha256_file(path)?,
+            original_sha256: None,
+            operator: None,
+            number_range: config.number_range,
+            quality_warnings: vec![],
+            run_id: None,
+        })?;
+        import.imported.push(LegacyPhoto {
+            path: path.to_path_buf(),
+            number,
+            date,
+        });
+    }
+
+    // Per folder numbering starts every folder after the numbers in it, the counter only
+    // carries the global sequence
+    if let Some(last) = import.last_number()
+        && config.numbering == Numbering::Global
+    {
+        numbering::seed_counter(target, &range, last)?;
+        info!("The numbering of {target:?} continues at {}", last + 1);
+    }
+    Ok(import)
+}
+
+/// Reads the first lines of the label and date stamps.
+struct StampReader<'a> {
+    config: &'a Config,
+    label: GlyphMatcher,
+    date: GlyphMatcher,
+    /// Reads the stamps instead of the glyph matchers, with `--ocr`
+    #[cfg(feature = "ocr")]
+    ocr: Option<StampOcr>,
+    /// The label template around the number, without spaces
+    label_prefix: String,
+    label_suffix: String,
+    counter: Regex,
+}
+
+impl<'a> StampReader<'a> {
+    fn new(config: &'a Config, ocr: bool) -> Result<Self, AppError> {
+        if ocr && !cfg!(feature = "ocr") {
+            return Err(AppError::OcrNotBuilt);
+        }
+        let template = config.label_template();
+        let (prefix, suffix) = template.split_once("{number}").unwrap_or((template, ""));
+        let counter = config.counter_format();
+        let mut label_chars = format!("{prefix}{suffix}{}0123456789", counter.prefix);
+        if counter.style == CounterStyle::Alpha {
+            label_chars.extend('A'..='Z');
+        }
+        let counter_pattern = format!("{}$", counter.pattern());
+        Ok(Self {
+            config,
+            label: GlyphMatcher::new(
+                &fonts::load_font(&config.label.font)?,
+                &label_chars,
+                pt_to_px(config.label.size_pt, config.dpi),
+                config.label.color.rgb(),
+            ),
+            date: GlyphMatcher::new(
+                &fonts::load_font(config.date_font())?,
+                "0123456789./-:,",
+                pt_to_px(config.date.size_pt, config.dpi),
+                config.date.color.rgb(),
+            ),
+            label_prefix: no_spaces(prefix),
+            label_suffix: no_spaces(suffix),
+            counter: Regex::new(&counter_pattern)?,
+            #[cfg(feature = "ocr")]
+            ocr: ocr.then(|| StampOcr::new(config.dpi)).transpose()?,
+        })
+    }
+
+    /// The lines in the `corner` of the photo without spaces, read with OCR or with `glyphs`.
+    fn lines(&self, glyphs: &GlyphMatcher, img: &RgbImage, corner: DrawPosition) -> Vec<String> {
+        #[cfg(feature = "ocr")]
+        if let Some(ocr) = &self.ocr {
+            return match ocr.read_corner(img, corner) {
+                Ok(lines) => lines.iter().map(|line| no_spaces(line)).collect(),
+                Err(e) => {
+                    warn!("{e}");
+                    vec![]
+                }
+            };
+        }
+        glyphs.read_corner(img, corner)
+    }
+
+    /// The number on the first line of the label.
+    fn number(&self, img: &RgbImage) -> Option<usize> {
+        let lines = self.lines(&self.label, img, self.config.label_position());
+        // Only the counter has to be read right, a misread letter of the template is fine as
+        // long as most of it is there, so other text in the color is not taken for the label
+        lines.iter().find_map(|line| {
+            let chars: Vec<char> = line.chars().collect();
+            let end = chars.len().checked_sub(self.label_suffix.chars().count())?;
+            let line: String = chars[..end].iter().collect();
+            let counter = self.counter.find(&line)?;
+            if line[..counter.start()].chars().count() * 2 < self.label_prefix.chars().count() {
+                return None;
+            }
+            self.config.counter_format().parse(counter.as_str())
+        })
+    }
+
+    /// The date on the first line of the date stamp, when it is written with numbers only.
+    fn date(&self, img: &RgbImage) -> Option<DateTime> {
+        let lines = self.lines(&self.date, img, self.config.date_position());
+        // The reader leaves out the spaces, so they are left out of the format too
+        let date = &self.config.date;
+        let format = match date.time {
+            StampTime::Inline => no_spaces(&format!("{}{}", date.format, date.time_format)),
+            StampTime::Off | StampTime::Line => no_spaces(&date.format),
+        };
+        lines.iter().find_map(|line| {
+            let parsed = strtime::parse(&format, line).ok()?;
+            match date.time {
+                StampTime::Inline => parsed.to_datetime().ok(),
+                StampTime::Off | StampTime::Line => parsed
+                    .to_date()
+                    .ok()
+                    .map(|d| d.to_datetime(jiff::civil::Time::midnight())),
+            }
+        })
+    }
+}
+
+impl fmt::Display for LegacyImport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "Imported: {}", self.imported.len())?;
+        for photo in &self.imported {
+            writeln!(
+                f,
+                "  {} {} {}",
+                photo.number,
+                photo.date.date(),
+                photo.path.display()
+            )?;
+        }
+        if self.already_imported > 0 {
+            writeln!(f, "Already in the ledger: {}", self.already_imported)?;
+        }
+        if !self.unreadable.is_empty() {
+            writeln!(f, "Could not read the stamp of:")?;
+            for path in &self.unreadable {
+                writeln!(f, "  {}", path.display())?;
+            }
+        }
+        match self.last_number() {
+            Some(last) => writeln!(f, "Numbering continues at {}", last + 1),
+            None => writeln!(f, "No new numbers found"),
+        }
+    }
+}
+
+fn no_spaces(text: &str) -> String {
+    text.chars().filter(|c| !c.is_whitespace()).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+
+    use image::Rgb;
+    use rusqlite::Connection;
+
+    use super::*;
+    use crate::config::write_default_config;
+    use crate::ledger::LEDGER_FILE_NAME;
+    use crate::test_util::temp_dir;
+    use crate::{run_image_processing, App};
+
+    #[test]
+    fn imports_the_photos_a_run_stamped_and_continues_their_numbering() {
+        let dir = temp_dir("legacy");
+        let source = dir.path().join("source");
+        let stamped = dir.path().join("stamped");
+        let target = dir.path().join("target");
+        fs::create_dir(&source).unwrap();
+        for name in ["IMG_20251224_101500.jpg", "IMG_20251225_090000.jpg"] {
+            RgbImage::from_pixel(1200, 900, Rgb([90, 140, 200]))
+                .save(source.join(name))
+                .unwrap();
+        }
+        let config_path = write_default_config(dir.path(), false).unwrap();
+        let app = App {
+            sources: vec![source],
+            target: stamped.clone(),
+            config: Some(config_path.clone()),
+            ..App::default()
+        };
+        run_image_processing(app, None).unwrap();
+
+        let config = Config::load(Some(&config_path)).unwrap();
+        let import = import_legacy(&stamped, &target, &config, false).unwrap();
+        assert!(import.unreadable.is_empty(), "{import}");
+        let mut read: Vec<_> = import
+            .imported
+            .iter()
+            .map(|photo| (photo.number, photo.date.date().to_string()))
+            .collect();
+        read.sort();
+        assert_eq!(
+            read,
+            [(1, "2025-12-24".to_string()), (2, "2025-12-25".to_string())]
+        );
+
+        let ledger = Connection::open(target.join(LEDGER_FILE_NAME)).unwrap();
+        let recorded: Vec<(i64, String)> = ledger
+            .prepare("SELECT number, date FROM files ORDER BY number")
+            .unwrap()
+            .query_map([], |row| Ok((row.get(0)?, row.get(1)?)))
+            .unwrap()
+            .collect::<Result<_, _>>()
+            .unwrap();
+        assert_eq!(recorded.len(), 2);
+        assert_eq!(recorded[1].0, 2);
+        assert!(recorded[1].1.starts_with("2025-12-25"), "{recorded:?}");
+
+        let range = config.number_range.unwrap_or_default().to_string();
+        assert_eq!(numbering::read_counter(&target).unwrap()[&range], 2);
+
+        // Running it again finds them in the ledger
+        let again = import_legacy(&stamped, &target, &config, false).unwrap();
+        assert_eq!((again.imported.len(), again.already_imported), (0, 2));
+    }
+}
@@ -0,0 +1,411 @@
+use jiff::civil::DateTime;
+use serde::{Deserialize, Serialize};
+
+/// The language of the text stamped onto the photos and sheets.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize, clap::ValueEnum)]
+#[serde(rename_all = "kebab-case")]
+pub enum Locale {
+    En,
+    #[default]
+    De,
+    Af,
+    Nl,
+    Fr,
+    Ar,
+    He,
+}
+
+/// The stamped text of one locale.
+struct Strings {
+    /// First line of the label, `{number}` is replaced with the number of the photo
+    photo_number: &'static str,
+    /// The dates on a sheet with photos of more than one day, `{first}` and `{last}` are replaced
+    date_range: &'static str,
+    /// The lines of the cover sheet of an output folder, with `{run}`, `{operator}` and `{count}`
+    header_run: &'static str,
+    header_operator: &'static str,
+    header_photos: &'static str,
+    months: [&'static str; 12],
+    months_short: [&'static str; 12],
+    /// From Monday to Sunday
+    weekdays: [&'static str; 7],
+    weekdays_short: [&'static str; 7],
+}
+
+const EN: Strings = Strings {
+    photo_number: "Photo no.: {number}",
+    date_range: "{first} to {last}",
+    header_run: "Run {run}",
+    header_operator: "Operator: {operator}",
+    header_photos: "Photos: {count}",
+    months: [
+        "January",
+        "February",
+        "March",
+        "April",
+        "May",
+        "June",
+        "July",
+        "August",
+        "September",
+        "October",
+        "November",
+        "December",
+    ],
+    months_short: [
+        "Jan", "Feb", "Mar", "Apr", "May", "Jun", "Jul", "Aug", "Sep", "Oct", "Nov", "Dec",
+    ],
+    weekdays: [
+        "Monday",
+        "Tuesday",
+        "Wednesday",
+        "Thursday",
+        "Friday",
+        "Saturday",
+        "Sunday",
+    ],
+    weekdays_short: ["Mon", "Tue", "Wed", "Thu", "Fri", "Sat", "Sun"],
+};
+
+const DE: Strings = Strings {
+    photo_number: "Foto Nr.: {number}",
+    date_range: "{first} bis {last}",
+    header_run: "Lauf {run}",
+    header_operator: "Bearbeiter: {operator}",
+    header_photos: "Fotos: {count}",
+    months: [
+        "Januar",
+        "Februar",
+        "März",
+        "April",
+        "Mai",
+        "Juni",
+        "Juli",
+        "August",
+        "September",
+        "Oktober",
+        "November",
+        "Dezember",
+    ],
+    months_short: [
+        "Jan", "Feb", "Mär", "Apr", "Mai", "Jun", "Jul", "Aug", "Sep", "Okt", "Nov", "Dez",
+    ],
+    weekdays: [
+        "Montag",
+        "Dienstag",
+        "Mittwoch",
+        "Donnerstag",
+        "Freitag",
+        "Samstag",
+        "Sonntag",
+    ],
+    weekdays_short: ["Mo", "Di", "Mi", "Do", "Fr", "Sa", "So"],
+};
+
+const AF: Strings = Strings {
+    photo_number: "Foto nr.: {number}",
+    date_range: "{first} tot {last}",
+    header_run: "Lopie {run}",
+    header_operator: "Operateur: {operator}",
+    header_photos: "Foto's: {count}",
+    months: [
+        "Januarie",
+        "Februarie",
+        "Maart",
+        "April",
+        "Mei",
+        "Junie",
+        "Julie",
+        "Augustus",
+        "September",
+        "Oktober",
+        "November",
+        "Desember",
+    ],
+    months_short: [
+        "Jan", "Feb", "Mrt", "Apr", "Mei", "Jun", "Jul", "Aug", "Sep", "Okt", "Nov", "Des",
+    ],
+    weekdays: [
+        "Maandag",
+        "Dinsdag",
+        "Woensdag",
+        "Donderdag",
+        "Vrydag",
+        "Saterdag",
+        "Sondag",
+    ],
+    weekdays_short: ["Ma", "Di", "Wo", "Do", "Vr", "Sa", "So"],
+};
+
+const NL: Strings = Strings {
+    photo_number: "Foto nr.: {number}",
+    date_range: "{first} t/m {last}",
+    header_run: "Run {run}",
+    header_operator: "Medewerker: {operator}",
+    header_photos: "Foto's: {count}",
+    months: [
+        "januari",
+        "februari",
+        "maart",
+        "april",
+        "mei",
+        "juni",
+        "juli",
+        "augustus",
+        "september",
+        "oktober",
+        "november",
+        "december",
+    ],
+    months_short: [
+        "jan", "feb", "mrt", "apr", "mei", "jun", "jul", "aug", "sep", "okt", "nov", "dec",
+    ],
+    weekdays: [
+        "maandag",
+        "dinsdag",
+        "woensdag",
+        "donderdag",
+        "vrijdag",
+        "zaterdag",
+        "zondag",
+    ],
+    weekdays_short: ["ma", "di", "wo", "do", "vr", "za", "zo"],
+};
+
+const FR: Strings = Strings {
+    photo_number: "Photo n° {number}",
+    date_range: "du {first} au {last}",
+    header_run: "Lot {run}",
+    header_operator: "Opérateur : {operator}",
+    header_photos: "Photos : {count}",
+    months: [
+        "janvier",
+        "février",
+        "mars",
+        "avril",
+        "mai",
+        "juin",
+        "juillet",
+        "août",
+        "septembre",
+        "octobre",
+        "novembre",
+        "décembre",
+    ],
+    months_short: [
+        "janv.", "févr.", "mars", "avr.", "mai", "juin", "juil.", "août", "sept.", "oct.", "nov.",
+        "déc.",
+    ],
+    weekdays: [
+        "lundi", "mardi", "mercredi", "jeudi", "vendredi", "samedi", "dimanche",
+    ],
+    weekdays_short: ["lun.", "mar.", "mer.", "jeu.", "ven.", "sam.", "dim."],
+};
+
+const AR: Strings = Strings {
+    photo_number: "صورة رقم: {number}",
+    date_range: "من {first} إلى {last}",
+    header_run: "التشغيل {run}",
+    header_operator: "المشغل: {operator}",
+    header_photos: "الصور: {count}",
+    months: [
+        "يناير",
+        "فبراير",
+        "مارس",
+        "أبريل",
+        "مايو",
+        "يونيو",
+        "يوليو",
+        "أغسطس",
+        "سبتمبر",
+        "أكتوبر",
+        "نوفمبر",
+        "ديسمبر",
+    ],
+    months_short: [
+        "يناير",
+        "فبراير",
+        "مارس",
+        "أبريل",
+        "مايو",
+        "يونيو",
+        "يوليو",
+        "أغسطس",
+        "سبتمبر",
+        "أكتوبر",
+        "نوفمبر",
+        "ديسمبر",
+    ],
+    weekdays: [
+        "الاثنين",
+        "الثلاثاء",
+        "الأربعاء",
+        "الخميس",
+        "الجمعة",
+        "السبت",
+        "الأحد",
+    ],
+    weekdays_short: [
+        "الاثنين",
+        "الثلاثاء",
+        "الأربعاء",
+        "الخميس",
+        "الجمعة",
+        "السبت",
+        "الأحد",
+    ],
+};
+
+const HE: Strings = Strings {
+    photo_number: "תמונה מס׳ {number}",
+    date_range: "{first} עד {last}",
+    header_run: "הרצה {run}",
+    header_operator: "מפעיל: {operator}",
+    header_photos: "תמונות: {count}",
+    months: [
+        "ינואר",
+        "פברואר",
+        "מרץ",
+        "אפריל",
+        "מאי",
+        "יוני",
+        "יולי",
+        "אוגוסט",
+        "ספטמבר",
+        "אוקטובר",
+        "נובמבר",
+        "דצמבר",
+    ],
+    months_short: [
+        "ינו׳", "פבר׳", "מרץ", "אפר׳", "מאי", "יוני", "יולי", "אוג׳", "ספט׳", "אוק׳", "נוב׳",
+        "דצמ׳",
+    ],
+    weekdays: [
+        "יום שני",
+        "יום שלישי",
+        "יום רביעי",
+        "יום חמישי",
+        "יום שישי",
+        "שבת",
+        "יום ראשון",
+    ],
+    weekdays_short: ["ב׳", "ג׳", "ד׳", "ה׳", "ו׳", "ש׳", "א׳"],
+};
+
+impl Locale {
+    fn strings(self) -> &'static Strings {
+        match self {
+            Locale::En => &EN,
+            Locale::De => &DE,
+            Locale::Af => &AF,
+            Locale::Nl => &NL,
+            Locale::Fr => &FR,
+            Locale::Ar => &AR,
+            Locale::He => &HE,
+        }
+    }
+
+    /// Whether the text of this locale is written right-to-left.
+    pub fn is_rtl(self) -> bool {
+        matches!(self, Locale::Ar | Locale::He)
+    }
+
+    /// Writes the digits in `text` the way this locale does, e.g. `١٢` for `12` in Arabic.
+    pub fn shape_digits(self, text: &str) -> String {
+        match self {
+            Locale::Ar => text
+                .chars()
+                .map(|c| match c.to_digit(10) {
+                    Some(d) if c.is_ascii_digit() => char::from_u32(0x0660 + d).unwrap_or(c),
+                    _ => c,
+                })
+                .collect(),
+            _ => text.to_string(),
+        }
+    }
+
+    /// The default first line of the label, with a `{number}` placeholder.
+    pub fn photo_number(self) -> &'static str {
+        self.strings().photo_number
+    }
+
+    /// Two formatted dates as a range, e.g. `24 12 2025 bis 26 12 2025`.
+    pub fn date_range(self, first: &str, last: &str) -> String {
+        self.strings()
+            .date_range
+            .replace("{first}", first)
+            .replace("{last}", last)
+    }
+
+    /// The run id line of the cover sheet of an output folder.
+    pub fn header_run(self, run_id: &str) -> String {
+        self.strings().header_run.replace("{run}", run_id)
+    }
+
+    /// The operator line of the cover sheet of an output folder.
+    pub fn header_operator(self, operator: &str) -> String {
+        self.strings()
+            .header_operator
+            .replace("{operator}", operator)
+    }
+
+    /// The photo count line of the cover sheet of an output folder.
+    pub fn header_photos(self, count: &str) -> String {
+        self.strings().header_photos.replace("{count}", count)
+    }
+
+    /// Formats `date` like [`DateTime::strftime`], but with the month names (`%B`, `%b` and `%h`),
+    /// the weekday names (`%A` and `%a`) and the digits of this locale.
+    pub fn strftime(self, date: &DateTime, format: &str) -> String {
+        let strings = self.strings();
+        let month = date.month() as usize - 1;
+        let weekday = date.weekday().to_monday_zero_offset() as usize;
+        let mut localized = String::with_capacity(format.len());
+        let mut chars = format.chars();
+        while let Some(c) = chars.next() {
+            if c != '%' {
+                localized.push(c);
+                continue;
+            }
+            match chars.next() {
+                Some('B') => localized.push_str(strings.months[month]),
+                Some('b' | 'h') => localized.push_str(strings.months_short[month]),
+                Some('A') => localized.push_str(strings.weekdays[weekday]),
+                Some('a') => localized.push_str(strings.weekdays_short[weekday]),
+                Some(other) => {
+                    localized.push('%');
+                    localized.push(other);
+                }
+                None => localized.push('%'),
+            }
+        }
+        self.shape_digits(&date.strftime(&localized).to_string())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn dates_are_formatted_in_the_language_of_the_locale() {
+        let date = jiff::civil::date(2025, 12, 24).at(14, 30, 0, 0);
+        assert_eq!(
+            Locale::En.strftime(&date, "%a %d %B %Y"),
+            "Wed 24 December 2025"
+        );
+        assert_eq!(Locale::De.strftime(&date, "%A %d %b"), "Mittwoch 24 Dez");
+        assert_eq!(Locale::Fr.strftime(&date, "%d %h %H:%M"), "24 déc. 14:30");
+        assert_eq!(Locale::He.strftime(&date, "%a %d %B"), "ד׳ 24 דצמבר");
+        assert_eq!(Locale::En.strftime(&date, "100%% %d"), "100% 24");
+    }
+
+    #[test]
+    fn arabic_dates_have_arabic_indic_digits() {
+        let date = jiff::civil::date(2025, 12, 24).at(14, 30, 0, 0);
+        assert_eq!(Locale::Ar.strftime(&date, "%d %B %Y"), "٢٤ ديسمبر ٢٠٢٥");
+        assert_eq!(Locale::Ar.strftime(&date, "%a"), "الأربعاء");
+        assert_eq!(Locale::Ar.shape_digits("Nr. 12"), "Nr. ١٢");
+        assert_eq!(Locale::He.shape_digits("12"), "12");
+    }
+}
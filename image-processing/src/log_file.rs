@@ -0,0 +1,78 @@
+use std::path::PathBuf;
+
+use serde::{Deserialize, Serialize};
+use tracing_appender::rolling::{RollingFileAppender, Rotation};
+
+use crate::error::AppError;
+
+/// Folder in the data directory the logs are written to by default.
+const LOG_DIR: &str = "logs";
+
+/// Where and how the log is written to a file, for support to see what happened in a run.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct LogFile {
+    pub enabled: bool,
+    pub rotation: LogRotation,
+    /// How many logs are kept, the oldest are removed
+    pub keep: usize,
+    /// Defaults to the logs folder in the data directory
+    pub dir: Option<PathBuf>,
+}
+
+impl Default for LogFile {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            rotation: LogRotation::Daily,
+            keep: 7,
+            dir: None,
+        }
+    }
+}
+
+/// When a new log file is started.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum LogRotation {
+    /// A log per hour, for long runs that log a lot
+    Hourly,
+    /// A log per day
+    Daily,
+}
+
+impl From<LogRotation> for Rotation {
+    fn from(rotation: LogRotation) -> Self {
+        match rotation {
+            LogRotation::Hourly => Rotation::HOURLY,
+            LogRotation::Daily => Rotation::DAILY,
+        }
+    }
+}
+
+impl LogFile {
+    /// The folder the logs are written to.
+    pub fn dir(&self) -> Result<PathBuf, AppError> {
+        match &self.dir {
+            Some(dir) => Ok(dir.clone()),
+            None => directories::ProjectDirs::from("", "", "photo-bench-ui")
+                .map(|d| d.data_dir().join(LOG_DIR))
+                .ok_or(AppError::NoDataDir),
+        }
+    }
+
+    /// A writer for `tracing_subscriber::fmt` that writes the logs named
+    /// `<name>.<date>.log` into [`Self::dir`], or `None` when the log file is not enabled.
+    pub fn writer(&self, name: &str) -> Result<Option<RollingFileAppender>, AppError> {
+        if !self.enabled {
+            return Ok(None);
+        }
+        let appender = RollingFileAppender::builder()
+            .rotation(self.rotation.into())
+            .filename_prefix(name)
+            .filename_suffix("log")
+            .max_log_files(self.keep.max(1))
+            .build(self.dir()?)?;
+        Ok(Some(appender))
+    }
+}
@@ -1,24 +1,440 @@
 use clap::Parser;
 
-use photo_date_bench::{error::AppError, App};
+use std::collections::HashMap;
+use std::io::{self, IsTerminal, Write};
+use std::path::PathBuf;
+use std::process::ExitCode;
+use std::sync::{Arc, Mutex};
+use std::thread::{self, ThreadId};
+use std::{env, fs};
+
+use image::Rgba;
+use indicatif::{MultiProgress, ProgressBar, ProgressStyle};
+use photo_date_bench::{
+    cli::{Cli, Command, ConfigCommand, FontsCommand},
+    completions,
+    config::{self, Config},
+    crash,
+    draw_text::FontSize,
+    error::AppError,
+    fonts, presets,
+    progress::{JsonLinesProgress, ProgressEvent, ProgressFormat, ProgressSink},
+};
 use tracing_subscriber::{
     fmt::format::FmtSpan, prelude::__tracing_subscriber_SubscriberExt, util::SubscriberInitExt,
+    Layer,
 };
 
-fn main() -> Result<(), AppError> {
+fn main() -> ExitCode {
+    match run() {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(e) => {
+            eprintln!("Error: {e}");
+            ExitCode::FAILURE
+        }
+    }
+}
+
+fn run() -> Result<(), AppError> {
+    let cli = Cli::parse();
+    // A dropped folder runs in a console window of its own that closes when the program ends
+    let dropped =
+        cli.command.is_none() && cli.app.as_ref().is_some_and(|app| app.is_dropped_folder());
+    crash::install(cli.crash_options(), dropped);
+    // The log lines would break up the progress bars, so only warnings are logged with them
+    let show_progress = matches!(&cli.app, Some(app) if !app.quiet && app.progress_format == ProgressFormat::Bars)
+        && cli.command.is_none()
+        && io::stderr().is_terminal();
+    let default_level = if show_progress { "warn" } else { "info" };
+
+    // Log to stderr so the reports printed to stdout can be piped
     #[cfg(target_os = "windows")]
     let events = tracing_subscriber::fmt::layer()
         .with_span_events(FmtSpan::CLOSE)
+        .with_writer(io::stderr)
         .with_ansi(false);
     #[cfg(not(target_os = "windows"))]
-    let events = tracing_subscriber::fmt::layer().with_span_events(FmtSpan::CLOSE);
+    let events = tracing_subscriber::fmt::layer()
+        .with_span_events(FmtSpan::CLOSE)
+        .with_writer(io::stderr);
+    // The log file gets the info lines too, the progress bars only hide them on the terminal
+    let config_path = cli.app.as_ref().and_then(|app| app.config.as_deref());
+    let mut log_file = Config::load(config_path).map(|c| c.log).unwrap_or_default();
+    if cli.app.as_ref().is_some_and(|app| app.log_file) {
+        log_file.enabled = true;
+    }
+    let file_events = log_file.writer("photo-date-bench")?.map(|writer| {
+        tracing_subscriber::fmt::layer()
+            .with_span_events(FmtSpan::CLOSE)
+            .with_writer(writer)
+            .with_ansi(false)
+            .with_filter(
+                tracing_subscriber::EnvFilter::try_from_default_env()
+                    .unwrap_or_else(|_| "info".into()),
+            )
+    });
     tracing_subscriber::registry()
         .with(
-            tracing_subscriber::EnvFilter::try_from_default_env().unwrap_or_else(|_| "info".into()),
+            events.with_filter(
+                tracing_subscriber::EnvFilter::try_from_default_env()
+                    .unwrap_or_else(|_| default_level.into()),
+            ),
         )
-        .with(events)
+        .with(file_events)
         .init();
-    let app = App::parse();
 
-    photo_date_bench::run_image_processing(app)
+    match (cli.command, cli.app) {
+        (
+            Some(Command::Stats {
+                target,
+                processed_with,
+            }),
+            _,
+        ) => {
+            let config = processed_with.config()?;
+            let stats = photo_date_bench::stats::collect_stats(&target, &config)?;
+            print!("{stats}");
+            Ok(())
+        }
+        (
+            Some(Command::Verify {
+                target,
+                processed_with,
+            }),
+            _,
+        ) => {
+            let config = processed_with.config()?;
+            let report = photo_date_bench::verify::verify_target(&target, &config)?;
+            println!("{}", serde_json::to_string_pretty(&report)?);
+            if report.issues.is_empty() {
+                Ok(())
+            } else {
+                Err(AppError::VerifyFailed(report.issues.len()))
+            }
+        }
+        (
+            Some(Command::Diff {
+                source,
+                target,
+                processed_with,
+            }),
+            _,
+        ) => {
+            let config = processed_with.config()?;
+            let report = photo_date_bench::diff::diff(&source, &target, &config)?;
+            println!("{}", serde_json::to_string_pretty(&report)?);
+            if report.is_empty() {
+                Ok(())
+            } else {
+                Err(AppError::Differences(report.len()))
+            }
+        }
+        (
+            Some(Command::Sheets {
+                target,
+                processed_with,
+                per_page,
+                out,
+                pdf,
+                locale,
+            }),
+            _,
+        ) => {
+            let mut config = processed_with.config()?;
+            if let Some(locale) = locale {
+                config.locale = locale;
+            }
+            let written =
+                photo_date_bench::sheet::compose_sheets(&target, &config, per_page, &out, pdf)?;
+            for path in &written {
+                println!("{}", path.display());
+            }
+            Ok(())
+        }
+        (
+            Some(Command::Dedupe {
+                source,
+                config,
+                move_to,
+                max_distance,
+            }),
+            _,
+        ) => {
+            let config = Config::load(config.as_deref())?;
+            let groups = photo_date_bench::dedupe::find_duplicates(&source, &config, max_distance)?;
+            for group in &groups {
+                print!("{group}");
+            }
+            println!("Found {} group(s) of duplicates", groups.len());
+            if let Some(move_to) = move_to {
+                photo_date_bench::dedupe::move_duplicates(
+                    &groups,
+                    &source,
+                    &move_to,
+                    config.trash,
+                )?;
+            }
+            Ok(())
+        }
+        (
+            Some(Command::Config {
+                command: ConfigCommand::Init { global, force },
+            }),
+            _,
+        ) => {
+            let dir = if global {
+                config::global_config_dir().ok_or(AppError::NoConfigDir)?
+            } else {
+                PathBuf::from(".")
+            };
+            let path = config::write_default_config(&dir, force)?;
+            println!("Wrote the default config to {}", path.display());
+            Ok(())
+        }
+        (Some(Command::Calibration { config, out }), _) => {
+            let config = Config::load(config.as_deref())?;
+            photo_date_bench::calibration::render_calibration(&config, &out)?;
+            println!("Wrote the calibration page to {}", out.display());
+            Ok(())
+        }
+        (
+            Some(Command::ImportLegacy {
+                legacy,
+                target,
+                config,
+                ocr,
+            }),
+            _,
+        ) => {
+            let config = Config::load(config.as_deref())?;
+            let import = photo_date_bench::legacy::import_legacy(&legacy, &target, &config, ocr)?;
+            print!("{import}");
+            Ok(())
+        }
+        #[cfg(feature = "self-update")]
+        (Some(Command::SelfUpdate { check }), _) => {
+            let outcome = photo_date_bench::self_update::self_update(check)?;
+            println!("{outcome}");
+            Ok(())
+        }
+        (Some(Command::Completions { shell }), _) => {
+            completions::write_completions(shell, &mut io::stdout().lock())?;
+            Ok(())
+        }
+        (Some(Command::Manpage), _) => {
+            completions::write_manpage(&mut io::stdout().lock())?;
+            Ok(())
+        }
+        (Some(Command::InstallSendTo), _) => {
+            let path = install_send_to()?;
+            println!("Added {} to the Send to menu", path.display());
+            Ok(())
+        }
+        (
+            Some(Command::Fonts {
+                command: FontsCommand::List,
+            }),
+            _,
+        ) => {
+            println!("Bundled fonts:");
+            for name in fonts::BUNDLED_FONTS {
+                println!("  {name}");
+            }
+            println!("Installed fonts:");
+            for font in fonts::system_fonts() {
+                println!("  {font}");
+            }
+            Ok(())
+        }
+        (Some(Command::Presets), _) => {
+            for preset in presets::presets() {
+                println!("{}", preset.name);
+                println!("  {}", preset.description);
+            }
+            Ok(())
+        }
+        (
+            Some(Command::Fonts {
+                command:
+                    FontsCommand::Preview {
+                        family,
+                        text,
+                        size_pt,
+                        out,
+                    },
+            }),
+            _,
+        ) => {
+            let config = Config::load(None)?;
+            let font_size = FontSize {
+                pt: size_pt,
+                dpi: config.dpi,
+            };
+            fonts::render_preview(&family, &text, font_size, Rgba([0, 0, 0, 255]), &out)?;
+            println!("Wrote the preview to {}", out.display());
+            Ok(())
+        }
+        (None, Some(mut app)) => {
+            if dropped {
+                let config = Config::load(app.config.as_deref())?;
+                app.sources = vec![std::mem::take(&mut app.target)];
+                app.target = config.dropped_target(&app.sources[0]);
+                let question = format!(
+                    "Process the photos in {} into {}?",
+                    app.sources[0].display(),
+                    app.target.display()
+                );
+                if !confirm(&question)? {
+                    return Err(AppError::Aborted);
+                }
+            }
+            let question = match app.trash {
+                true => {
+                    "The originals will be moved to the recycle bin after processing. Continue?"
+                }
+                false => "The originals will be deleted after processing. Continue?",
+            };
+            if app.delete_originals && !confirm(question)? {
+                return Err(AppError::Aborted);
+            }
+            let progress: Option<Arc<dyn ProgressSink>> = match app.progress_format {
+                ProgressFormat::Json => Some(Arc::new(JsonLinesProgress::new(io::stdout()))),
+                ProgressFormat::Bars if show_progress => Some(Arc::new(ProgressBars::new())),
+                ProgressFormat::Bars => None,
+            };
+            let result = photo_date_bench::run_image_processing(app, progress);
+            // Mistakes in the command, the config or the photos are only printed
+            if let Err(e) = &result
+                && e.is_internal()
+                && let Some(path) = crash::write_report(&format!("{e}\n{e:?}"), None)
+            {
+                eprintln!("A crash report was written to {}", path.display());
+            }
+            if dropped {
+                if let Err(e) = &result {
+                    eprintln!("{e}");
+                }
+                print!("Press Enter to close");
+                io::stdout().flush()?;
+                io::stdin().read_line(&mut String::new())?;
+            }
+            result
+        }
+        (None, None) => unreachable!("clap requires either a subcommand or the image arguments"),
+    }
+}
+
+/// Asks a yes or no question on the terminal. Anything but yes is a no.
+fn confirm(question: &str) -> Result<bool, AppError> {
+    print!("{question} [y/N] ");
+    io::stdout().flush()?;
+    let mut answer = String::new();
+    io::stdin().read_line(&mut answer)?;
+    Ok(matches!(answer.trim().to_lowercase().as_str(), "y" | "yes"))
+}
+
+/// Writes a script into the Send to folder of the user that runs the program on the folder
+/// that is sent to it.
+fn install_send_to() -> Result<PathBuf, AppError> {
+    let appdata = env::var_os("APPDATA").ok_or(AppError::NoSendToDir)?;
+    let dir = PathBuf::from(appdata).join("Microsoft\\Windows\\SendTo");
+    if !dir.is_dir() {
+        return Err(AppError::NoSendToDir);
+    }
+    let exe = env::current_exe()?;
+    let path = dir.join("Photo Date Bench.cmd");
+    fs::write(&path, format!("@echo off\r\n\"{}\" %1\r\n", exe.display()))?;
+    Ok(path)
+}
+
+/// Terminal progress with an overall bar and a line per worker with the file it is on.
+struct ProgressBars {
+    bars: MultiProgress,
+    overall: ProgressBar,
+    workers: Mutex<HashMap<ThreadId, ProgressBar>>,
+}
+
+impl ProgressBars {
+    fn new() -> Self {
+        let bars = MultiProgress::new();
+        let overall = bars.add(ProgressBar::new(0));
+        overall.set_style(
+            ProgressStyle::with_template(
+                "{bar:40} {pos}/{len} {percent}% {per_sec} ETA {eta} {msg}",
+            )
+            .expect("Valid progress template"),
+        );
+        Self {
+            bars,
+            overall,
+            workers: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// The line of the worker thread the event is reported from.
+    fn worker(&self) -> ProgressBar {
+        let mut workers = self.workers.lock().unwrap_or_else(|e| e.into_inner());
+        workers
+            .entry(thread::current().id())
+            .or_insert_with(|| {
+                let bar = self.bars.add(ProgressBar::new_spinner());
+                bar.set_style(
+                    ProgressStyle::with_template("  {spinner} {msg}")
+                        .expect("Valid progress template"),
+                );
+                bar
+            })
+            .clone()
+    }
+}
+
+impl ProgressSink for ProgressBars {
+    fn event(&self, event: &ProgressEvent) {
+        match event {
+            ProgressEvent::Scanned { total } => self.overall.set_length(*total as u64),
+            ProgressEvent::Started { file } => {
+                let worker = self.worker();
+                worker.set_message(file.display().to_string());
+                worker.tick();
+            }
+            ProgressEvent::Stage { file, stage } => {
+                let worker = self.worker();
+                worker.set_message(format!("{} ({})", file.display(), stage.name()));
+                worker.tick();
+            }
+            // Failures are logged by the library
+            ProgressEvent::Finished { .. } | ProgressEvent::Failed { .. } => {
+                self.worker().set_message("");
+                self.overall.inc(1);
+            }
+            // The plan of a dry run is logged at info, which the bars hide
+            ProgressEvent::Planned {
+                file, output, date, ..
+            } => {
+                let line = format!("{} ({date}) → {}", file.display(), output.display());
+                if self.bars.println(&line).is_err() {
+                    eprintln!("{line}");
+                }
+                self.overall.inc(1);
+            }
+            // Printed to stdout, as the info lines it is also logged as are hidden
+            ProgressEvent::Summary { lines } => {
+                for line in *lines {
+                    println!("{line}");
+                }
+            }
+            ProgressEvent::Warning { .. } | ProgressEvent::Skipped { .. } => {}
+            ProgressEvent::Done { failed, .. } => {
+                let workers = self.workers.lock().unwrap_or_else(|e| e.into_inner());
+                for worker in workers.values() {
+                    worker.finish_and_clear();
+                }
+                if *failed > 0 {
+                    self.overall.finish_with_message(format!("{failed} failed"));
+                } else {
+                    self.overall.finish();
+                }
+            }
+        }
+    }
 }
@@ -0,0 +1,148 @@
+use std::collections::{HashMap, HashSet};
+use std::fs::{File, OpenOptions};
+use std::io::{BufRead, BufReader, Write};
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+
+use jiff::civil::DateTime;
+use serde::{Deserialize, Serialize};
+use tracing::warn;
+
+use crate::error::AppError;
+use crate::naming::NameTemplate;
+use crate::numbering::NumberRange;
+use crate::quality::QualityWarning;
+
+/// Name of the manifest file kept in the root of the target folder.
+pub const MANIFEST_FILE_NAME: &str = "photo-bench-manifest.jsonl";
+
+/// One processed photo. The manifest has one json line of this per output file.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ManifestEntry {
+    pub number: usize,
+    /// The original file that was processed
+    pub source: PathBuf,
+    /// Path of the output relative to the target folder
+    pub output: PathBuf,
+    pub date: DateTime,
+    /// SHA-256 of the output file
+    pub sha256: String,
+    /// SHA-256 of the original, when its start was stamped on the photo
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub original_sha256: Option<String>,
+    /// The person who did the run
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub operator: Option<String>,
+    /// The range of numbers the bench was limited to
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub number_range: Option<NumberRange>,
+    /// Why the photo might have to be retaken
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub quality_warnings: Vec<QualityWarning>,
+    /// The run that processed the photo, so an interrupted run knows what it finished
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub run_id: Option<String>,
+}
+
+/// Append-only writer for the manifest of a target folder, shared between the worker threads.
+#[derive(Debug)]
+pub struct Manifest {
+    file: Mutex<File>,
+}
+
+impl Manifest {
+    pub fn open(target: &Path) -> Result<Self, AppError> {
+        let file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(target.join(MANIFEST_FILE_NAME))?;
+        Ok(Self {
+            file: Mutex::new(file),
+        })
+    }
+
+    pub fn record(&self, entry: &ManifestEntry) -> Result<(), AppError> {
+        let mut line = serde_json::to_string(entry)?;
+        line.push('\n');
+        // A poisoned lock only means another worker panicked mid write, the file is still usable
+        let mut file = self.file.lock().unwrap_or_else(|e| e.into_inner());
+        file.write_all(line.as_bytes())?;
+        Ok(())
+    }
+}
+
+/// Reads all the entries in the manifest of the target folder.
+/// Returns an empty list when the target has no manifest yet.
+pub fn read_manifest(target: &Path) -> Result<Vec<ManifestEntry>, AppError> {
+    read_manifest_file(&target.join(MANIFEST_FILE_NAME))
+}
+
+/// Reads all the entries in a manifest file, e.g. one fetched from a remote target.
+pub fn read_manifest_file(path: &Path) -> Result<Vec<ManifestEntry>, AppError> {
+    if !path.exists() {
+        return Ok(vec![]);
+    }
+    let reader = BufReader::new(File::open(path)?);
+    let mut entries = vec![];
+    for (i, line) in reader.lines().enumerate() {
+        let line = line?;
+        if line.trim().is_empty() {
+            continue;
+        }
+        match serde_json::from_str(&line) {
+            Ok(entry) => entries.push(entry),
+            // A crash can leave a half written last line
+            Err(e) => warn!("{e}. Skipping line {} of {path:?}", i + 1),
+        }
+    }
+    Ok(entries)
+}
+
+/// Tells the outputs of earlier runs from other photos, reading the manifest of a folder once.
+#[derive(Debug, Default)]
+pub struct KnownOutputs {
+    /// The outputs recorded in each folder, `None` for the folders without a manifest
+    folders: HashMap<PathBuf, Option<HashSet<PathBuf>>>,
+}
+
+impl KnownOutputs {
+    /// Whether the file is an output of an earlier run. Its name has to fit the template, and
+    /// when the template has free text the manifest of a folder it is in has to record it.
+    pub fn is_output(&mut self, template: &NameTemplate, path: &Path) -> bool {
+        template.matches(path) && (!template.has_free_text() || self.recorded(path))
+    }
+
+    /// Whether a source was numbered before, like [`Self::is_output`] but for sources of any
+    /// image type, see [`NameTemplate::matches_source`].
+    pub fn is_numbered_source(&mut self, template: &NameTemplate, path: &Path) -> bool {
+        template.matches_source(path) && (!template.has_free_text() || self.recorded(path))
+    }
+
+    fn recorded(&mut self, path: &Path) -> bool {
+        for dir in path.ancestors().skip(1) {
+            let outputs = self
+                .folders
+                .entry(dir.to_path_buf())
+                .or_insert_with(|| recorded_outputs(dir));
+            if let (Some(outputs), Ok(relative)) = (outputs, path.strip_prefix(dir))
+                && outputs.contains(relative)
+            {
+                return true;
+            }
+        }
+        false
+    }
+}
+
+fn recorded_outputs(dir: &Path) -> Option<HashSet<PathBuf>> {
+    if !dir.join(MANIFEST_FILE_NAME).exists() {
+        return None;
+    }
+    match read_manifest(dir) {
+        Ok(entries) => Some(entries.into_iter().map(|entry| entry.output).collect()),
+        Err(e) => {
+            warn!("{e}. Could not read the manifest in {dir:?}");
+            None
+        }
+    }
+}
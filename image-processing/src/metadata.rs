@@ -0,0 +1,660 @@
+use exif::{Exif, Field, In, Tag, Value};
+use jiff::civil::DateTime;
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use std::fs::File;
+use std::io::{BufRead, BufReader, Read};
+use std::path::Path;
+use std::sync::LazyLock;
+use tracing::debug;
+
+use crate::error::AppError;
+
+fn read_exif<P: AsRef<Path>>(file_path: P) -> Result<Option<Exif>, AppError> {
+    let file = File::open(file_path)?;
+    let mut reader = BufReader::new(file);
+    let exifreader = exif::Reader::new();
+    match exifreader.read_from_container(&mut reader) {
+        Ok(e) => Ok(Some(e)),
+        Err(exif::Error::NotFound(e)) => {
+            debug!(
+                "{e}. Exif data not found. Probably an image we already processed. Returning none"
+            );
+            Ok(None)
+        }
+        Err(e) => Err(e.into()),
+    }
+}
+
+/// Signature every PNG file starts with.
+const PNG_SIGNATURE: &[u8] = b"\x89PNG\r\n\x1a\n";
+
+/// Keyword of the PNG text chunk with the XMP packet.
+const XMP_KEYWORD: &str = "XML:com.adobe.xmp";
+
+/// XMP properties with the date a photo was taken, the most precise first.
+const XMP_DATE_PROPERTIES: [&str; 3] = [
+    "exif:DateTimeOriginal",
+    "photoshop:DateCreated",
+    "xmp:CreateDate",
+];
+
+/// A property in an XMP packet, written as an attribute or as an element.
+static XMP_PROPERTY: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(r#"([A-Za-z]+:[A-Za-z]+)\s*(?:=\s*"([^"]*)"|>([^<]*)<)"#)
+        .expect("Valid XMP property regex")
+});
+
+/// The date the photo was taken, from the EXIF data or, for PNGs like screenshots that
+/// have none, from the PNG text chunks.
+pub fn get_image_date<P: AsRef<Path>>(file_path: P) -> Result<Option<DateTime>, AppError> {
+    let file_path = file_path.as_ref();
+    if let Some(date) = get_exif_date(file_path, Tag::DateTimeOriginal, Tag::SubSecTimeOriginal)? {
+        return Ok(Some(date));
+    }
+    get_png_text_date(file_path)
+}
+
+/// Reads an EXIF date like `DateTimeOriginal` (Tag 36867), with the fractions of the second
+/// in `subsec_tag`.
+pub(crate) fn get_exif_date(
+    file_path: &Path,
+    date_tag: Tag,
+    subsec_tag: Tag,
+) -> Result<Option<DateTime>, AppError> {
+    let Some(exif) = read_exif(file_path)? else {
+        return Ok(None);
+    };
+
+    let Some(Field {
+        value: Value::Ascii(dates),
+        ..
+    }) = exif.get_field(date_tag, In::PRIMARY)
+    else {
+        debug!("No {date_tag} found");
+        return Ok(None);
+    };
+
+    // The dates value is a vector of bytes, convert it to a string.
+    // The standard EXIF format is "YYYY:MM:DD HH:MM:SS"
+    let date_str = String::from_utf8(dates[0].clone())?;
+    let date = match DateTime::strptime("%Y:%m:%d %H:%M:%S", &date_str) {
+        Ok(date) => date,
+        Err(e) => {
+            debug!("{e}. Could not parse {date_str} as jiff Timestamp.");
+            return Ok(None);
+        }
+    };
+
+    // Photos taken in the same second are only ordered by the fractions in "SubSecTimeOriginal"
+    let subsec = match exif.get_field(subsec_tag, In::PRIMARY) {
+        Some(Field {
+            value: Value::Ascii(subsec),
+            ..
+        }) => subsec.first().and_then(|s| subsec_nanos(s)),
+        _ => None,
+    };
+    match subsec {
+        Some(nanos) => Ok(Some(date.with().subsec_nanosecond(nanos).build()?)),
+        None => Ok(Some(date)),
+    }
+}
+
+/// Reads the date from the XMP packet of a jpeg, which editors like Lightroom write.
+pub(crate) fn get_jpeg_xmp_date(file_path: &Path) -> Result<Option<DateTime>, AppError> {
+    Ok(read_jpeg_xmp(file_path)?.and_then(|xmp| get_xmp_date(&xmp)))
+}
+
+/// The XMP packet of a jpeg. Only the segments before the pixels are read.
+fn read_jpeg_xmp(file_path: &Path) -> Result<Option<String>, AppError> {
+    let mut reader = BufReader::new(File::open(file_path)?);
+    let mut marker = [0; 2];
+    if reader.read_exact(&mut marker).is_err() || marker != [0xFF, 0xD8] {
+        return Ok(None);
+    }
+    loop {
+        let mut header = [0; 4];
+        if reader.read_exact(&mut header).is_err() || header[0] != 0xFF {
+            return Ok(None);
+        }
+        // The pixels start at the start of scan, the XMP is before it
+        if header[1] == 0xDA {
+            return Ok(None);
+        }
+        let length = usize::from(u16::from_be_bytes([header[2], header[3]])).saturating_sub(2);
+        let mut segment = vec![0; length];
+        reader.read_exact(&mut segment)?;
+        if header[1] == 0xE1
+            && let Some(xmp) = segment.strip_prefix(XMP_HEADER)
+        {
+            return Ok(Some(String::from_utf8_lossy(xmp).into_owned()));
+        }
+    }
+}
+
+/// Reads the date from the `Creation Time` text chunk or the XMP packet of a PNG. Only the
+/// chunks before the pixels are read, which is where screenshot tools write them.
+pub(crate) fn get_png_text_date(file_path: &Path) -> Result<Option<DateTime>, AppError> {
+    let mut reader = BufReader::new(File::open(file_path)?);
+    if !reader.fill_buf()?.starts_with(PNG_SIGNATURE) {
+        return Ok(None);
+    }
+    let png = match png::Decoder::new(reader).read_info() {
+        Ok(png) => png,
+        Err(e) => {
+            debug!("{e}. Could not read the text chunks of {file_path:?}");
+            return Ok(None);
+        }
+    };
+    let info = png.info();
+
+    let mut texts: Vec<(String, String)> = info
+        .uncompressed_latin1_text
+        .iter()
+        .map(|c| (c.keyword.clone(), c.text.clone()))
+        .collect();
+    for chunk in &info.compressed_latin1_text {
+        match chunk.get_text() {
+            Ok(text) => texts.push((chunk.keyword.clone(), text)),
+            Err(e) => debug!("{e}. Could not decompress the {} chunk", chunk.keyword),
+        }
+    }
+    for chunk in &info.utf8_text {
+        match chunk.get_text() {
+            Ok(text) => texts.push((chunk.keyword.clone(), text)),
+            Err(e) => debug!("{e}. Could not decompress the {} chunk", chunk.keyword),
+        }
+    }
+
+    let creation_time = texts
+        .iter()
+        .filter(|(keyword, _)| keyword == "Creation Time")
+        .find_map(|(_, text)| parse_text_date(text));
+    if creation_time.is_some() {
+        return Ok(creation_time);
+    }
+    let xmp = texts
+        .iter()
+        .filter(|(keyword, _)| keyword == XMP_KEYWORD)
+        .find_map(|(_, text)| get_xmp_date(text));
+    if xmp.is_none() {
+        debug!("No date found in the text chunks of {file_path:?}");
+    }
+    Ok(xmp)
+}
+
+/// The date the photo was taken from an XMP packet.
+fn get_xmp_date(xmp: &str) -> Option<DateTime> {
+    let properties: Vec<(&str, &str)> = XMP_PROPERTY
+        .captures_iter(xmp)
+        .filter_map(|c| {
+            let value = c.get(2).or_else(|| c.get(3))?;
+            Some((c.get(1)?.as_str(), value.as_str()))
+        })
+        .collect();
+    XMP_DATE_PROPERTIES.iter().find_map(|property| {
+        properties
+            .iter()
+            .filter(|(name, _)| name == property)
+            .find_map(|(_, value)| parse_text_date(value))
+    })
+}
+
+/// Parses a date as written in PNG text chunks and XMP: RFC 2822 as the PNG spec suggests for
+/// `Creation Time`, ISO 8601 as XMP uses, or the EXIF format. The time is kept as written,
+/// like the EXIF date.
+fn parse_text_date(text: &str) -> Option<DateTime> {
+    let text = text.trim();
+    if let Ok(date) = jiff::fmt::rfc2822::parse(text) {
+        return Some(date.datetime());
+    }
+    if let Ok(date) = text.trim_end_matches(['Z', 'z']).parse::<DateTime>() {
+        return Some(date);
+    }
+    match DateTime::strptime("%Y:%m:%d %H:%M:%S", text) {
+        Ok(date) => Some(date),
+        Err(e) => {
+            debug!("{e}. Could not parse {text} as a date");
+            None
+        }
+    }
+}
+
+/// Converts the digits of a fraction of a second, e.g. `"25"` for 0.25s, to nanoseconds.
+fn subsec_nanos(digits: &[u8]) -> Option<i32> {
+    let digits = std::str::from_utf8(digits)
+        .ok()?
+        .trim_matches(char::from(0))
+        .trim();
+    if digits.is_empty() || !digits.bytes().all(|b| b.is_ascii_digit()) {
+        return None;
+    }
+    format!("{digits:0<9}")[..9].parse().ok()
+}
+
+/// Reads the EXIF `Orientation` tag, from 1 for upright to 8, if the file has one.
+pub fn get_orientation<P: AsRef<Path>>(file_path: P) -> Result<Option<u32>, AppError> {
+    let Some(exif) = read_exif(file_path)? else {
+        return Ok(None);
+    };
+    Ok(exif
+        .get_field(Tag::Orientation, In::PRIMARY)
+        .and_then(|f| f.value.get_uint(0)))
+}
+
+/// Reads the camera model from the EXIF `Model` tag, if the file has one.
+pub fn get_camera_model<P: AsRef<Path>>(file_path: P) -> Result<Option<String>, AppError> {
+    let Some(exif) = read_exif(file_path)? else {
+        return Ok(None);
+    };
+
+    let Some(Field {
+        value: Value::Ascii(models),
+        ..
+    }) = exif.get_field(Tag::Model, In::PRIMARY)
+    else {
+        return Ok(None);
+    };
+
+    let Some(model) = models.first() else {
+        return Ok(None);
+    };
+    let model = String::from_utf8(model.clone())?;
+    let model = model.trim_matches(char::from(0)).trim();
+    if model.is_empty() {
+        return Ok(None);
+    }
+    Ok(Some(model.to_string()))
+}
+
+/// Where a photo was taken, in degrees. South and west are negative.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct GpsPosition {
+    pub latitude: f64,
+    pub longitude: f64,
+}
+
+/// Reads the EXIF `GPSLatitude` and `GPSLongitude` with their references, if the file has both.
+pub fn get_gps_position<P: AsRef<Path>>(file_path: P) -> Result<Option<GpsPosition>, AppError> {
+    let Some(exif) = read_exif(file_path)? else {
+        return Ok(None);
+    };
+    // Degrees, minutes and seconds, negative when the reference is `negative`
+    let degrees = |tag: Tag, reference: Tag, negative: u8| {
+        let Value::Rational(parts) = &exif.get_field(tag, In::PRIMARY)?.value else {
+            return None;
+        };
+        let degrees: f64 = parts
+            .iter()
+            .zip([1.0, 60.0, 3600.0])
+            .map(|(part, per_degree)| part.to_f64() / per_degree)
+            .sum();
+        if !degrees.is_finite() {
+            return None;
+        }
+        let is_negative = exif.get_field(reference, In::PRIMARY).is_some_and(|f| {
+            matches!(&f.value, Value::Ascii(refs) if refs.first().and_then(|r| r.first()) == Some(&negative))
+        });
+        match is_negative {
+            true => Some(-degrees),
+            false => Some(degrees),
+        }
+    };
+    let latitude = degrees(Tag::GPSLatitude, Tag::GPSLatitudeRef, b'S');
+    let longitude = degrees(Tag::GPSLongitude, Tag::GPSLongitudeRef, b'W');
+    match (latitude, longitude) {
+        // Phones without a fix write zeros
+        (Some(latitude), Some(longitude)) if (latitude, longitude) != (0.0, 0.0) => {
+            Ok(Some(GpsPosition {
+                latitude,
+                longitude,
+            }))
+        }
+        _ => {
+            debug!("No GPS position found");
+            Ok(None)
+        }
+    }
+}
+
+/// The EXIF fields of an original that are worth keeping with the output.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct ExifSummary {
+    pub make: Option<String>,
+    pub model: Option<String>,
+    /// As written in the EXIF data, e.g. `2025:12:24 10:15:00`
+    pub date_time_original: Option<String>,
+    pub orientation: Option<u32>,
+    pub width: Option<u32>,
+    pub height: Option<u32>,
+}
+
+impl ExifSummary {
+    /// The make and model of the camera, e.g. `Canon EOS 80D`. The make is left out when the
+    /// model already starts with it.
+    pub fn camera_name(&self) -> Option<String> {
+        match (&self.make, &self.model) {
+            (Some(make), Some(model)) => {
+                let brand = make.split_whitespace().next().unwrap_or_default();
+                match model.to_lowercase().starts_with(&brand.to_lowercase()) {
+                    true => Some(model.clone()),
+                    false => Some(format!("{make} {model}")),
+                }
+            }
+            (make, model) => make.clone().or_else(|| model.clone()),
+        }
+    }
+}
+
+/// Reads a summary of the EXIF data of a file. Returns `None` when the file has no EXIF data.
+pub fn get_exif_summary<P: AsRef<Path>>(file_path: P) -> Result<Option<ExifSummary>, AppError> {
+    let Some(exif) = read_exif(file_path)? else {
+        return Ok(None);
+    };
+
+    let text = |tag: Tag| match exif.get_field(tag, In::PRIMARY) {
+        Some(Field {
+            value: Value::Ascii(values),
+            ..
+        }) => values
+            .first()
+            .map(|v| {
+                String::from_utf8_lossy(v)
+                    .trim_matches(char::from(0))
+                    .trim()
+                    .to_string()
+            })
+            .filter(|v| !v.is_empty()),
+        _ => None,
+    };
+    let number = |tag: Tag| {
+        exif.get_field(tag, In::PRIMARY)
+            .and_then(|f| f.value.get_uint(0))
+    };
+
+    Ok(Some(ExifSummary {
+        make: text(Tag::Make),
+        model: text(Tag::Model),
+        date_time_original: text(Tag::DateTimeOriginal),
+        orientation: number(Tag::Orientation),
+        width: number(Tag::PixelXDimension),
+        height: number(Tag::PixelYDimension),
+    }))
+}
+
+/// What is written into the EXIF and XMP data of an output.
+#[derive(Debug, Clone, Copy)]
+pub struct OutputTags<'a> {
+    /// Where the output came from, so other programs can read the number without OCR
+    pub ids: Option<OutputIds<'a>>,
+    /// The person who did the run, written as the Artist
+    pub operator: Option<&'a str>,
+    /// The date of the photo, written as the DateTimeOriginal and CreateDate so photo managers
+    /// sort the outputs by it
+    pub date: Option<&'a DateTime>,
+}
+
+/// The number, original and run of an output.
+#[derive(Debug, Clone, Copy)]
+pub struct OutputIds<'a> {
+    /// The number as stamped, e.g. `12` or `S-B`
+    pub number: &'a str,
+    /// The file name of the original
+    pub original: &'a str,
+    /// The run the output was made in
+    pub run_id: &'a str,
+}
+
+/// Header of the JPEG APP1 segment with the XMP packet.
+const XMP_HEADER: &[u8] = b"http://ns.adobe.com/xap/1.0/\0";
+
+/// The value of an entry written into the EXIF data.
+enum ExifValue {
+    Ascii(String),
+    Long(u32),
+}
+
+/// The length of an IFD with `entries`, without the values that do not fit in them.
+fn ifd_len<T>(entries: &[T]) -> usize {
+    2 + entries.len() * 12 + 4
+}
+
+impl OutputTags<'_> {
+    /// The EXIF data in TIFF format: the number as the ImageDescription, the original as the
+    /// DocumentName, the app as the Software, the operator as the Artist and the date as the
+    /// DateTimeOriginal and CreateDate.
+    pub fn exif(&self) -> Vec<u8> {
+        // The entries of an IFD are sorted by their tag
+        let mut primary = vec![];
+        if let Some(ids) = &self.ids {
+            primary.push((0x010D_u16, ExifValue::Ascii(ids.original.into())));
+            primary.push((0x010E, ExifValue::Ascii(ids.number.into())));
+        }
+        primary.push((0x0131, ExifValue::Ascii("photo-date-bench".into())));
+        if let Some(operator) = self.operator {
+            primary.push((0x013B, ExifValue::Ascii(operator.into())));
+        }
+        let mut exif = vec![];
+        if let Some(date) = self.date {
+            let text = date.strftime("%Y:%m:%d %H:%M:%S").to_string();
+            // CreateDate is what photo managers call the DateTimeDigitized
+            exif.push((0x9003_u16, ExifValue::Ascii(text.clone())));
+            exif.push((0x9004, ExifValue::Ascii(text)));
+            if date.subsec_nanosecond() != 0 {
+                let nanos = format!("{:09}", date.subsec_nanosecond());
+                let subsec = nanos.trim_end_matches('0');
+                exif.push((0x9291, ExifValue::Ascii(subsec.into())));
+                exif.push((0x9292, ExifValue::Ascii(subsec.into())));
+            }
+        }
+        // The dates are in the Exif IFD, which the primary one points to
+        if !exif.is_empty() {
+            let offset = 8 + ifd_len(&primary) + 12;
+            primary.push((0x8769, ExifValue::Long(offset as u32)));
+        }
+
+        let mut tiff = b"II*\0".to_vec();
+        tiff.extend_from_slice(&8u32.to_le_bytes());
+        let ifds: Vec<_> = [primary, exif]
+            .into_iter()
+            .filter(|ifd| !ifd.is_empty())
+            .collect();
+        let data_start = 8 + ifds.iter().map(|ifd| ifd_len(ifd)).sum::<usize>();
+        let mut data = vec![];
+        for ifd in ifds {
+            tiff.extend_from_slice(&(ifd.len() as u16).to_le_bytes());
+            for (tag, value) in ifd {
+                tiff.extend_from_slice(&tag.to_le_bytes());
+                let mut value = match value {
+                    ExifValue::Long(long) => {
+                        tiff.extend_from_slice(&4u16.to_le_bytes());
+                        tiff.extend_from_slice(&1u32.to_le_bytes());
+                        tiff.extend_from_slice(&long.to_le_bytes());
+                        continue;
+                    }
+                    ExifValue::Ascii(text) => text.into_bytes(),
+                };
+                value.push(0);
+                tiff.extend_from_slice(&2u16.to_le_bytes());
+                tiff.extend_from_slice(&(value.len() as u32).to_le_bytes());
+                // Values of up to 4 bytes are stored in the entry itself
+                if value.len() <= 4 {
+                    value.resize(4, 0);
+                    tiff.extend_from_slice(&value);
+                } else {
+                    let offset = data_start + data.len();
+                    tiff.extend_from_slice(&(offset as u32).to_le_bytes());
+                    data.extend_from_slice(&value);
+                }
+            }
+            // No next IFD
+            tiff.extend_from_slice(&0u32.to_le_bytes());
+        }
+        tiff.extend_from_slice(&data);
+        tiff
+    }
+
+    /// The XMP packet with the number, original and run, the number as the description and the
+    /// operator as the creator. `None` without the ids.
+    pub fn xmp(&self) -> Option<String> {
+        let ids = self.ids?;
+        let number = xml_escape(ids.number);
+        let original = xml_escape(ids.original);
+        let run_id = xml_escape(ids.run_id);
+        let creator = match self.operator {
+            Some(operator) => format!(
+                "\n   <dc:creator><rdf:Seq><rdf:li>{}</rdf:li></rdf:Seq></dc:creator>",
+                xml_escape(operator)
+            ),
+            None => String::new(),
+        };
+        Some(format!(
+            r#"<?xpacket begin="" id="W5M0MpCehiHzreSzNTczkc9d"?>
+<x:xmpmeta xmlns:x="adobe:ns:meta/">
+ <rdf:RDF xmlns:rdf="http://www.w3.org/1999/02/22-rdf-syntax-ns#">
+  <rdf:Description rdf:about=""
+    xmlns:dc="http://purl.org/dc/elements/1.1/"
+    xmlns:photobench="https://github.com/lanklaas/photo-date-bench/ns/1.0/"
+    photobench:Number="{number}"
+    photobench:Original="{original}"
+    photobench:RunId="{run_id}">
+   <dc:description><rdf:Alt><rdf:li xml:lang="x-default">{number}</rdf:li></rdf:Alt></dc:description>{creator}
+  </rdf:Description>
+ </rdf:RDF>
+</x:xmpmeta>
+<?xpacket end="w"?>"#
+        ))
+    }
+
+    /// Adds the XMP packet to an encoded jpeg, after its APP segments.
+    pub fn insert_xmp(&self, jpeg: &mut Vec<u8>) {
+        let Some(xmp) = self.xmp() else {
+            return;
+        };
+        let mut segment = XMP_HEADER.to_vec();
+        segment.extend_from_slice(xmp.as_bytes());
+        // The length counts itself, but not the marker
+        let Ok(len) = u16::try_from(segment.len() + 2) else {
+            debug!("The XMP packet is too big for a jpeg segment");
+            return;
+        };
+
+        // Skip the SOI marker and the APP0 (JFIF), APP1 (EXIF) and APP2 (ICC) segments
+        let mut at = 2;
+        while jpeg.len() > at + 4 && jpeg[at] == 0xFF && (0xE0..=0xEF).contains(&jpeg[at + 1]) {
+            at += 2 + u16::from_be_bytes([jpeg[at + 2], jpeg[at + 3]]) as usize;
+        }
+        let mut header = vec![0xFF, 0xE1];
+        header.extend_from_slice(&len.to_be_bytes());
+        header.extend_from_slice(&segment);
+        jpeg.splice(at..at, header);
+    }
+}
+
+fn xml_escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+#[cfg(test)]
+mod tests {
+    use image::codecs::jpeg::JpegEncoder;
+    use image::{ImageEncoder, RgbImage};
+    use jiff::civil::date;
+
+    use super::*;
+    use crate::test_util::temp_dir;
+
+    /// Encodes a small jpeg with the tags, the way the outputs are written.
+    fn tagged_jpeg(tags: &OutputTags, path: &Path) {
+        let mut jpeg = vec![];
+        let mut encoder = JpegEncoder::new_with_quality(&mut jpeg, 90);
+        encoder.set_exif_metadata(tags.exif()).unwrap();
+        let img = RgbImage::from_pixel(32, 24, image::Rgb([200, 120, 40]));
+        encoder
+            .write_image(&img, 32, 24, image::ExtendedColorType::Rgb8)
+            .unwrap();
+        tags.insert_xmp(&mut jpeg);
+        std::fs::write(path, jpeg).unwrap();
+    }
+
+    /// Tag 269, which the exif crate has no name for.
+    const DOCUMENT_NAME: Tag = Tag(exif::Context::Tiff, 0x010D);
+
+    fn text(exif: &Exif, tag: Tag) -> Option<String> {
+        let field = exif.get_field(tag, In::PRIMARY)?;
+        match &field.value {
+            Value::Ascii(values) => Some(String::from_utf8(values[0].clone()).unwrap()),
+            _ => None,
+        }
+    }
+
+    #[test]
+    fn output_tags_read_back_from_the_jpeg() {
+        let dir = temp_dir("output-tags");
+        let path = dir.path().join("12.jpg");
+        let taken = date(2025, 12, 24).at(10, 15, 30, 250_000_000);
+        let tags = OutputTags {
+            ids: Some(OutputIds {
+                number: "12",
+                original: "IMG_1234.JPG",
+                run_id: "20251224T081500Z-4242-0",
+            }),
+            operator: Some("Anna & <Ben>"),
+            date: Some(&taken),
+        };
+        tagged_jpeg(&tags, &path);
+
+        let exif = read_exif(&path).unwrap().unwrap();
+        assert_eq!(text(&exif, Tag::ImageDescription).as_deref(), Some("12"));
+        assert_eq!(text(&exif, DOCUMENT_NAME).as_deref(), Some("IMG_1234.JPG"));
+        assert_eq!(
+            text(&exif, Tag::Software).as_deref(),
+            Some("photo-date-bench")
+        );
+        assert_eq!(text(&exif, Tag::Artist).as_deref(), Some("Anna & <Ben>"));
+        assert_eq!(
+            text(&exif, Tag::DateTimeOriginal).as_deref(),
+            Some("2025:12:24 10:15:30")
+        );
+        assert_eq!(text(&exif, Tag::SubSecTimeOriginal).as_deref(), Some("25"));
+        assert_eq!(get_image_date(&path).unwrap(), Some(taken));
+
+        let xmp = read_jpeg_xmp(&path).unwrap().unwrap();
+        assert!(xmp.contains(r#"photobench:Number="12""#), "{xmp}");
+        assert!(
+            xmp.contains(r#"photobench:Original="IMG_1234.JPG""#),
+            "{xmp}"
+        );
+        assert!(
+            xmp.contains(r#"photobench:RunId="20251224T081500Z-4242-0""#),
+            "{xmp}"
+        );
+        assert!(
+            xmp.contains("<rdf:li>Anna &amp; &lt;Ben&gt;</rdf:li>"),
+            "{xmp}"
+        );
+        // The segment went in before the pixels, which still decode
+        assert_eq!(image::open(&path).unwrap().width(), 32);
+    }
+
+    #[test]
+    fn output_tags_without_a_date_or_ids_leave_them_out() {
+        let dir = temp_dir("output-tags");
+        let path = dir.path().join("12.jpg");
+        let tags = OutputTags {
+            ids: None,
+            operator: Some("Anna"),
+            date: None,
+        };
+        tagged_jpeg(&tags, &path);
+
+        let exif = read_exif(&path).unwrap().unwrap();
+        assert_eq!(text(&exif, Tag::Artist).as_deref(), Some("Anna"));
+        assert_eq!(text(&exif, Tag::ImageDescription), None);
+        assert_eq!(get_image_date(&path).unwrap(), None);
+        assert_eq!(read_jpeg_xmp(&path).unwrap(), None);
+    }
+}
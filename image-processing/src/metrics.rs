@@ -0,0 +1,45 @@
+use std::net::SocketAddr;
+use std::sync::OnceLock;
+use std::time::Duration;
+
+use metrics::{counter, gauge, histogram};
+use metrics_exporter_prometheus::PrometheusBuilder;
+use tracing::info;
+
+use crate::error::AppError;
+
+/// Address the exporter is listening on. The recorder is global, so it is only installed once per process.
+static EXPORTER: OnceLock<SocketAddr> = OnceLock::new();
+
+/// Serves the metrics of all the runs in this process for Prometheus on `http://{addr}/metrics`.
+pub fn serve(addr: SocketAddr) -> Result<(), AppError> {
+    if let Some(serving) = EXPORTER.get() {
+        if *serving != addr {
+            info!("Metrics are already served on {serving}");
+        }
+        return Ok(());
+    }
+    PrometheusBuilder::new()
+        .with_http_listener(addr)
+        .install()?;
+    let _ = EXPORTER.set(addr);
+    info!("📈 Serving metrics on http://{addr}/metrics");
+    Ok(())
+}
+
+pub(crate) fn photo_processed() {
+    counter!("photo_bench_photos_processed_total").increment(1);
+}
+
+pub(crate) fn photo_failed() {
+    counter!("photo_bench_photos_failed_total").increment(1);
+}
+
+/// Photos waiting for a free worker.
+pub(crate) fn queued(change: f64) {
+    gauge!("photo_bench_queue_depth").increment(change);
+}
+
+pub(crate) fn stage_duration(stage: &'static str, duration: Duration) {
+    histogram!("photo_bench_stage_seconds", "stage" => stage).record(duration.as_secs_f64());
+}
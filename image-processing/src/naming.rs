@@ -0,0 +1,301 @@
+use std::path::{Component, Path};
+
+use jiff::civil::DateTime;
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+
+use crate::error::AppError;
+use crate::image_ops;
+use crate::numbering::CounterFormat;
+
+/// Template for the file names of the output photos, e.g. `{date}_{number:04}_{orig_stem}`.
+///
+/// Supported placeholders:
+/// - `{number}`: the number of the photo with the counter style and prefix, `{number:04}` zero
+///   pads numeric counters to 4 digits
+/// - `{date}`: the date of the photo as `YYYYMMDD`
+/// - `{orig_stem}`: the file name of the original without the extension
+/// - `{dir}`: the folder of the original directly under the source, e.g. `SampleA` for
+///   `source/SampleA/x.jpg`, see [`source_dir`]
+#[derive(Debug, Clone)]
+pub struct NameTemplate {
+    parts: Vec<Part>,
+    /// Matches the file stems the template produces and captures the number
+    pattern: Regex,
+    counter: CounterFormat,
+}
+
+/// A piece of a name template.
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Part {
+    Literal(String),
+    /// Zero padded to the width
+    Number(usize),
+    Date,
+    OrigStem,
+    Dir,
+}
+
+/// Matches the dates `{date}` is written as, `YYYYMMDD`.
+const DATE_PATTERN: &str = r"\d{4}(?:0[1-9]|1[0-2])(?:0[1-9]|[12]\d|3[01])";
+
+impl NameTemplate {
+    pub fn new(template: &str, counter: CounterFormat) -> Result<Self, AppError> {
+        let placeholder = Regex::new(r"\{(number(?::0(\d+))?|date|orig_stem|dir)\}")?;
+        let mut parts = vec![];
+        let mut pattern = String::from("^");
+        let mut last = 0;
+        let mut has_number = false;
+        let unknown_placeholder = || {
+            AppError::NameTemplate(format!(
+                "Unknown placeholder in {template}. Use {{number}}, {{number:04}}, {{date}}, {{orig_stem}} or {{dir}}"
+            ))
+        };
+        for c in placeholder.captures_iter(template) {
+            let m = c.get(0).expect("Capture 0 is always the whole match");
+            let literal = &template[last..m.start()];
+            if literal.contains(['{', '}']) {
+                return Err(unknown_placeholder());
+            }
+            pattern.push_str(&regex::escape(literal));
+            if !literal.is_empty() {
+                parts.push(Part::Literal(literal.to_string()));
+            }
+            last = m.end();
+            let part = match &c[1] {
+                "date" => Part::Date,
+                "orig_stem" => Part::OrigStem,
+                "dir" => Part::Dir,
+                _ => Part::Number(c.get(2).map_or(0, |w| w.as_str().parse().unwrap_or(0))),
+            };
+            match &part {
+                Part::Date => pattern.push_str(DATE_PATTERN),
+                Part::OrigStem | Part::Dir => pattern.push_str(".+?"),
+                // The number is the only capture group
+                &Part::Number(width) if !has_number => {
+                    has_number = true;
+                    pattern.push_str(&format!("({})", counter.rendered_pattern(width)));
+                }
+                &Part::Number(width) => {
+                    pattern.push_str(&format!("(?:{})", counter.rendered_pattern(width)))
+                }
+                Part::Literal(_) => unreachable!("Placeholders are not literals"),
+            }
+            parts.push(part);
+        }
+        let rest = &template[last..];
+        if rest.contains(['{', '}']) {
+            return Err(unknown_placeholder());
+        }
+        if !has_number {
+            return Err(AppError::NameTemplate(format!(
+                "{template} must contain {{number}} so every photo gets a unique name"
+            )));
+        }
+        pattern.push_str(&regex::escape(rest));
+        pattern.push('$');
+        if !rest.is_empty() {
+            parts.push(Part::Literal(rest.to_string()));
+        }
+
+        Ok(Self {
+            parts,
+            pattern: Regex::new(&pattern)?,
+            counter,
+        })
+    }
+
+    /// The file name of an output photo.
+    pub fn render(&self, number: usize, date: &DateTime, orig_stem: &str, dir: &str) -> String {
+        let mut stem = String::new();
+        for part in &self.parts {
+            match part {
+                Part::Literal(literal) => stem.push_str(literal),
+                &Part::Number(width) => stem.push_str(&self.counter.format(number, width)),
+                Part::Date => stem.push_str(&date.strftime("%Y%m%d").to_string()),
+                Part::OrigStem => stem.push_str(orig_stem),
+                Part::Dir => stem.push_str(dir),
+            }
+        }
+        format!("{stem}.jpg")
+    }
+
+    /// The number of a file named by this template, `None` when the file was not named by it.
+    pub fn number_of(&self, path: &Path) -> Option<usize> {
+        if !image_ops::is_jpg(path) {
+            return None;
+        }
+        self.number_of_stem(path)
+    }
+
+    /// The number in the name of a file of any type named like this template, e.g. `12.png`.
+    fn number_of_stem(&self, path: &Path) -> Option<usize> {
+        let stem = path.file_stem().and_then(|x| x.to_str())?;
+        self.counter.parse(&self.pattern.captures(stem)?[1])
+    }
+
+    /// How the numbers are written.
+    pub fn counter_format(&self) -> &CounterFormat {
+        &self.counter
+    }
+
+    /// Whether the file looks like an output of this template, i.e. it was processed before.
+    pub fn matches(&self, path: &Path) -> bool {
+        self.number_of(path).is_some()
+    }
+
+    /// Whether the name of a source fits the template whatever its type, so number-only
+    /// sources like `12.png` or `12.jpeg` are skipped as numbered before too.
+    pub fn matches_source(&self, path: &Path) -> bool {
+        self.number_of_stem(path).is_some()
+    }
+
+    /// Whether the template copies text from the original, `{orig_stem}` or `{dir}`, so
+    /// camera names like `IMG_1234` can fit it too and the name alone does not tell an output.
+    pub fn has_free_text(&self) -> bool {
+        self.parts
+            .iter()
+            .any(|part| matches!(part, Part::OrigStem | Part::Dir))
+    }
+}
+
+/// Where the name of the source folder of a photo is added, see [`source_dir`].
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize, clap::ValueEnum)]
+#[serde(rename_all = "kebab-case")]
+pub enum DirPrefix {
+    #[default]
+    Off,
+    /// A line of the label under the number
+    Label,
+    /// In front of the file name, unless the name template places `{dir}` itself
+    Name,
+    /// Both on the label and in the file name
+    Both,
+}
+
+impl DirPrefix {
+    pub fn in_label(self) -> bool {
+        matches!(self, DirPrefix::Label | DirPrefix::Both)
+    }
+
+    pub fn in_name(self) -> bool {
+        matches!(self, DirPrefix::Name | DirPrefix::Both)
+    }
+}
+
+/// The folder directly under the source an image is in, e.g. `SampleA` for
+/// `source/SampleA/day1/x.jpg`. Images directly in the source get the name of the source itself.
+pub fn source_dir(root: &Path, path: &Path) -> String {
+    let folder = match path
+        .strip_prefix(root)
+        .map(|p| p.components().collect::<Vec<_>>())
+    {
+        Ok(components) if components.len() > 1 => match components[0] {
+            Component::Normal(name) => Some(name.to_os_string()),
+            _ => None,
+        },
+        _ => None,
+    };
+    folder
+        .or_else(|| {
+            // Canonicalized for sources like `.`
+            let parent = path.parent()?.canonicalize().ok()?;
+            parent.file_name().map(|name| name.to_os_string())
+        })
+        .map(|name| name.to_string_lossy().into_owned())
+        .unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use jiff::civil::date;
+
+    use super::*;
+    use crate::numbering::CounterStyle;
+
+    fn template(template: &str) -> NameTemplate {
+        NameTemplate::new(template, CounterFormat::default()).unwrap()
+    }
+
+    #[test]
+    fn templates_need_a_number_and_known_placeholders() {
+        let counter = CounterFormat::default;
+        assert!(NameTemplate::new("{date}_{orig_stem}", counter()).is_err());
+        assert!(NameTemplate::new("{number}_{camera}", counter()).is_err());
+        assert!(NameTemplate::new("{number}}", counter()).is_err());
+        assert!(NameTemplate::new("{number:4}", counter()).is_err());
+        assert!(NameTemplate::new("Foto {number:04} {dir}", counter()).is_ok());
+    }
+
+    #[test]
+    fn render_fills_in_the_placeholders() {
+        let day = date(2025, 12, 24).at(10, 15, 0, 0);
+        assert_eq!(
+            template("{number}").render(7, &day, "IMG_1", "trip"),
+            "7.jpg"
+        );
+        assert_eq!(
+            template("{date}_{number:04}_{orig_stem}").render(7, &day, "IMG_1", "trip"),
+            "20251224_0007_IMG_1.jpg"
+        );
+        assert_eq!(
+            template("{dir}-{number:02}").render(123, &day, "IMG_1", "trip"),
+            "trip-123.jpg"
+        );
+
+        let alpha = CounterFormat {
+            style: CounterStyle::Alpha,
+            prefix: "S-".to_string(),
+        };
+        let template = NameTemplate::new("{number}", alpha).unwrap();
+        assert_eq!(template.render(28, &day, "IMG_1", "trip"), "S-AB.jpg");
+    }
+
+    #[test]
+    fn number_of_reads_the_names_render_writes() {
+        let day = date(2025, 12, 24).at(10, 15, 0, 0);
+        for text in [
+            "{number}",
+            "{date}_{number:04}_{orig_stem}",
+            "{dir}-{number:02}",
+        ] {
+            let template = template(text);
+            for number in [0, 7, 99, 12345] {
+                let name = template.render(number, &day, "IMG_1", "trip");
+                assert_eq!(template.number_of(Path::new(&name)), Some(number), "{name}");
+            }
+        }
+    }
+
+    #[test]
+    fn number_of_matches_the_extension_in_any_case() {
+        let template = template("{number}");
+        assert_eq!(template.number_of(Path::new("day/12.jpg")), Some(12));
+        assert_eq!(template.number_of(Path::new("day/12.JPG")), Some(12));
+        assert_eq!(template.number_of(Path::new("day/12.Jpg")), Some(12));
+        assert_eq!(template.number_of(Path::new("day/12.png")), None);
+        assert_eq!(template.number_of(Path::new("day/12")), None);
+    }
+
+    #[test]
+    fn sources_of_any_type_fitting_the_template_match() {
+        let template = template("{number}");
+        for name in ["day/12.jpg", "day/12.png", "day/12.jpeg", "day/12.CR2"] {
+            assert!(template.matches_source(Path::new(name)), "{name}");
+        }
+        assert!(!template.matches(Path::new("day/12.png")));
+        assert!(!template.matches_source(Path::new("day/IMG_12.png")));
+    }
+
+    #[test]
+    fn number_of_leaves_out_names_the_template_does_not_write() {
+        let template = template("{date}_{number:04}");
+        assert_eq!(template.number_of(Path::new("20251224_0012.jpg")), Some(12));
+        // Not padded, or padded wider than the template writes
+        assert_eq!(template.number_of(Path::new("20251224_12.jpg")), None);
+        assert_eq!(template.number_of(Path::new("20251224_00012.jpg")), None);
+        // Not a date
+        assert_eq!(template.number_of(Path::new("20251324_0012.jpg")), None);
+        assert_eq!(template.number_of(Path::new("IMG_0012.jpg")), None);
+    }
+}
@@ -0,0 +1,162 @@
+use std::path::PathBuf;
+use std::time::Duration;
+
+use lettre::message::header::ContentType;
+use lettre::message::Mailbox;
+use lettre::transport::smtp::authentication::Credentials;
+use lettre::transport::smtp::client::{Tls, TlsParameters};
+use lettre::{Message, SmtpTransport, Transport};
+use serde::{Deserialize, Serialize};
+use tracing::{info, warn};
+
+use crate::error::AppError;
+
+/// How long sending a notification may take before it is given up.
+const TIMEOUT: Duration = Duration::from_secs(60);
+
+/// Where the summary of a run is sent when it is over, for unattended runs.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Notify {
+    /// The summary is posted to it as json
+    pub webhook: Option<String>,
+    pub smtp: Option<Smtp>,
+}
+
+/// The mail server the summary is mailed through.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Smtp {
+    /// e.g. `smtps://smtp.example.com:465` or `smtp://smtp.example.com:587`
+    pub url: String,
+    pub user: Option<String>,
+    pub password: Option<String>,
+    pub from: String,
+    pub to: Vec<String>,
+}
+
+/// How a run ended.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum RunStatus {
+    Finished,
+    Failed,
+}
+
+/// What is sent when a run is over.
+#[derive(Debug, Serialize)]
+pub struct RunSummary {
+    /// One line for people, chat webhooks show it as the message
+    pub text: String,
+    pub status: RunStatus,
+    pub run_id: String,
+    pub sources: Vec<PathBuf>,
+    pub target: PathBuf,
+    pub processed: usize,
+    pub failed: usize,
+    pub skipped: usize,
+    pub seconds: f64,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub operator: Option<String>,
+    /// Why the run stopped, when it did not finish
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+impl Notify {
+    /// Posts and mails `summary` where configured. A notification that can not be sent is
+    /// only logged, it does not fail the run.
+    pub fn send(&self, summary: &RunSummary) {
+        if let Some(url) = &self.webhook {
+            match post_webhook(url, summary) {
+                Ok(()) => info!("Posted the summary of the run to the webhook"),
+                Err(e) => warn!("{e}"),
+            }
+        }
+        if let Some(smtp) = &self.smtp {
+            match smtp.send(summary) {
+                Ok(()) => info!("Mailed the summary of the run to {}", smtp.to.join(", ")),
+                Err(e) => warn!("{e}"),
+            }
+        }
+    }
+}
+
+fn post_webhook(url: &str, summary: &RunSummary) -> Result<(), AppError> {
+    let json = serde_json::to_vec(summary)?;
+    ureq::post(url)
+        .config()
+        .timeout_global(Some(TIMEOUT))
+        .build()
+        .header("Content-Type", "application/json")
+        .send(&json[..])
+        .map_err(|e| AppError::Notify(format!("{url}: {e}")))?;
+    Ok(())
+}
+
+impl Smtp {
+    fn send(&self, summary: &RunSummary) -> Result<(), AppError> {
+        let subject = match summary.status {
+            RunStatus::Finished => "Photo Date Bench run finished",
+            RunStatus::Failed => "Photo Date Bench run failed",
+        };
+        let mut message = Message::builder()
+            .from(mailbox(&self.from)?)
+            .subject(subject)
+            .header(ContentType::TEXT_PLAIN);
+        for to in &self.to {
+            message = message.to(mailbox(to)?);
+        }
+        let message = message
+            .body(format!(
+                "{}\n\n{}\n",
+                summary.text,
+                serde_json::to_string_pretty(summary)?
+            ))
+            .map_err(notify_error)?;
+        self.transport()?.send(&message).map_err(notify_error)?;
+        Ok(())
+    }
+
+    /// The connection to the server of the url. `smtps://` is encrypted from the start and
+    /// `smtp://` upgrades with STARTTLS, which is required when there is a user, so a password
+    /// is never sent unencrypted.
+    fn transport(&self) -> Result<SmtpTransport, AppError> {
+        let unknown = || AppError::Notify(format!("Unknown SMTP url {}", self.url));
+        let (scheme, rest) = self.url.split_once("://").ok_or_else(unknown)?;
+        let authority = rest.split('/').next().unwrap_or_default();
+        let (host, port) = match authority.rsplit_once(':') {
+            Some((host, port)) => (host, Some(port.parse().map_err(|_| unknown())?)),
+            None => (authority, None),
+        };
+        let mut transport = match scheme {
+            "smtps" => SmtpTransport::relay(host).map_err(notify_error)?,
+            "smtp" if self.user.is_some() => {
+                SmtpTransport::starttls_relay(host).map_err(notify_error)?
+            }
+            "smtp" => {
+                let tls = TlsParameters::new(host.to_string()).map_err(notify_error)?;
+                SmtpTransport::builder_dangerous(host).tls(Tls::Opportunistic(tls))
+            }
+            _ => return Err(unknown()),
+        };
+        if let Some(port) = port {
+            transport = transport.port(port);
+        }
+        if let Some(user) = &self.user {
+            let password = self.password.clone().unwrap_or_default();
+            transport = transport.credentials(Credentials::new(user.clone(), password));
+        }
+        Ok(transport.timeout(Some(TIMEOUT)).build())
+    }
+}
+
+fn mailbox(address: &str) -> Result<Mailbox, AppError> {
+    address
+        .parse()
+        .map_err(|e| AppError::Notify(format!("{address}: {e}")))
+}
+
+fn notify_error(e: impl std::fmt::Display) -> AppError {
+    AppError::Notify(e.to_string())
+}
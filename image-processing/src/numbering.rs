@@ -0,0 +1,669 @@
+use std::collections::{BTreeMap, HashMap, HashSet, VecDeque};
+use std::fmt;
+use std::fs::{self, OpenOptions};
+use std::io::{self, Read, Seek, Write};
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Mutex;
+
+use jiff::civil::DateTime;
+use serde::{Deserialize, Serialize};
+
+use crate::error::AppError;
+use crate::image_ops;
+use crate::manifest::{read_manifest, ManifestEntry};
+use crate::naming::NameTemplate;
+
+/// Name of the file in the root of the target with the last number every run handed out.
+pub const COUNTER_FILE_NAME: &str = "photo-bench-counter.json";
+
+/// How output files are numbered.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize, clap::ValueEnum)]
+#[serde(rename_all = "kebab-case")]
+pub enum Numbering {
+    /// One sequence over all the date folders in the target
+    #[default]
+    Global,
+    /// Every date folder starts at 1
+    PerFolder,
+}
+
+/// How the number of a photo is written in the file names and on the label.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize, clap::ValueEnum)]
+#[serde(rename_all = "kebab-case")]
+pub enum CounterStyle {
+    /// 1, 2, 3, ...
+    #[default]
+    Numeric,
+    /// A, B, ... Z, AA, AB, ... where A is 1
+    Alpha,
+}
+
+/// The counter style with the prefix written in front of every number, e.g. `S12` or `S-B`.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct CounterFormat {
+    pub style: CounterStyle,
+    pub prefix: String,
+}
+
+impl CounterFormat {
+    /// The number as it is written, `width` zero pads numeric counters.
+    pub fn format(&self, number: usize, width: usize) -> String {
+        let counter = match self.style {
+            CounterStyle::Numeric => format!("{number:0width$}"),
+            CounterStyle::Alpha => {
+                let mut letters = vec![];
+                let mut n = number;
+                while n > 0 {
+                    n -= 1;
+                    letters.push(b'A' + (n % 26) as u8);
+                    n /= 26;
+                }
+                letters.reverse();
+                String::from_utf8(letters).expect("Ascii letters")
+            }
+        };
+        format!("{}{counter}", self.prefix)
+    }
+
+    /// The number of a counter written by [`Self::format`].
+    pub fn parse(&self, text: &str) -> Option<usize> {
+        let counter = text.strip_prefix(&self.prefix)?;
+        match self.style {
+            CounterStyle::Numeric => counter.parse().ok(),
+            CounterStyle::Alpha if !counter.is_empty() => {
+                counter.bytes().try_fold(0usize, |n, b| {
+                    if !b.is_ascii_uppercase() {
+                        return None;
+                    }
+                    n.checked_mul(26)?.checked_add((b - b'A' + 1) as usize)
+                })
+            }
+            CounterStyle::Alpha => None,
+        }
+    }
+
+    /// Regex matching only the counters [`Self::format`] writes with `width`: numbers wider than
+    /// the padding have no leading zeros.
+    pub fn rendered_pattern(&self, width: usize) -> String {
+        let counter = match (self.style, width) {
+            (CounterStyle::Numeric, 0 | 1) => r"0|[1-9]\d*".to_string(),
+            (CounterStyle::Numeric, width) => format!(r"\d{{{width}}}|[1-9]\d{{{width},}}"),
+            (CounterStyle::Alpha, _) => "[A-Z]+".to_string(),
+        };
+        format!("{}(?:{counter})", regex::escape(&self.prefix))
+    }
+
+    /// Regex matching a written counter.
+    pub fn pattern(&self) -> String {
+        let counter = match self.style {
+            CounterStyle::Numeric => r"\d+",
+            CounterStyle::Alpha => "[A-Z]+",
+        };
+        format!("{}{counter}", regex::escape(&self.prefix))
+    }
+}
+
+/// Inclusive range of numbers a bench may use, e.g. `1000-1999`, so several benches
+/// can write into the same target without using the same numbers.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(try_from = "String", into = "String")]
+pub struct NumberRange {
+    pub start: usize,
+    pub end: usize,
+}
+
+impl Default for NumberRange {
+    fn default() -> Self {
+        Self {
+            start: 1,
+            end: usize::MAX,
+        }
+    }
+}
+
+impl NumberRange {
+    pub fn contains(&self, number: usize) -> bool {
+        (self.start..=self.end).contains(&number)
+    }
+}
+
+/// Checks that every number of the range can be written in the counter style. The alpha counter
+/// has no letters for 0.
+pub fn check_range(style: CounterStyle, range: &NumberRange) -> Result<(), AppError> {
+    match (style, range.start) {
+        (CounterStyle::Alpha, 0) => Err(AppError::AlphaZero(*range)),
+        _ => Ok(()),
+    }
+}
+
+impl FromStr for NumberRange {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let invalid = || format!("{s} is not a range of numbers like 1000-1999");
+        let (start, end) = s.split_once('-').ok_or_else(invalid)?;
+        let start = start.trim().parse().map_err(|_| invalid())?;
+        let end = end.trim().parse().map_err(|_| invalid())?;
+        if start > end {
+            return Err(invalid());
+        }
+        Ok(Self { start, end })
+    }
+}
+
+impl TryFrom<String> for NumberRange {
+    type Error = String;
+
+    fn try_from(value: String) -> Result<Self, Self::Error> {
+        value.parse()
+    }
+}
+
+impl From<NumberRange> for String {
+    fn from(value: NumberRange) -> Self {
+        value.to_string()
+    }
+}
+
+impl fmt::Display for NumberRange {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}-{}", self.start, self.end)
+    }
+}
+
+/// Hands out the numbers and names for the output files.
+#[derive(Debug)]
+pub struct Counter {
+    numbers: Numbers,
+    template: NameTemplate,
+    range: NumberRange,
+    shared: SharedCounter,
+    /// The highest number in the range the manifest of the target records, by output folder
+    /// relative to the target with per folder numbering. The photos need not be in the target,
+    /// e.g. the staging folder of a remote target on another bench.
+    recorded: HashMap<Option<PathBuf>, usize>,
+    /// Numbers an interrupted run claimed and did not finish, handed out first, by output
+    /// folder with per folder numbering
+    reused: Mutex<HashMap<Option<PathBuf>, VecDeque<usize>>>,
+}
+
+#[derive(Debug)]
+enum Numbers {
+    Global(AtomicUsize),
+    /// The next number per output folder
+    PerFolder(Mutex<HashMap<PathBuf, usize>>),
+}
+
+impl Counter {
+    /// Creates the counter, continuing after the highest number in the range already in the target.
+    pub fn new(
+        numbering: Numbering,
+        template: NameTemplate,
+        range: NumberRange,
+        target: &Path,
+    ) -> Result<Self, AppError> {
+        check_range(template.counter_format().style, &range)?;
+        let mut recorded: HashMap<Option<PathBuf>, usize> = HashMap::new();
+        for entry in read_manifest(target)? {
+            if !range.contains(entry.number) {
+                continue;
+            }
+            let folder = match numbering {
+                Numbering::Global => None,
+                Numbering::PerFolder => entry.output.parent().map(Path::to_path_buf),
+            };
+            let highest = recorded.entry(folder).or_default();
+            *highest = (*highest).max(entry.number);
+        }
+        let numbers = match numbering {
+            Numbering::Global => {
+                let after_recorded = recorded.get(&None).map_or(0, |n| n + 1);
+                let number = Self::first_free(target, &template, &range)?.max(after_recorded);
+                tracing::info!("Start number automatically set to: {}", number);
+                Numbers::Global(number.into())
+            }
+            Numbering::PerFolder => Numbers::PerFolder(Mutex::new(HashMap::new())),
+        };
+        Ok(Self {
+            numbers,
+            template,
+            range,
+            shared: SharedCounter {
+                target: target.to_path_buf(),
+            },
+            recorded,
+            reused: Mutex::new(HashMap::new()),
+        })
+    }
+
+    fn first_free(
+        dir: &Path,
+        template: &NameTemplate,
+        range: &NumberRange,
+    ) -> Result<usize, AppError> {
+        let max = image_ops::find_max_number_jpg(dir, template, range)?;
+        Ok((max + 1).max(range.start))
+    }
+
+    /// Hands out the numbers an interrupted run claimed but did not finish before any new ones,
+    /// for the run that continues it. They are still claimed in the counter file.
+    pub fn reuse(&self, claims: &[UnfinishedClaims]) {
+        let mut reused = self.reused.lock().unwrap_or_else(|e| e.into_inner());
+        for claims in claims.iter().filter(|c| c.range == self.range) {
+            let folder = match &self.numbers {
+                Numbers::Global(_) if claims.folder.is_none() => None,
+                Numbers::PerFolder(_) if claims.folder.is_some() => {
+                    claims.folder.as_ref().map(|x| self.shared.target.join(x))
+                }
+                _ => continue,
+            };
+            reused.entry(folder).or_default().extend(&claims.numbers);
+        }
+    }
+
+    /// The last numbers in the counter file of the target, and with global numbering the one
+    /// before the first number this counter hands out, to tell the numbers a run claims from
+    /// the ones before it.
+    pub fn last_numbers(&self) -> Result<BTreeMap<String, usize>, AppError> {
+        let mut last = read_counter(&self.shared.target)?;
+        if let Numbers::Global(next) = &self.numbers {
+            let before = next.load(Ordering::SeqCst).saturating_sub(1);
+            let entry = last
+                .entry(self.shared.key(&self.range, None))
+                .or_insert(before);
+            *entry = (*entry).max(before);
+        }
+        Ok(last)
+    }
+
+    /// The next number for a file in `out_dir`.
+    pub fn next(&self, out_dir: &Path) -> Result<usize, AppError> {
+        self.take(out_dir, false)
+    }
+
+    /// The number [`Counter::next`] would give a file in `out_dir`, without claiming it in the
+    /// counter file of the target, for a dry run.
+    pub fn plan(&self, out_dir: &Path) -> Result<usize, AppError> {
+        self.take(out_dir, true)
+    }
+
+    /// Checks that the range has a number left for every photo going into `out_dirs`, before
+    /// any of them is claimed, so a run does not stop halfway with the numbers it claimed.
+    pub fn check_capacity<'a>(
+        &self,
+        out_dirs: impl IntoIterator<Item = &'a Path>,
+    ) -> Result<(), AppError> {
+        let mut needed: HashMap<Option<&Path>, usize> = HashMap::new();
+        for out_dir in out_dirs {
+            let folder = match &self.numbers {
+                Numbers::Global(_) => None,
+                Numbers::PerFolder(_) => Some(out_dir),
+            };
+            *needed.entry(folder).or_default() += 1;
+        }
+        let reused = self.reused.lock().unwrap_or_else(|e| e.into_inner());
+        for (folder, needed) in needed {
+            let reusable = reused
+                .get(&folder.map(Path::to_path_buf))
+                .map_or(0, VecDeque::len);
+            let next = match (&self.numbers, folder) {
+                (Numbers::PerFolder(_), Some(out_dir)) => self.next_in_folder(out_dir)?,
+                (Numbers::Global(next), _) => next.load(Ordering::SeqCst),
+                (Numbers::PerFolder(_), None) => unreachable!("Per folder numbers have a folder"),
+            };
+            let next = self.shared.peek(&self.range, folder, next)?;
+            let free = match self.range.contains(next) {
+                true => (self.range.end - next).saturating_add(1),
+                false => 0,
+            };
+            let available = reusable.saturating_add(free);
+            if available < needed {
+                return Err(AppError::NumberRangeTooSmall(self.range, available, needed));
+            }
+        }
+        Ok(())
+    }
+
+    /// The next number in `out_dir` with per folder numbering, after the highest one in the
+    /// folder and in the manifest.
+    fn next_in_folder(&self, out_dir: &Path) -> Result<usize, AppError> {
+        let Numbers::PerFolder(numbers) = &self.numbers else {
+            unreachable!("Only per folder numbering has a number per folder");
+        };
+        let numbers = numbers.lock().unwrap_or_else(|e| e.into_inner());
+        if let Some(&n) = numbers.get(out_dir) {
+            return Ok(n);
+        }
+        let folder = out_dir.strip_prefix(&self.shared.target).ok();
+        let after_recorded = self
+            .recorded
+            .get(&folder.map(Path::to_path_buf))
+            .map_or(0, |n| n + 1);
+        Ok(Self::first_free(out_dir, &self.template, &self.range)?.max(after_recorded))
+    }
+
+    fn take(&self, out_dir: &Path, dry_run: bool) -> Result<usize, AppError> {
+        let folder = match &self.numbers {
+            Numbers::Global(_) => None,
+            Numbers::PerFolder(_) => Some(out_dir.to_path_buf()),
+        };
+        let mut reused = self.reused.lock().unwrap_or_else(|e| e.into_inner());
+        if let Some(number) = reused.get_mut(&folder).and_then(VecDeque::pop_front) {
+            return Ok(number);
+        }
+        drop(reused);
+        let (number, folder) = match &self.numbers {
+            Numbers::Global(number) => (number.fetch_add(1, Ordering::SeqCst), None),
+            Numbers::PerFolder(numbers) => {
+                let number = self.next_in_folder(out_dir)?;
+                let mut numbers = numbers.lock().unwrap_or_else(|e| e.into_inner());
+                numbers.insert(out_dir.to_path_buf(), number + 1);
+                (number, Some(out_dir))
+            }
+        };
+        if !self.range.contains(number) {
+            return Err(AppError::NumberRangeExhausted(self.range));
+        }
+        let number = match dry_run {
+            true => self.shared.peek(&self.range, folder, number)?,
+            false => self.shared.claim(&self.range, folder, number)?,
+        };
+        // The counter file can move the number past the one taken above
+        match &self.numbers {
+            Numbers::Global(next) => {
+                next.fetch_max(number + 1, Ordering::SeqCst);
+            }
+            Numbers::PerFolder(numbers) => {
+                let mut numbers = numbers.lock().unwrap_or_else(|e| e.into_inner());
+                let next = numbers.entry(out_dir.to_path_buf()).or_default();
+                *next = (*next).max(number + 1);
+            }
+        }
+        if !self.range.contains(number) {
+            return Err(AppError::NumberRangeExhausted(self.range));
+        }
+        Ok(number)
+    }
+
+    /// The file name of the output file with this number, e.g. `12.jpg` or `0012.jpg`.
+    /// `dir` is the folder of the original under the source.
+    pub fn file_name(&self, number: usize, date: &DateTime, original: &Path, dir: &str) -> String {
+        let orig_stem = original
+            .file_stem()
+            .and_then(|x| x.to_str())
+            .unwrap_or_default();
+        self.template.render(number, date, orig_stem, dir)
+    }
+}
+
+/// The last numbers handed out in the target, shared by every run writing into it, from the
+/// app or the command line. Without it two runs at the same time would both find the same
+/// highest number in the folders and use the next one twice.
+#[derive(Debug)]
+struct SharedCounter {
+    target: PathBuf,
+}
+
+impl SharedCounter {
+    /// The key of the last number of the range, and of the folder with per folder numbering.
+    fn key(&self, range: &NumberRange, folder: Option<&Path>) -> String {
+        // Benches with their own number ranges, and folders with their own sequence, do not
+        // get in the way of each other
+        let mut key = range.to_string();
+        if let Some(folder) = folder {
+            let folder = folder.strip_prefix(&self.target).unwrap_or(folder);
+            key = format!("{key} {}", folder.to_string_lossy().replace('\\', "/"));
+        }
+        key
+    }
+
+    /// The number [`SharedCounter::claim`] would claim, leaving the counter file as it is.
+    fn peek(
+        &self,
+        range: &NumberRange,
+        folder: Option<&Path>,
+        number: usize,
+    ) -> Result<usize, AppError> {
+        let last = read_counter(&self.target)?;
+        Ok(match last.get(&self.key(range, folder)) {
+            Some(&last) if last >= number => last + 1,
+            _ => number,
+        })
+    }
+
+    /// Claims `number`, or the number after the last one any run claimed when that is higher.
+    fn claim(
+        &self,
+        range: &NumberRange,
+        folder: Option<&Path>,
+        number: usize,
+    ) -> Result<usize, AppError> {
+        let key = self.key(range, folder);
+        update_counter_file(&self.target, |last| {
+            let claimed = match last.get(&key) {
+                Some(&last) if last >= number => {
+                    tracing::info!(
+                        "Another run used the numbers up to {last} in the meantime, continuing at {}",
+                        last + 1
+                    );
+                    last + 1
+                }
+                _ => number,
+            };
+            // A number past the range is not handed out, so it is not recorded either
+            if range.contains(claimed) {
+                last.insert(key, claimed);
+            }
+            claimed
+        })
+    }
+}
+
+/// Records `last` as the last number used in the range of the target, unless a run already
+/// went past it, so the next run continues after it. Used to take over numbers that were not
+/// handed out by a run, e.g. from photos stamped by another tool.
+pub fn seed_counter(target: &Path, range: &NumberRange, last: usize) -> Result<(), AppError> {
+    update_counter_file(target, |numbers| {
+        let entry = numbers.entry(range.to_string()).or_insert(last);
+        *entry = (*entry).max(last);
+    })
+}
+
+/// The numbers a run claimed in the counter file of the target, in one range and with per
+/// folder numbering one folder, that the manifest has no photo of.
+#[derive(Debug, Clone)]
+pub struct UnfinishedClaims {
+    pub range: NumberRange,
+    /// The output folder relative to the target, with per folder numbering
+    pub folder: Option<PathBuf>,
+    pub numbers: Vec<usize>,
+}
+
+impl UnfinishedClaims {
+    /// Whether `number` in `folder`, relative to the target, is one of the claims.
+    pub fn contains(&self, number: usize, folder: &Path) -> bool {
+        self.folder.as_deref().is_none_or(|x| x == folder) && self.numbers.contains(&number)
+    }
+}
+
+/// The last numbers in the counter file of the target, by range and with per folder numbering
+/// by folder. Empty when the target has no counter file yet.
+pub fn read_counter(target: &Path) -> Result<BTreeMap<String, usize>, AppError> {
+    let text = match fs::read_to_string(target.join(COUNTER_FILE_NAME)) {
+        Ok(text) => text,
+        Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(BTreeMap::new()),
+        Err(e) => return Err(e.into()),
+    };
+    Ok(match text.trim() {
+        "" => BTreeMap::new(),
+        text => serde_json::from_str(text)?,
+    })
+}
+
+/// The numbers claimed in the counter file of the target since it was `before` that the
+/// manifest has no photo of.
+pub fn unfinished_claims(
+    target: &Path,
+    before: &BTreeMap<String, usize>,
+    recorded: &[ManifestEntry],
+) -> Result<Vec<UnfinishedClaims>, AppError> {
+    let mut unfinished = vec![];
+    for (key, claimed) in read_counter(target)? {
+        let before = before.get(&key).copied().unwrap_or(0);
+        let (range, folder) = match key.split_once(' ') {
+            Some((range, folder)) => (range, Some(PathBuf::from(folder))),
+            None => (key.as_str(), None),
+        };
+        let Ok(range) = range.parse::<NumberRange>() else {
+            continue;
+        };
+        let finished: HashSet<usize> = recorded
+            .iter()
+            .filter(|e| e.number_range.unwrap_or_default() == range)
+            .filter(|e| {
+                let parent = e.output.parent().unwrap_or(Path::new(""));
+                folder.as_deref().is_none_or(|x| x == parent)
+            })
+            .map(|e| e.number)
+            .collect();
+        let numbers: Vec<usize> = (before + 1..=claimed)
+            .filter(|n| range.contains(*n) && !finished.contains(n))
+            .collect();
+        if !numbers.is_empty() {
+            unfinished.push(UnfinishedClaims {
+                range,
+                folder,
+                numbers,
+            });
+        }
+    }
+    Ok(unfinished)
+}
+
+/// Reads the last numbers from the counter file of the target, lets `update` change them and
+/// writes them back. The file is locked while it is read and written, so runs in other
+/// processes wait for their turn.
+fn update_counter_file<T>(
+    target: &Path,
+    update: impl FnOnce(&mut BTreeMap<String, usize>) -> T,
+) -> Result<T, AppError> {
+    let mut file = OpenOptions::new()
+        .read(true)
+        .write(true)
+        .create(true)
+        .truncate(false)
+        .open(target.join(COUNTER_FILE_NAME))?;
+    file.lock()?;
+    let mut text = String::new();
+    file.read_to_string(&mut text)?;
+    let mut last: BTreeMap<String, usize> = match text.trim() {
+        "" => BTreeMap::new(),
+        text => serde_json::from_str(text)?,
+    };
+    let result = update(&mut last);
+    file.set_len(0)?;
+    file.rewind()?;
+    file.write_all(serde_json::to_string_pretty(&last)?.as_bytes())?;
+    file.unlock()?;
+    Ok(result)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::temp_dir;
+
+    fn counter(target: &Path, range: &str) -> Counter {
+        let template = NameTemplate::new("{number}", CounterFormat::default()).unwrap();
+        Counter::new(Numbering::Global, template, range.parse().unwrap(), target).unwrap()
+    }
+
+    #[test]
+    fn number_ranges_need_two_bounds_in_order() {
+        let range: NumberRange = "1000-1999".parse().unwrap();
+        assert_eq!((range.start, range.end), (1000, 1999));
+        assert_eq!(range.to_string(), "1000-1999");
+        assert_eq!(" 5 - 5 ".parse::<NumberRange>().unwrap().to_string(), "5-5");
+
+        for bad in [
+            "",
+            "1000",
+            "1000-",
+            "-1999",
+            "a-b",
+            "1.5-3",
+            "-1-5",
+            "1999-1000",
+        ] {
+            assert!(bad.parse::<NumberRange>().is_err(), "{bad}");
+        }
+    }
+
+    #[test]
+    fn a_range_runs_out_of_numbers() {
+        let target = temp_dir("counter");
+        let counter = counter(target.path(), "1-3");
+        let dirs = [target.path(); 4];
+
+        counter.check_capacity(dirs[..3].iter().copied()).unwrap();
+        assert!(matches!(
+            counter.check_capacity(dirs.iter().copied()),
+            Err(AppError::NumberRangeTooSmall(_, 3, 4))
+        ));
+        for expected in 1..=3 {
+            assert_eq!(counter.next(target.path()).unwrap(), expected);
+        }
+        assert!(matches!(
+            counter.next(target.path()),
+            Err(AppError::NumberRangeExhausted(_))
+        ));
+        assert!(matches!(
+            counter.check_capacity([target.path()]),
+            Err(AppError::NumberRangeTooSmall(_, 0, 1))
+        ));
+    }
+
+    #[test]
+    fn claims_continue_after_each_other_in_the_counter_file() {
+        let target = temp_dir("counter");
+        // Both start before either claimed a number, so both would start at 1
+        let first = counter(target.path(), "1-100");
+        let second = counter(target.path(), "1-100");
+
+        assert_eq!(first.next(target.path()).unwrap(), 1);
+        assert_eq!(second.next(target.path()).unwrap(), 2);
+        assert_eq!(first.next(target.path()).unwrap(), 3);
+        assert_eq!(read_counter(target.path()).unwrap()["1-100"], 3);
+        // A dry run sees the claims without claiming
+        let planned = counter(target.path(), "1-100");
+        assert_eq!(planned.plan(target.path()).unwrap(), 4);
+        assert_eq!(read_counter(target.path()).unwrap()["1-100"], 3);
+
+        // Another range keeps its own last number
+        let other = counter(target.path(), "200-299");
+        assert_eq!(other.next(target.path()).unwrap(), 200);
+        assert_eq!(read_counter(target.path()).unwrap()["1-100"], 3);
+    }
+
+    #[test]
+    fn alpha_counters_read_back_as_the_number_they_were_written_from() {
+        let alpha = CounterFormat {
+            style: CounterStyle::Alpha,
+            prefix: "S-".to_string(),
+        };
+        assert_eq!(alpha.format(1, 0), "S-A");
+        assert_eq!(alpha.format(26, 4), "S-Z");
+        assert_eq!(alpha.format(27, 0), "S-AA");
+        assert_eq!(alpha.format(702, 0), "S-ZZ");
+        assert_eq!(alpha.format(703, 0), "S-AAA");
+        for number in 1..=20_000 {
+            assert_eq!(alpha.parse(&alpha.format(number, 0)), Some(number));
+        }
+
+        for bad in ["S-", "S-a", "S-A1", "A", "T-A"] {
+            assert_eq!(alpha.parse(bad), None, "{bad}");
+        }
+    }
+}
@@ -0,0 +1,88 @@
+use std::cell::RefCell;
+
+use image::{imageops, RgbImage};
+use tesseract::{PageSegMode, Tesseract};
+
+use crate::draw_text::DrawPosition;
+use crate::error::AppError;
+
+/// Share of the width and height of the photo the stamp is searched in, in its corner.
+const STAMP_AREA_W: f32 = 0.5;
+const STAMP_AREA_H: f32 = 0.25;
+/// The Tesseract language the stamps are read in, its traineddata has to be installed.
+const LANGUAGE: &str = "eng";
+
+/// Reads the stamps in the corners of photos with Tesseract OCR, whatever their font, size
+/// and color, e.g. the "Foto Nr." labels of the old tool.
+pub struct StampOcr {
+    /// Kept between the photos, made again when reading a photo failed halfway
+    tesseract: RefCell<Option<Tesseract>>,
+    dpi: i32,
+}
+
+impl StampOcr {
+    /// Fails when Tesseract or its data for the language is not installed.
+    pub fn new(dpi: f32) -> Result<Self, AppError> {
+        Ok(Self {
+            tesseract: RefCell::new(Some(new_tesseract()?)),
+            dpi: dpi.round() as i32,
+        })
+    }
+
+    /// Reads the lines of text in the `corner` of the photo, from the top down.
+    pub fn read_corner(
+        &self,
+        img: &RgbImage,
+        corner: DrawPosition,
+    ) -> Result<Vec<String>, AppError> {
+        let (w, h) = img.dimensions();
+        let (area_w, area_h) = (
+            (w as f32 * STAMP_AREA_W) as u32,
+            (h as f32 * STAMP_AREA_H) as u32,
+        );
+        if area_w == 0 || area_h == 0 {
+            return Ok(vec![]);
+        }
+        let x = if corner.is_left() { 0 } else { w - area_w };
+        let y = match corner {
+            DrawPosition::TopLeft | DrawPosition::TopRight => 0,
+            DrawPosition::BottomLeft | DrawPosition::BottomRight => h - area_h,
+        };
+        let area = imageops::crop_imm(img, x, y, area_w, area_h).to_image();
+
+        let tesseract = match self.tesseract.take() {
+            Some(tesseract) => tesseract,
+            None => new_tesseract()?,
+        };
+        let ocr_error = |e: &dyn std::error::Error| AppError::Ocr(e.to_string());
+        let mut tesseract = tesseract
+            .set_frame(
+                area.as_raw(),
+                area_w as i32,
+                area_h as i32,
+                3,
+                3 * area_w as i32,
+            )
+            .map_err(|e| ocr_error(&e))?
+            .set_source_resolution(self.dpi)
+            .recognize()
+            .map_err(|e| ocr_error(&e))?;
+        let text = tesseract.get_text().map_err(|e| ocr_error(&e))?;
+        self.tesseract.replace(Some(tesseract));
+        Ok(text
+            .lines()
+            .map(str::trim)
+            .filter(|line| !line.is_empty())
+            .map(str::to_string)
+            .collect())
+    }
+}
+
+fn new_tesseract() -> Result<Tesseract, AppError> {
+    let mut tesseract =
+        Tesseract::new(None, Some(LANGUAGE)).map_err(|e| AppError::Ocr(e.to_string()))?;
+    // The stamps are a block of a few lines somewhere in the corner, between the things in
+    // the photo, and their lines are read whole
+    tesseract.set_page_seg_mode(PageSegMode::PsmAuto);
+    Ok(tesseract)
+}
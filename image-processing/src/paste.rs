@@ -0,0 +1,86 @@
+use std::fs;
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use image::ImageFormat;
+
+use crate::error::AppError;
+use crate::progress::{ProgressEvent, ProgressSink};
+use crate::{run_image_processing, App};
+
+/// Folder in the cache directory pasted images wait in while they are processed.
+const PASTE_DIR: &str = "pasted";
+
+/// How a pasted image ended, from the progress of its run.
+#[derive(Default)]
+enum Outcome {
+    #[default]
+    Pending,
+    Written(PathBuf),
+    NotWritten(String),
+}
+
+/// Processes one image given as its bytes, e.g. a screenshot pasted from the clipboard or
+/// dragged from a browser, into the target of `app` with its options like any run, so it gets
+/// the next number there. The sources of `app` are left out. Its date is the one in its EXIF
+/// data, or else today. Returns the output photo.
+pub fn process_bytes(
+    bytes: &[u8],
+    app: App,
+    progress: Option<Arc<dyn ProgressSink>>,
+) -> Result<PathBuf, AppError> {
+    let extension = match image::guess_format(bytes) {
+        Ok(ImageFormat::Jpeg) => "jpg",
+        Ok(ImageFormat::Png) => "png",
+        _ => return Err(AppError::Pasted("it is no jpeg or png".into())),
+    };
+    let stamp = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_nanos();
+    let folder = directories::ProjectDirs::from("", "", "photo-bench-ui")
+        .ok_or(AppError::NoCacheDir)?
+        .cache_dir()
+        .join(PASTE_DIR)
+        .join(format!("{}-{stamp}", std::process::id()));
+    fs::create_dir_all(&folder)?;
+    // The name has today in it, the date of images without EXIF data
+    let today = jiff::Zoned::now().strftime("%Y%m%d").to_string();
+    let file = folder.join(format!("pasted_{today}.{extension}"));
+    fs::write(&file, bytes)?;
+
+    let outcome = Arc::new(Mutex::new(Outcome::Pending));
+    let seen = outcome.clone();
+    let sink = move |event: &ProgressEvent| {
+        let ended = match event {
+            ProgressEvent::Finished { output, .. } => Some(Outcome::Written(output.to_path_buf())),
+            ProgressEvent::Failed { error, .. } => Some(Outcome::NotWritten(error.clone())),
+            ProgressEvent::Skipped { reason, .. } => {
+                Some(Outcome::NotWritten(format!("it was skipped, {reason}")))
+            }
+            _ => None,
+        };
+        if let Some(ended) = ended {
+            *seen.lock().unwrap_or_else(|e| e.into_inner()) = ended;
+        }
+        if let Some(progress) = &progress {
+            progress.event(event);
+        }
+    };
+    let app = App {
+        sources: vec![file],
+        files_from: None,
+        yes: true,
+        ..app
+    };
+    let result = run_image_processing(app, Some(Arc::new(sink)));
+    let _ = fs::remove_dir_all(&folder);
+    result?;
+
+    match std::mem::take(&mut *outcome.lock().unwrap_or_else(|e| e.into_inner())) {
+        Outcome::Written(output) => Ok(output),
+        Outcome::NotWritten(reason) => Err(AppError::Pasted(reason)),
+        Outcome::Pending => Err(AppError::Pasted("it was not processed".into())),
+    }
+}
@@ -0,0 +1,112 @@
+use std::collections::{HashMap, HashSet};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Condvar, Mutex};
+use std::thread;
+
+use tracing::debug;
+
+/// Reads the next few originals into memory on a background thread while the workers are busy,
+/// so the latency of a network share overlaps with the processing instead of adding to it.
+#[derive(Debug)]
+pub struct Prefetcher {
+    shared: Arc<Shared>,
+}
+
+#[derive(Debug)]
+struct Shared {
+    state: Mutex<State>,
+    changed: Condvar,
+}
+
+#[derive(Debug, Default)]
+struct State {
+    /// Read, but not taken by a worker yet
+    ready: HashMap<PathBuf, Vec<u8>>,
+    bytes: usize,
+    /// Being read right now
+    reading: Option<PathBuf>,
+    /// Taken by a worker before they were read, so they are not read anymore
+    claimed: HashSet<PathBuf>,
+    stopped: bool,
+}
+
+impl Prefetcher {
+    /// Starts reading `paths` in the order they are processed, keeping at most `ahead` files
+    /// and `budget` bytes in memory.
+    pub fn start(paths: Vec<PathBuf>, ahead: usize, budget: usize) -> Self {
+        let shared = Arc::new(Shared {
+            state: Mutex::new(State::default()),
+            changed: Condvar::new(),
+        });
+        let prefetch = shared.clone();
+        thread::spawn(move || {
+            for path in paths {
+                let mut state = prefetch.lock();
+                while !state.stopped && (state.ready.len() >= ahead || state.bytes >= budget) {
+                    state = prefetch
+                        .changed
+                        .wait(state)
+                        .unwrap_or_else(|e| e.into_inner());
+                }
+                if state.stopped {
+                    return;
+                }
+                if state.claimed.remove(&path) {
+                    continue;
+                }
+                state.reading = Some(path.clone());
+                drop(state);
+
+                // A failed read is left to the worker, which reports the error
+                let data = fs::read(&path);
+                let mut state = prefetch.lock();
+                state.reading = None;
+                match data {
+                    Ok(data) => {
+                        state.bytes += data.len();
+                        state.ready.insert(path, data);
+                    }
+                    Err(e) => debug!("{e}. Could not prefetch {path:?}"),
+                }
+                prefetch.changed.notify_all();
+            }
+        });
+        Self { shared }
+    }
+
+    /// The contents of the file when it was prefetched. Waits when it is being read right now,
+    /// otherwise the worker has to read it itself.
+    pub fn take(&self, path: &Path) -> Option<Vec<u8>> {
+        let mut state = self.shared.lock();
+        loop {
+            if let Some(data) = state.ready.remove(path) {
+                state.bytes -= data.len();
+                self.shared.changed.notify_all();
+                return Some(data);
+            }
+            if state.reading.as_deref() != Some(path) {
+                state.claimed.insert(path.to_path_buf());
+                return None;
+            }
+            state = self
+                .shared
+                .changed
+                .wait(state)
+                .unwrap_or_else(|e| e.into_inner());
+        }
+    }
+}
+
+impl Drop for Prefetcher {
+    fn drop(&mut self) {
+        self.shared.lock().stopped = true;
+        self.shared.changed.notify_all();
+    }
+}
+
+impl Shared {
+    fn lock(&self) -> std::sync::MutexGuard<'_, State> {
+        self.state.lock().unwrap_or_else(|e| e.into_inner())
+    }
+}
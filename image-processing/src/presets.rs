@@ -0,0 +1,82 @@
+use serde::{Deserialize, Serialize};
+
+use crate::config::{Color, Config, StampLayout};
+use crate::error::AppError;
+
+/// The presets shipped with the program.
+const BUNDLED: &str = include_str!("../assets/presets.toml");
+
+/// A named look of the stamps, e.g. `Classic orange`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct StampPreset {
+    pub name: String,
+    pub description: String,
+    pub layout: StampLayout,
+    pub caption_color: Color,
+    pub date: PresetStamp,
+    pub label: PresetStamp,
+    pub gps: PresetStamp,
+}
+
+/// The font, size and color of one stamp of a preset.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct PresetStamp {
+    pub font: String,
+    pub size_pt: usize,
+    pub color: Color,
+}
+
+#[derive(Deserialize)]
+struct Presets {
+    preset: Vec<StampPreset>,
+}
+
+impl StampPreset {
+    /// Sets the layout and the fonts, sizes and colors of the stamps in `config`. What is
+    /// stamped and where is left as the config has it.
+    pub fn apply(&self, config: &mut Config) {
+        config.layout = self.layout;
+        config.caption_color = self.caption_color;
+        let (font, size_pt, color) = self.date.parts();
+        (config.date.font, config.date.size_pt, config.date.color) = (Some(font), size_pt, color);
+        (config.label.font, config.label.size_pt, config.label.color) = self.label.parts();
+        (config.gps.font, config.gps.size_pt, config.gps.color) = self.gps.parts();
+    }
+
+    /// Whether the preset is called `name`, ignoring case and with `-` or `_` for the spaces,
+    /// so `classic-orange` finds `Classic orange`.
+    fn is_named(&self, name: &str) -> bool {
+        let normalize = |name: &str| name.to_lowercase().replace(['-', '_'], " ");
+        normalize(&self.name) == normalize(name)
+    }
+}
+
+impl PresetStamp {
+    fn parts(&self) -> (String, usize, Color) {
+        (self.font.clone(), self.size_pt, self.color)
+    }
+}
+
+/// The presets shipped with the program, in the order they are listed.
+pub fn presets() -> Vec<StampPreset> {
+    toml::from_str::<Presets>(BUNDLED)
+        .expect("Valid bundled presets")
+        .preset
+}
+
+/// The preset called `name`.
+pub fn find(name: &str) -> Result<StampPreset, AppError> {
+    let presets = presets();
+    let names = presets
+        .iter()
+        .map(|p| p.name.as_str())
+        .collect::<Vec<_>>()
+        .join(", ");
+    presets
+        .iter()
+        .find(|p| p.is_named(name))
+        .cloned()
+        .ok_or_else(|| AppError::UnknownPreset(name.to_string(), names))
+}
@@ -0,0 +1,89 @@
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use tracing::{debug, warn};
+
+use crate::error::AppError;
+
+/// Folder in the temp directory the preview folders of the sessions are made in.
+const PREVIEW_DIR: &str = "photo-bench-previews";
+/// Folders of sessions that crashed are removed by a later session after this long.
+const STALE_AFTER: Duration = Duration::from_secs(24 * 60 * 60);
+
+/// A folder for the preview renders and thumbnails of one session of the app, in the temp
+/// directory and apart from the cache the runs use. It is removed when the session ends.
+#[derive(Debug)]
+pub struct PreviewDir {
+    path: PathBuf,
+}
+
+impl PreviewDir {
+    /// Makes the folder of a new session, and removes the ones sessions that crashed left.
+    pub fn create() -> Result<Self, AppError> {
+        let root = std::env::temp_dir().join(PREVIEW_DIR);
+        remove_stale(&root);
+        let started = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_millis();
+        let path = root.join(format!("{}-{started}", std::process::id()));
+        fs::create_dir_all(&path)?;
+        debug!("Keeping the previews of this session in {path:?}");
+        Ok(Self { path })
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Removes the previews made so far, keeping the folder for the next ones.
+    pub fn clear(&self) -> Result<(), AppError> {
+        for entry in fs::read_dir(&self.path)? {
+            let path = entry?.path();
+            match path.is_dir() {
+                true => fs::remove_dir_all(&path)?,
+                false => fs::remove_file(&path)?,
+            }
+        }
+        Ok(())
+    }
+
+    /// Removes the folder, at the end of the session.
+    pub fn remove(&self) {
+        match fs::remove_dir_all(&self.path) {
+            Ok(()) => {}
+            Err(e) if e.kind() == io::ErrorKind::NotFound => {}
+            Err(e) => warn!("{e}. Could not remove the previews in {:?}", self.path),
+        }
+    }
+}
+
+impl Drop for PreviewDir {
+    fn drop(&mut self) {
+        self.remove();
+    }
+}
+
+/// Removes the session folders in `root` that were not changed for a day. Ones of sessions
+/// that are still open are newer.
+fn remove_stale(root: &Path) {
+    let Ok(entries) = fs::read_dir(root) else {
+        return;
+    };
+    for entry in entries.filter_map(|e| e.ok()) {
+        let stale = entry
+            .metadata()
+            .and_then(|m| m.modified())
+            .ok()
+            .and_then(|modified| modified.elapsed().ok())
+            .is_some_and(|age| age > STALE_AFTER);
+        if stale && let Err(e) = fs::remove_dir_all(entry.path()) {
+            debug!(
+                "{e}. Could not remove the old previews in {:?}",
+                entry.path()
+            );
+        }
+    }
+}
@@ -0,0 +1,121 @@
+use std::io::Write;
+use std::path::Path;
+use std::sync::Mutex;
+
+use jiff::civil::DateTime;
+use serde::Serialize;
+use tracing::error;
+
+use crate::metadata::ExifSummary;
+use crate::quality::QualityWarning;
+use crate::skip::SkipReason;
+use crate::timings::Stage;
+
+/// Something that happened during a run, passed to the [`ProgressSink`].
+#[derive(Debug, Clone, Serialize)]
+#[serde(tag = "event", rename_all = "kebab-case")]
+pub enum ProgressEvent<'a> {
+    /// The source was walked and this many photos will be processed
+    Scanned { total: usize },
+    /// A file in the source is not processed
+    Skipped { file: &'a Path, reason: SkipReason },
+    /// A worker started on a photo
+    Started { file: &'a Path },
+    /// A worker moved on to the next step of a photo, so big photos do not look stuck
+    Stage { file: &'a Path, stage: Stage },
+    /// A photo was written to the target, with a summary of the EXIF data of the original so
+    /// it can be shown without reading the file again
+    Finished {
+        file: &'a Path,
+        output: &'a Path,
+        exif: Option<&'a ExifSummary>,
+        completed: usize,
+        total: usize,
+    },
+    /// A processed photo looks blurry or badly exposed
+    Warning {
+        file: &'a Path,
+        warnings: &'a [QualityWarning],
+    },
+    /// Where a photo would be written, in a dry run
+    Planned {
+        file: &'a Path,
+        output: &'a Path,
+        date: &'a DateTime,
+        number: usize,
+    },
+    /// A photo could not be processed
+    Failed {
+        file: &'a Path,
+        error: String,
+        completed: usize,
+        total: usize,
+    },
+    /// All the photos were processed
+    Done { processed: usize, failed: usize },
+    /// How the run went and the time per stage, at the very end, for the terminal
+    Summary { lines: &'a [String] },
+}
+
+impl ProgressEvent<'_> {
+    /// How far the run is, from 0 to 100.
+    pub fn percent(&self) -> Option<f32> {
+        match self {
+            ProgressEvent::Finished {
+                completed, total, ..
+            }
+            | ProgressEvent::Failed {
+                completed, total, ..
+            } => Some(*completed as f32 / (*total).max(1) as f32 * 100.0),
+            ProgressEvent::Done { .. } => Some(100.0),
+            _ => None,
+        }
+    }
+}
+
+/// Receives the progress of a run, e.g. to draw a progress bar. Called from the worker threads.
+pub trait ProgressSink: Send + Sync {
+    fn event(&self, event: &ProgressEvent);
+}
+
+impl<F: Fn(&ProgressEvent) + Send + Sync> ProgressSink for F {
+    fn event(&self, event: &ProgressEvent) {
+        self(event)
+    }
+}
+
+/// How the CLI shows the progress of a run.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, clap::ValueEnum)]
+pub enum ProgressFormat {
+    /// Progress bars in the terminal
+    #[default]
+    Bars,
+    /// One json object per event on stdout, for scripts and other programs
+    Json,
+}
+
+/// Writes every event as one line of json.
+pub struct JsonLinesProgress<W> {
+    out: Mutex<W>,
+}
+
+impl<W: Write + Send> JsonLinesProgress<W> {
+    pub fn new(out: W) -> Self {
+        Self {
+            out: Mutex::new(out),
+        }
+    }
+}
+
+impl<W: Write + Send> ProgressSink for JsonLinesProgress<W> {
+    fn event(&self, event: &ProgressEvent) {
+        let mut out = self.out.lock().unwrap_or_else(|e| e.into_inner());
+        let written = serde_json::to_writer(&mut *out, event)
+            .map_err(std::io::Error::from)
+            .and_then(|_| writeln!(out))
+            .and_then(|_| out.flush());
+        if let Err(e) = written {
+            error!("{e}. Could not write the progress");
+        }
+    }
+}
@@ -0,0 +1,99 @@
+use std::fmt;
+
+use image::{buffer::ConvertBuffer, GrayImage, RgbImage};
+use serde::{Deserialize, Serialize};
+
+/// When a photo is flagged as blurry or badly exposed.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct QualityThresholds {
+    pub check: bool,
+    /// Photos with a lower variance of the Laplacian are flagged as blurry
+    pub min_sharpness: f32,
+    /// Photos with more of their pixels clipped to black or white are flagged
+    pub max_clipped_percent: f32,
+}
+
+impl Default for QualityThresholds {
+    fn default() -> Self {
+        Self {
+            check: true,
+            min_sharpness: 100.0,
+            max_clipped_percent: 5.0,
+        }
+    }
+}
+
+/// Why a photo might have to be retaken.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "kebab-case")]
+pub enum QualityWarning {
+    Blurry { sharpness: f32 },
+    Underexposed { clipped_percent: f32 },
+    Overexposed { clipped_percent: f32 },
+}
+
+impl fmt::Display for QualityWarning {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            QualityWarning::Blurry { sharpness } => write!(f, "blurry (sharpness {sharpness:.0})"),
+            QualityWarning::Underexposed { clipped_percent } => {
+                write!(f, "underexposed ({clipped_percent:.1}% black)")
+            }
+            QualityWarning::Overexposed { clipped_percent } => {
+                write!(f, "overexposed ({clipped_percent:.1}% white)")
+            }
+        }
+    }
+}
+
+/// Checks a photo for blur and clipped shadows or highlights.
+pub fn check_quality(img: &RgbImage, thresholds: &QualityThresholds) -> Vec<QualityWarning> {
+    if !thresholds.check {
+        return vec![];
+    }
+    let gray: GrayImage = img.convert();
+    let mut warnings = vec![];
+
+    let sharpness = laplacian_variance(&gray);
+    if sharpness < thresholds.min_sharpness {
+        warnings.push(QualityWarning::Blurry { sharpness });
+    }
+
+    let total = gray.len().max(1) as f32;
+    let percent = |count: usize| count as f32 / total * 100.0;
+    let black = percent(gray.iter().filter(|&&v| v <= 2).count());
+    if black > thresholds.max_clipped_percent {
+        warnings.push(QualityWarning::Underexposed {
+            clipped_percent: black,
+        });
+    }
+    let white = percent(gray.iter().filter(|&&v| v >= 253).count());
+    if white > thresholds.max_clipped_percent {
+        warnings.push(QualityWarning::Overexposed {
+            clipped_percent: white,
+        });
+    }
+    warnings
+}
+
+/// Variance of the Laplacian, low values mean few edges and usually a blurry photo.
+fn laplacian_variance(gray: &GrayImage) -> f32 {
+    let (w, h) = gray.dimensions();
+    if w < 3 || h < 3 {
+        return f32::MAX;
+    }
+    let px = |x: u32, y: u32| gray.get_pixel(x, y).0[0] as f64;
+    let mut sum = 0f64;
+    let mut sum_sq = 0f64;
+    for y in 1..h - 1 {
+        for x in 1..w - 1 {
+            let lap = 4.0 * px(x, y) - px(x - 1, y) - px(x + 1, y) - px(x, y - 1) - px(x, y + 1);
+            sum += lap;
+            sum_sq += lap * lap;
+        }
+    }
+    let n = ((w - 2) * (h - 2)) as f64;
+    let mean = sum / n;
+    (sum_sq / n - mean * mean) as f32
+}
@@ -0,0 +1,234 @@
+use std::ffi::OsStr;
+use std::fs::{self, File};
+use std::io::{Cursor, Read};
+use std::path::Path;
+
+use image::{DynamicImage, ImageReader};
+
+use crate::error::AppError;
+
+/// Extensions of the camera raw files taken in. They are all TIFF containers, with the EXIF
+/// data in the usual places and the jpeg preview the camera made in one of the IFDs.
+const RAW_EXTENSIONS: [&str; 4] = ["cr2", "nef", "arw", "dng"];
+
+/// TIFF tags the previews are found by.
+const COMPRESSION: u16 = 0x0103;
+const STRIP_OFFSETS: u16 = 0x0111;
+const STRIP_BYTE_COUNTS: u16 = 0x0117;
+const SUB_IFDS: u16 = 0x014A;
+const JPEG_OFFSET: u16 = 0x0201;
+const JPEG_LENGTH: u16 = 0x0202;
+
+/// More IFDs than any raw has, so a broken file can not loop forever.
+const MAX_IFDS: usize = 64;
+
+pub fn is_raw_file(path: &Path) -> bool {
+    path.extension()
+        .and_then(OsStr::to_str)
+        .is_some_and(|ext| RAW_EXTENSIONS.contains(&ext.to_lowercase().as_str()))
+}
+
+/// Whether `data` is a TIFF container. Plain TIFFs are not taken in, so it is a raw.
+pub fn is_tiff(data: &[u8]) -> bool {
+    data.starts_with(b"II*\0") || data.starts_with(b"MM\0*")
+}
+
+/// The jpeg data of the largest preview in the raw file `data`, when it has one the jpeg
+/// decoders can read. Raws keep small thumbnails and the lossless raw data next to it.
+pub fn preview(data: &[u8]) -> Option<&[u8]> {
+    let tiff = Tiff {
+        data,
+        little_endian: data.starts_with(b"II"),
+    };
+    let mut queue = vec![tiff.u32(4)?];
+    let mut seen = vec![];
+    let mut best: Option<(&[u8], u64)> = None;
+    while let Some(offset) = queue.pop() {
+        if offset == 0 || seen.contains(&offset) || seen.len() >= MAX_IFDS {
+            continue;
+        }
+        seen.push(offset);
+        let Some(ifd) = tiff.ifd(offset) else {
+            continue;
+        };
+        queue.extend(ifd.values(SUB_IFDS));
+        queue.extend(ifd.next);
+
+        let mut candidates = vec![];
+        if let (Some(&start), Some(&length)) = (
+            ifd.values(JPEG_OFFSET).first(),
+            ifd.values(JPEG_LENGTH).first(),
+        ) {
+            candidates.push((start, length));
+        }
+        let jpeg_compressed = ifd
+            .values(COMPRESSION)
+            .first()
+            .is_some_and(|c| *c == 6 || *c == 7);
+        if let ([start], [length], true) = (
+            ifd.values(STRIP_OFFSETS).as_slice(),
+            ifd.values(STRIP_BYTE_COUNTS).as_slice(),
+            jpeg_compressed,
+        ) {
+            candidates.push((*start, *length));
+        }
+        for (start, length) in candidates {
+            let (start, length) = (start as usize, length as usize);
+            let Some(jpeg) = data.get(start..start.saturating_add(length)) else {
+                continue;
+            };
+            let Some((width, height)) = jpeg_size(jpeg) else {
+                continue;
+            };
+            let pixels = u64::from(width) * u64::from(height);
+            if best.is_none_or(|(_, most)| pixels > most) {
+                best = Some((jpeg, pixels));
+            }
+        }
+    }
+    best.map(|(jpeg, _)| jpeg)
+}
+
+/// The preview of the file at `path` when it is a raw, by its contents rather than its name,
+/// as the staged copies of the originals are named after the outputs.
+pub fn read_preview(path: &Path) -> Result<Option<Vec<u8>>, AppError> {
+    let mut file = File::open(path)?;
+    let mut header = [0; 4];
+    if file.read_exact(&mut header).is_err() || !is_tiff(&header) {
+        return Ok(None);
+    }
+    let data = fs::read(path)?;
+    match preview(&data) {
+        Some(jpeg) => Ok(Some(jpeg.to_vec())),
+        None => Err(AppError::RawPreview(path.to_path_buf())),
+    }
+}
+
+/// Decodes the preview of the raw file at `path`, or any other image.
+pub fn open(path: &Path) -> Result<DynamicImage, AppError> {
+    if !is_raw_file(path) {
+        return Ok(image::open(path)?);
+    }
+    let data = fs::read(path)?;
+    let jpeg = preview(&data).ok_or_else(|| AppError::RawPreview(path.to_path_buf()))?;
+    Ok(ImageReader::with_format(Cursor::new(jpeg), image::ImageFormat::Jpeg).decode()?)
+}
+
+/// The width and height of the image at `path`, for raws of their preview. Only reads the
+/// header of other images.
+pub fn dimensions(path: &Path) -> Result<(u32, u32), AppError> {
+    if !is_raw_file(path) {
+        return Ok(image::image_dimensions(path)?);
+    }
+    let data = fs::read(path)?;
+    preview(&data)
+        .and_then(jpeg_size)
+        .ok_or_else(|| AppError::RawPreview(path.to_path_buf()))
+}
+
+/// The size in the frame header of a baseline or progressive jpeg. `None` for the lossless
+/// jpegs raw data is kept in, which the decoders can not read.
+fn jpeg_size(jpeg: &[u8]) -> Option<(u32, u32)> {
+    if !jpeg.starts_with(&[0xFF, 0xD8]) {
+        return None;
+    }
+    let mut at = 2;
+    loop {
+        if *jpeg.get(at)? != 0xFF {
+            return None;
+        }
+        let marker = *jpeg.get(at + 1)?;
+        match marker {
+            // Fill bytes before a marker
+            0xFF => at += 1,
+            0x01 | 0xD0..=0xD7 => at += 2,
+            0xC0..=0xC2 => {
+                let height = u16::from_be_bytes([*jpeg.get(at + 5)?, *jpeg.get(at + 6)?]);
+                let width = u16::from_be_bytes([*jpeg.get(at + 7)?, *jpeg.get(at + 8)?]);
+                return Some((width.into(), height.into()));
+            }
+            0xC3 | 0xC5..=0xC7 | 0xC9..=0xCB | 0xCD..=0xCF | 0xDA | 0xD9 => return None,
+            _ => {
+                let length = u16::from_be_bytes([*jpeg.get(at + 2)?, *jpeg.get(at + 3)?]);
+                at += 2 + usize::from(length);
+            }
+        }
+    }
+}
+
+struct Tiff<'a> {
+    data: &'a [u8],
+    little_endian: bool,
+}
+
+/// The entries of one IFD that are read, as tag and values.
+struct Ifd {
+    entries: Vec<(u16, Vec<u32>)>,
+    next: Option<u32>,
+}
+
+impl Ifd {
+    fn values(&self, tag: u16) -> Vec<u32> {
+        self.entries
+            .iter()
+            .find(|(t, _)| *t == tag)
+            .map(|(_, values)| values.clone())
+            .unwrap_or_default()
+    }
+}
+
+impl Tiff<'_> {
+    fn u16(&self, at: u32) -> Option<u16> {
+        let bytes = self
+            .data
+            .get(at as usize..at as usize + 2)?
+            .try_into()
+            .ok()?;
+        Some(match self.little_endian {
+            true => u16::from_le_bytes(bytes),
+            false => u16::from_be_bytes(bytes),
+        })
+    }
+
+    fn u32(&self, at: u32) -> Option<u32> {
+        let bytes = self
+            .data
+            .get(at as usize..at as usize + 4)?
+            .try_into()
+            .ok()?;
+        Some(match self.little_endian {
+            true => u32::from_le_bytes(bytes),
+            false => u32::from_be_bytes(bytes),
+        })
+    }
+
+    /// Reads the IFD at `offset`, keeping the SHORT, LONG and IFD values.
+    fn ifd(&self, offset: u32) -> Option<Ifd> {
+        let count = self.u16(offset)?;
+        let mut entries = vec![];
+        for i in 0..u32::from(count) {
+            let entry = offset.checked_add(2 + i * 12)?;
+            let tag = self.u16(entry)?;
+            let kind = self.u16(entry + 2)?;
+            let n = self.u32(entry + 4)?.min(1024);
+            let size = match kind {
+                3 => 2,
+                4 | 13 => 4,
+                _ => continue,
+            };
+            let start = match n * size <= 4 {
+                true => entry + 8,
+                false => self.u32(entry + 8)?,
+            };
+            let values = (0..n)
+                .filter_map(|j| match size {
+                    2 => self.u16(start.checked_add(j * 2)?).map(u32::from),
+                    _ => self.u32(start.checked_add(j * 4)?),
+                })
+                .collect();
+            entries.push((tag, values));
+        }
+        let next = self.u32(offset.checked_add(2 + u32::from(count) * 12)?);
+        Some(Ifd { entries, next })
+    }
+}
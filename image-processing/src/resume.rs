@@ -0,0 +1,248 @@
+use std::collections::BTreeMap;
+use std::fs::{self, File, OpenOptions, TryLockError};
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+
+use jiff::Timestamp;
+use serde::{Deserialize, Serialize};
+use tracing::{info, warn};
+use walkdir::WalkDir;
+
+use crate::error::AppError;
+use crate::manifest::ManifestEntry;
+use crate::naming::NameTemplate;
+use crate::numbering::{self, UnfinishedClaims};
+
+/// Start of the names of the run files kept in the root of the target folder while a run is
+/// going, e.g. `photo-bench-run-20251224T081500Z-4242-0.json`.
+pub const RUN_FILE_PREFIX: &str = "photo-bench-run-";
+
+/// What the run file of a run records, so a run that was interrupted can be continued.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RunRecord {
+    pub run_id: String,
+    /// The sources as they were given, a run with the same sources continues the run
+    pub sources: Vec<PathBuf>,
+    pub started_at: Timestamp,
+    /// The last numbers in the target before the run claimed any
+    #[serde(default)]
+    pub counter: BTreeMap<String, usize>,
+}
+
+/// The run file of the run going on, locked so other runs do not take it for an interrupted
+/// one. A crash or power loss leaves it behind, which is how the next run knows to continue.
+#[derive(Debug)]
+pub struct RunFile {
+    path: PathBuf,
+    file: File,
+}
+
+impl RunFile {
+    /// Writes the run file of `run` into the target and holds its lock until the run finishes.
+    /// Fails when another run holds it, e.g. two runs continuing the same interrupted run.
+    pub fn start(target: &Path, run: &RunRecord) -> Result<Self, AppError> {
+        let path = run_file_path(target, &run.run_id);
+        let mut file = OpenOptions::new()
+            .write(true)
+            .create(true)
+            .truncate(false)
+            .open(&path)?;
+        match file.try_lock() {
+            Ok(()) => {}
+            Err(TryLockError::WouldBlock) => return Err(AppError::RunFileLocked(path)),
+            Err(TryLockError::Error(e)) => return Err(e.into()),
+        }
+        file.set_len(0)?;
+        file.write_all(serde_json::to_string_pretty(run)?.as_bytes())?;
+        file.sync_all()?;
+        Ok(Self { path, file })
+    }
+
+    /// Removes the run file, the run does not have to be continued.
+    pub fn finish(self) -> Result<(), AppError> {
+        self.file.unlock()?;
+        drop(self.file);
+        fs::remove_file(&self.path)?;
+        Ok(())
+    }
+}
+
+/// A run into the target that did not finish, found by its run file.
+#[derive(Debug)]
+pub struct InterruptedRun {
+    pub run: RunRecord,
+    path: PathBuf,
+}
+
+impl InterruptedRun {
+    /// Forgets the run, when the photos are processed again instead of continuing it.
+    pub fn discard(self) -> Result<(), AppError> {
+        info!("Not continuing the interrupted run {}", self.run.run_id);
+        fs::remove_file(&self.path)?;
+        Ok(())
+    }
+}
+
+/// The latest run over `sources` into the target that was interrupted, if any. Runs that are
+/// still going hold the lock of their run file and are left alone.
+pub fn interrupted(target: &Path, sources: &[PathBuf]) -> Result<Option<InterruptedRun>, AppError> {
+    let paths = match run_files(target) {
+        Ok(paths) => paths,
+        Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(None),
+        Err(e) => return Err(e.into()),
+    };
+    let mut latest: Option<InterruptedRun> = None;
+    for path in paths {
+        let file = File::open(&path)?;
+        if is_going(&file)? {
+            continue;
+        }
+        let run: RunRecord = match serde_json::from_reader(&file) {
+            Ok(run) => run,
+            // A crash while the run file was written leaves it empty or half written
+            Err(e) => {
+                warn!("{e}. Skipping the run file {path:?}");
+                continue;
+            }
+        };
+        if run.sources != sources {
+            continue;
+        }
+        if latest.as_ref().is_none_or(|x| x.run.run_id < run.run_id) {
+            latest = Some(InterruptedRun { run, path });
+        }
+    }
+    Ok(latest)
+}
+
+/// What an interrupted run left unfinished in the target.
+#[derive(Debug, Default)]
+pub struct Unfinished {
+    /// The numbers it claimed for the photos it did not finish, which the run continuing it
+    /// gives those photos
+    pub claims: Vec<UnfinishedClaims>,
+    /// The outputs it was writing when it was interrupted, which can be half written
+    pub leftovers: Vec<PathBuf>,
+}
+
+impl Unfinished {
+    /// Removes the outputs the interrupted run was writing, their numbers are used again.
+    pub fn remove_leftovers(&self) -> Result<(), AppError> {
+        for path in &self.leftovers {
+            warn!("Removing {path:?}, the interrupted run did not finish it");
+            fs::remove_file(path)?;
+        }
+        Ok(())
+    }
+}
+
+/// What the interrupted `run` did not finish, from the numbers it claimed that `recorded`, the
+/// manifest of the target, has no photo of. Nothing is used again while another run writes
+/// into the target, as the numbers could be its own.
+pub fn unfinished(
+    target: &Path,
+    template: &NameTemplate,
+    run: &RunRecord,
+    recorded: &[ManifestEntry],
+) -> Result<Unfinished, AppError> {
+    let mut claims = numbering::unfinished_claims(target, &run.counter, recorded)?;
+    if claims.is_empty() {
+        return Ok(Unfinished::default());
+    }
+    if other_runs_going(target)? {
+        warn!("Another run is writing into the target, the numbers the interrupted run did not finish are not used again");
+        return Ok(Unfinished::default());
+    }
+    let mut leftovers = vec![];
+    for entry in WalkDir::new(target).into_iter().filter_map(|e| e.ok()) {
+        let path = entry.path();
+        let (Some(number), Ok(relative)) = (template.number_of(path), path.strip_prefix(target))
+        else {
+            continue;
+        };
+        let folder = relative.parent().unwrap_or(Path::new(""));
+        let Some(claim) = claims.iter_mut().find(|c| c.contains(number, folder)) else {
+            continue;
+        };
+        // Files from before the run are not its own, their numbers were never free
+        let written = entry.metadata().ok().and_then(|m| m.modified().ok());
+        match written.and_then(|x| Timestamp::try_from(x).ok()) {
+            Some(written) if written >= run.started_at && entry.file_type().is_file() => {
+                leftovers.push(path.to_path_buf())
+            }
+            _ => claim.numbers.retain(|n| *n != number),
+        }
+    }
+    Ok(Unfinished { claims, leftovers })
+}
+
+/// Whether a run other than the one starting holds the lock of its run file in the target.
+fn other_runs_going(target: &Path) -> Result<bool, AppError> {
+    for path in run_files(target)? {
+        if is_going(&File::open(&path)?)? {
+            return Ok(true);
+        }
+    }
+    Ok(false)
+}
+
+/// The run files in the root of the target.
+fn run_files(target: &Path) -> io::Result<Vec<PathBuf>> {
+    let mut paths = vec![];
+    for entry in fs::read_dir(target)? {
+        let path = entry?.path();
+        let is_run_file = path
+            .file_name()
+            .and_then(|x| x.to_str())
+            .is_some_and(|name| name.starts_with(RUN_FILE_PREFIX) && name.ends_with(".json"));
+        if is_run_file {
+            paths.push(path);
+        }
+    }
+    Ok(paths)
+}
+
+/// Whether the run of the run file is still going, i.e. it holds the lock of the file.
+fn is_going(file: &File) -> Result<bool, AppError> {
+    match file.try_lock_shared() {
+        Ok(()) => Ok(false),
+        Err(TryLockError::WouldBlock) => Ok(true),
+        Err(TryLockError::Error(e)) => Err(e.into()),
+    }
+}
+
+fn run_file_path(target: &Path, run_id: &str) -> PathBuf {
+    target.join(format!("{RUN_FILE_PREFIX}{run_id}.json"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::temp_dir;
+
+    fn record(run_id: &str) -> RunRecord {
+        RunRecord {
+            run_id: run_id.to_string(),
+            sources: vec![PathBuf::from("cards")],
+            started_at: Timestamp::now(),
+            counter: BTreeMap::new(),
+        }
+    }
+
+    #[test]
+    fn a_run_file_another_run_holds_is_not_taken() {
+        let target = temp_dir("run-file");
+        let run = record("20251224T081500Z-4242-0");
+        let going = RunFile::start(target.path(), &run).unwrap();
+
+        assert!(matches!(
+            RunFile::start(target.path(), &run),
+            Err(AppError::RunFileLocked(_))
+        ));
+        assert!(other_runs_going(target.path()).unwrap());
+        assert!(interrupted(target.path(), &run.sources).unwrap().is_none());
+
+        going.finish().unwrap();
+        assert!(run_files(target.path()).unwrap().is_empty());
+    }
+}
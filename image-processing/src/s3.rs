@@ -0,0 +1,142 @@
+use std::env;
+use std::fmt;
+use std::fs;
+use std::path::Path;
+
+use ::s3::creds::Credentials;
+use ::s3::error::S3Error;
+use ::s3::{Bucket, Region};
+
+use crate::error::AppError;
+use crate::sink::{content_type, OutputSink, PutIf, S3Settings, Version};
+
+/// A bucket of S3 or S3 compatible storage like MinIO, written to with the rust-s3 client.
+pub struct S3Sink {
+    bucket: Box<Bucket>,
+    prefix: String,
+}
+
+impl S3Sink {
+    /// `location` is the bucket and optionally a prefix, e.g. `photos/bench-1`. The keys come
+    /// from the config or else the `AWS_ACCESS_KEY_ID`, `AWS_SECRET_ACCESS_KEY` and
+    /// `AWS_SESSION_TOKEN` environment variables.
+    pub fn new(location: &str, settings: &S3Settings) -> Result<Self, AppError> {
+        let (bucket, prefix) = location.split_once('/').unwrap_or((location, ""));
+        if bucket.is_empty() {
+            return Err(AppError::UnknownTarget(format!("s3://{location}")));
+        }
+        let from_env = |name: &str| env::var(name).ok().filter(|v| !v.is_empty());
+        let access_key = settings
+            .access_key
+            .clone()
+            .or_else(|| from_env("AWS_ACCESS_KEY_ID"))
+            .ok_or_else(|| {
+                AppError::Upload("No S3 access key in the config or AWS_ACCESS_KEY_ID".into())
+            })?;
+        let secret_key = settings
+            .secret_key
+            .clone()
+            .or_else(|| from_env("AWS_SECRET_ACCESS_KEY"))
+            .ok_or_else(|| {
+                AppError::Upload("No S3 secret key in the config or AWS_SECRET_ACCESS_KEY".into())
+            })?;
+        let session_token = from_env("AWS_SESSION_TOKEN");
+        let credentials = Credentials::new(
+            Some(&access_key),
+            Some(&secret_key),
+            None,
+            session_token.as_deref(),
+            None,
+        )
+        .map_err(|e| AppError::Upload(e.to_string()))?;
+        // AWS is addressed by the bucket name in the host, other endpoints like MinIO by the
+        // bucket in the path
+        let bucket = match &settings.endpoint {
+            Some(endpoint) => {
+                let region = Region::Custom {
+                    region: settings.region.clone(),
+                    endpoint: endpoint.trim_end_matches('/').to_string(),
+                };
+                Bucket::new(bucket, region, credentials)
+                    .map_err(upload_error)?
+                    .with_path_style()
+            }
+            None => {
+                let region: Region = settings
+                    .region
+                    .parse()
+                    .map_err(|e: std::str::Utf8Error| AppError::Upload(e.to_string()))?;
+                Bucket::new(bucket, region, credentials).map_err(upload_error)?
+            }
+        };
+        Ok(Self {
+            bucket,
+            prefix: prefix.trim_end_matches('/').to_string(),
+        })
+    }
+
+    /// The path of `key` in the bucket, under the prefix.
+    fn path(&self, key: &str) -> String {
+        match self.prefix.as_str() {
+            "" => key.to_string(),
+            prefix => format!("{prefix}/{key}"),
+        }
+    }
+}
+
+impl OutputSink for S3Sink {
+    fn put(&self, file: &Path, key: &str, _resume: bool, condition: PutIf) -> Result<(), AppError> {
+        let content = fs::read(file)?;
+        let mut put = self
+            .bucket
+            .put_object_builder(self.path(key), &content)
+            .with_content_type(content_type(file));
+        put = match condition {
+            PutIf::Always => put,
+            PutIf::Absent => put
+                .with_header("If-None-Match", "*")
+                .map_err(upload_error)?,
+            PutIf::Unchanged(etag) => put.with_header("If-Match", etag).map_err(upload_error)?,
+        };
+        match put.execute() {
+            Ok(_) => Ok(()),
+            Err(S3Error::HttpFailWithBody(412, _)) => Err(AppError::AlreadyStored(key.to_string())),
+            Err(e) => Err(upload_error(e)),
+        }
+    }
+
+    /// The file is read into memory to keep its ETag, only the manifest is fetched.
+    fn get(&self, key: &str, to: &Path) -> Result<Option<Version>, AppError> {
+        let response = match self.bucket.get_object(self.path(key)) {
+            Ok(response) => response,
+            Err(S3Error::HttpFailWithBody(404, _)) => return Ok(None),
+            Err(e) => return Err(upload_error(e)),
+        };
+        match response.status_code() {
+            200..=299 => {}
+            404 => return Ok(None),
+            status => {
+                return Err(AppError::Upload(format!(
+                    "Could not get {key}, the server returned {status}"
+                )));
+            }
+        }
+        fs::write(to, response.bytes())?;
+        Ok(Some(Version {
+            etag: response.headers().get("etag").cloned(),
+        }))
+    }
+}
+
+fn upload_error(e: S3Error) -> AppError {
+    AppError::Upload(e.to_string())
+}
+
+impl fmt::Display for S3Sink {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.prefix.as_str() {
+            "" => write!(f, "s3://{}", self.bucket.name),
+            prefix => write!(f, "s3://{}/{prefix}", self.bucket.name),
+        }
+    }
+}
@@ -0,0 +1,167 @@
+use std::env::{self, consts};
+use std::path::{Path, PathBuf};
+use std::{fmt, fs};
+
+use serde::Deserialize;
+use tracing::{debug, info};
+
+use crate::error::AppError;
+use crate::hashing;
+
+const RELEASES_URL: &str = "https://api.github.com/repos/lanklaas/photo-date-bench/releases/latest";
+
+/// The largest download that is read, well over the size of a release binary.
+const MAX_DOWNLOAD_BYTES: u64 = 256 * 1024 * 1024;
+
+/// The parts of a GitHub release that are needed to update.
+#[derive(Debug, Deserialize)]
+struct Release {
+    tag_name: String,
+    assets: Vec<Asset>,
+}
+
+#[derive(Debug, Deserialize)]
+struct Asset {
+    name: String,
+    browser_download_url: String,
+}
+
+/// What [`self_update`] did.
+#[derive(Debug)]
+pub enum UpdateOutcome {
+    UpToDate { version: String },
+    Available { version: String },
+    Updated { version: String, exe: PathBuf },
+}
+
+/// Checks the latest GitHub release and, unless `check_only`, replaces the running binary with
+/// the one built for this platform. The binary of a release is named
+/// `photo-date-bench-<arch>-<os>`, with `.exe` on Windows, e.g.
+/// `photo-date-bench-x86_64-windows.exe`, and needs a `<name>.sha256` next to it that the
+/// download is checked against. A release without one is not installed.
+pub fn self_update(check_only: bool) -> Result<UpdateOutcome, AppError> {
+    let release: Release = serde_json::from_slice(&download(RELEASES_URL)?)?;
+    let version = release.tag_name.trim_start_matches('v').to_string();
+    if parse_version(&version) <= parse_version(env!("CARGO_PKG_VERSION")) {
+        return Ok(UpdateOutcome::UpToDate { version });
+    }
+    if check_only {
+        return Ok(UpdateOutcome::Available { version });
+    }
+
+    let name = asset_name();
+    let find_asset = |asset_name: &str| {
+        release
+            .assets
+            .iter()
+            .find(|asset| asset.name == asset_name)
+            .ok_or_else(|| {
+                AppError::SelfUpdate(format!(
+                    "Release {version} has no {asset_name} for this platform"
+                ))
+            })
+    };
+    let asset = find_asset(&name)?;
+    // Checked before the download, so a release without a checksum is never installed
+    let checksum = find_asset(&format!("{name}.sha256"))?;
+    info!("Downloading {}", asset.browser_download_url);
+    let binary = download(&asset.browser_download_url)?;
+    let expected = String::from_utf8_lossy(&download(&checksum.browser_download_url)?)
+        .split_whitespace()
+        .next()
+        .unwrap_or_default()
+        .to_lowercase();
+    if hashing::sha256_bytes(&binary) != expected {
+        return Err(AppError::SelfUpdate(format!(
+            "The download of {name} does not match its checksum"
+        )));
+    }
+    debug!("The checksum of {name} matches");
+
+    let exe = env::current_exe()?;
+    replace_exe(&exe, &binary)?;
+    Ok(UpdateOutcome::Updated { version, exe })
+}
+
+impl fmt::Display for UpdateOutcome {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            UpdateOutcome::UpToDate { version } => write!(
+                f,
+                "Version {} is up to date, the latest release is {version}",
+                env!("CARGO_PKG_VERSION")
+            ),
+            UpdateOutcome::Available { version } => write!(
+                f,
+                "Version {version} is available, run self-update to install it"
+            ),
+            UpdateOutcome::Updated { version, exe } => {
+                write!(f, "Updated {} to version {version}", exe.display())
+            }
+        }
+    }
+}
+
+/// Swaps the new binary in next to the running one. Windows does not allow a running binary
+/// to be overwritten, but it can be renamed, so the old one is moved aside and removed on the
+/// next update.
+fn replace_exe(exe: &Path, binary: &[u8]) -> Result<(), AppError> {
+    let new = exe.with_extension("new");
+    let old = exe.with_extension("old");
+    if old.exists() {
+        fs::remove_file(&old)?;
+    }
+    fs::write(&new, binary)?;
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        fs::set_permissions(&new, fs::Permissions::from_mode(0o755))?;
+    }
+    fs::rename(exe, &old)?;
+    if let Err(e) = fs::rename(&new, exe) {
+        // Put the old binary back so the program still runs
+        fs::rename(&old, exe)?;
+        return Err(e.into());
+    }
+    #[cfg(not(target_os = "windows"))]
+    fs::remove_file(&old)?;
+    Ok(())
+}
+
+/// The name of the release binary for this platform.
+fn asset_name() -> String {
+    format!(
+        "photo-date-bench-{}-{}{}",
+        consts::ARCH,
+        consts::OS,
+        consts::EXE_SUFFIX
+    )
+}
+
+/// The numbers of a version like `1.2.3`, without a pre-release part, to compare versions.
+fn parse_version(version: &str) -> Vec<u64> {
+    version
+        .split(['-', '+'])
+        .next()
+        .unwrap_or_default()
+        .split('.')
+        .map(|part| part.parse().unwrap_or(0))
+        .collect()
+}
+
+fn download(url: &str) -> Result<Vec<u8>, AppError> {
+    let error = |e: ureq::Error| AppError::SelfUpdate(format!("Could not download {url}: {e}"));
+    let mut response = ureq::get(url)
+        .header(
+            "User-Agent",
+            concat!("photo-date-bench/", env!("CARGO_PKG_VERSION")),
+        )
+        .call()
+        .map_err(error)?;
+    response
+        .body_mut()
+        .with_config()
+        .limit(MAX_DOWNLOAD_BYTES)
+        .read_to_vec()
+        .map_err(error)
+}
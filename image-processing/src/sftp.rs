@@ -0,0 +1,365 @@
+use std::collections::HashSet;
+use std::env;
+use std::fmt;
+use std::fs::File;
+use std::io::{self, Seek, SeekFrom};
+use std::net::{TcpStream, ToSocketAddrs};
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use std::time::{Duration, SystemTime};
+
+use ssh2::{CheckResult, ErrorCode, KnownHostFileKind, OpenFlags, OpenType, Session, Sftp};
+
+use crate::error::AppError;
+use crate::sink::{OutputSink, PutIf, SftpSettings, Version};
+
+/// The SFTP status of a file that is not there, `SSH_FX_NO_SUCH_FILE`.
+const NO_SUCH_FILE: i32 = 2;
+/// The keys in `~/.ssh` that are tried when the config has no key or password, like ssh does.
+const DEFAULT_KEYS: [&str; 3] = ["id_ed25519", "id_ecdsa", "id_rsa"];
+const CONNECT_TIMEOUT: Duration = Duration::from_secs(30);
+/// How long a read or write may stall before the put is given up, in milliseconds.
+const STALL_TIMEOUT_MS: u32 = 60_000;
+/// How old the lock file of a put that needs a file unchanged may get before another bench
+/// takes it over, when the bench that made it lost its link.
+const STALE_LOCK: Duration = Duration::from_secs(600);
+
+/// A folder on a server reached over SSH, written to with libssh2.
+pub struct SftpSink {
+    /// `user@host:port/path`, the path from the root of the server
+    location: String,
+    user: String,
+    host: String,
+    port: u16,
+    /// The folder of the target from the root of the server, without the last `/`
+    root: String,
+    settings: SftpSettings,
+    /// The session of the last put, made again after a put that failed
+    connection: Mutex<Option<Sftp>>,
+    /// The keys no file was stored under before their put started, the partial file a failed
+    /// put leaves under them is continued
+    checked: Mutex<HashSet<String>>,
+}
+
+impl SftpSink {
+    /// `location` is the url without `sftp://`, e.g. `bench@archive.local/srv/photos`. Without
+    /// a user in the url the user logged in to this computer is used.
+    pub fn new(location: &str, settings: &SftpSettings) -> Result<Self, AppError> {
+        let (authority, root) = location.split_once('/').unwrap_or((location, ""));
+        let (user, address) = match authority.split_once('@') {
+            Some((user, address)) => (Some(user), address),
+            None => (None, authority),
+        };
+        let (host, port) = match address.rsplit_once(':') {
+            Some((host, port)) => (host, port.parse().ok()),
+            None => (address, Some(22)),
+        };
+        let (Some(port), false, false) = (port, host.is_empty(), user == Some("")) else {
+            return Err(AppError::UnknownTarget(format!("sftp://{location}")));
+        };
+        if settings.password.is_some() && user.is_none() {
+            return Err(AppError::Upload(format!(
+                "Give the user of the password in the url, e.g. sftp://user@{location}"
+            )));
+        }
+        let user = match user {
+            Some(user) => user.to_string(),
+            None => env::var("USER")
+                .or_else(|_| env::var("USERNAME"))
+                .map_err(|_| {
+                    AppError::Upload(format!(
+                        "Give the user in the url, e.g. sftp://user@{location}"
+                    ))
+                })?,
+        };
+        Ok(Self {
+            location: location.to_string(),
+            user,
+            host: host.to_string(),
+            port,
+            root: match root.trim_end_matches('/') {
+                "" => String::new(),
+                root => format!("/{root}"),
+            },
+            settings: settings.clone(),
+            connection: Mutex::new(None),
+            checked: Mutex::new(HashSet::new()),
+        })
+    }
+
+    /// The path of `key` on the server.
+    fn path(&self, key: &str) -> String {
+        format!("{}/{key}", self.root)
+    }
+
+    /// Runs `f` with the session of the earlier calls, or a new one. A session that failed is
+    /// dropped, so the put is tried again on a new one when the link dropped.
+    fn with_sftp<T>(&self, f: impl FnOnce(&Sftp) -> Result<T, AppError>) -> Result<T, AppError> {
+        let mut connection = self.connection.lock().unwrap_or_else(|e| e.into_inner());
+        let sftp = match connection.take() {
+            Some(sftp) => sftp,
+            None => self.connect()?,
+        };
+        let result = f(&sftp);
+        if matches!(result, Ok(_) | Err(AppError::AlreadyStored(_))) {
+            *connection = Some(sftp);
+        }
+        result
+    }
+
+    fn connect(&self) -> Result<Sftp, AppError> {
+        let address = (self.host.as_str(), self.port)
+            .to_socket_addrs()?
+            .next()
+            .ok_or_else(|| AppError::Upload(format!("Could not find {}", self.host)))?;
+        let tcp = TcpStream::connect_timeout(&address, CONNECT_TIMEOUT)?;
+        let mut session = Session::new().map_err(ssh_error)?;
+        session.set_tcp_stream(tcp);
+        session.set_timeout(STALL_TIMEOUT_MS);
+        session.handshake().map_err(ssh_error)?;
+        self.check_host_key(&session)?;
+        self.log_in(&session)?;
+        session.sftp().map_err(ssh_error)
+    }
+
+    /// The server has to be in `~/.ssh/known_hosts` with the key it sent, so the photos never
+    /// go to a server posing as it.
+    fn check_host_key(&self, session: &Session) -> Result<(), AppError> {
+        let mut known_hosts = session.known_hosts().map_err(ssh_error)?;
+        if let Some(file) = ssh_file("known_hosts") {
+            known_hosts
+                .read_file(&file, KnownHostFileKind::OpenSSH)
+                .map_err(ssh_error)?;
+        }
+        let (key, _) = session
+            .host_key()
+            .ok_or_else(|| AppError::Upload(format!("{} sent no host key", self.host)))?;
+        match known_hosts.check_port(&self.host, self.port, key) {
+            CheckResult::Match => Ok(()),
+            CheckResult::Mismatch => Err(AppError::Upload(format!(
+                "The host key of {} is not the one in ~/.ssh/known_hosts",
+                self.host
+            ))),
+            CheckResult::NotFound | CheckResult::Failure => Err(AppError::Upload(format!(
+                "{} is not in ~/.ssh/known_hosts, connect to it with ssh once to add it",
+                self.host
+            ))),
+        }
+    }
+
+    /// Logs in with the password or key of the config, or else like ssh with the agent and
+    /// the keys in `~/.ssh`.
+    fn log_in(&self, session: &Session) -> Result<(), AppError> {
+        let passphrase = self.settings.passphrase.as_deref();
+        if let Some(password) = &self.settings.password {
+            session
+                .userauth_password(&self.user, password)
+                .map_err(ssh_error)?;
+        } else if let Some(key) = &self.settings.key {
+            session
+                .userauth_pubkey_file(&self.user, None, key, passphrase)
+                .map_err(ssh_error)?;
+        } else {
+            let _ = session.userauth_agent(&self.user);
+            for key in DEFAULT_KEYS.into_iter().filter_map(ssh_file) {
+                if session.authenticated() {
+                    break;
+                }
+                let _ = session.userauth_pubkey_file(&self.user, None, &key, passphrase);
+            }
+        }
+        match session.authenticated() {
+            true => Ok(()),
+            false => Err(AppError::Upload(format!(
+                "Could not log in to {} as {}",
+                self.host, self.user
+            ))),
+        }
+    }
+}
+
+impl OutputSink for SftpSink {
+    /// The first put of a key that needs it absent creates the file exclusively, so it never
+    /// replaces a file stored under the key. A put that needs it unchanged is done while
+    /// holding a lock file next to it, see [`put_unchanged`].
+    fn put(&self, file: &Path, key: &str, resume: bool, condition: PutIf) -> Result<(), AppError> {
+        let checked = || self.checked.lock().unwrap_or_else(|e| e.into_inner());
+        let first = condition == PutIf::Absent && !checked().contains(key);
+        let path = self.path(key);
+        let path = Path::new(&path);
+        if let PutIf::Unchanged(version) = condition {
+            return self.with_sftp(|sftp| put_unchanged(sftp, file, key, path, version));
+        }
+        self.with_sftp(|sftp| {
+            make_folders(sftp, path)?;
+            let (flags, offset) = if first {
+                (OpenFlags::WRITE | OpenFlags::EXCLUSIVE, 0)
+            } else if resume {
+                // Appends what is missing to the partial file the failed put left
+                let size = match sftp.stat(path) {
+                    Ok(stat) => stat.size.unwrap_or(0),
+                    Err(e) if not_found(&e) => 0,
+                    Err(e) => return Err(ssh_error(e)),
+                };
+                (OpenFlags::WRITE | OpenFlags::CREATE, size)
+            } else {
+                (OpenFlags::WRITE | OpenFlags::TRUNCATE, 0)
+            };
+            let mut remote = match sftp.open_mode(path, flags, 0o644, OpenType::File) {
+                Ok(remote) => remote,
+                Err(e) => {
+                    if first && exists(sftp, path)? {
+                        return Err(AppError::AlreadyStored(key.to_string()));
+                    }
+                    return Err(ssh_error(e));
+                }
+            };
+            if first {
+                checked().insert(key.to_string());
+            }
+            let mut local = File::open(file)?;
+            local.seek(SeekFrom::Start(offset))?;
+            remote.seek(SeekFrom::Start(offset))?;
+            io::copy(&mut local, &mut remote)?;
+            Ok(())
+        })
+    }
+
+    fn get(&self, key: &str, to: &Path) -> Result<Option<Version>, AppError> {
+        let path = self.path(key);
+        self.with_sftp(|sftp| {
+            let mut remote = match sftp.open(Path::new(&path)) {
+                Ok(remote) => remote,
+                Err(e) if not_found(&e) => return Ok(None),
+                Err(e) => return Err(ssh_error(e)),
+            };
+            let stat = remote.stat().map_err(ssh_error)?;
+            io::copy(&mut remote, &mut File::create(to)?)?;
+            Ok(Some(Version {
+                etag: Some(version_of(&stat)),
+            }))
+        })
+    }
+}
+
+impl fmt::Display for SftpSink {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "sftp://{}", self.location)
+    }
+}
+
+/// Replaces the file at `path` with `file` while it is still the version `version`. SFTP has
+/// no conditional writes, so the benches take turns with a lock file next to it, made
+/// exclusively. A file that changed, or a lock another bench holds, fails with
+/// [`AppError::AlreadyStored`]. The file is written from the start, a partial write changes
+/// its version and is merged again.
+fn put_unchanged(
+    sftp: &Sftp,
+    file: &Path,
+    key: &str,
+    path: &Path,
+    version: &str,
+) -> Result<(), AppError> {
+    make_folders(sftp, path)?;
+    let lock = path.with_file_name(format!(
+        "{}.lock",
+        path.file_name().unwrap_or_default().to_string_lossy()
+    ));
+    let take_lock = || {
+        sftp.open_mode(
+            &lock,
+            OpenFlags::WRITE | OpenFlags::EXCLUSIVE,
+            0o644,
+            OpenType::File,
+        )
+    };
+    if let Err(e) = take_lock() {
+        let stale = match sftp.stat(&lock) {
+            Ok(stat) => stat.mtime.is_some_and(|mtime| {
+                let now = SystemTime::now()
+                    .duration_since(SystemTime::UNIX_EPOCH)
+                    .unwrap_or_default();
+                now.as_secs().saturating_sub(mtime) > STALE_LOCK.as_secs()
+            }),
+            // Let go of in the meantime, or the folder can not be written to. Tried again like
+            // a dropped link
+            Err(not) if not_found(&not) => return Err(ssh_error(e)),
+            Err(e) => return Err(ssh_error(e)),
+        };
+        if !stale {
+            return Err(AppError::AlreadyStored(key.to_string()));
+        }
+        sftp.unlink(&lock).map_err(ssh_error)?;
+        take_lock().map_err(|_| AppError::AlreadyStored(key.to_string()))?;
+    }
+    let written = (|| {
+        let stored = match sftp.stat(path) {
+            Ok(stat) => version_of(&stat),
+            Err(e) if not_found(&e) => String::new(),
+            Err(e) => return Err(ssh_error(e)),
+        };
+        if stored != version {
+            return Err(AppError::AlreadyStored(key.to_string()));
+        }
+        let flags = OpenFlags::WRITE | OpenFlags::TRUNCATE;
+        let mut remote = sftp
+            .open_mode(path, flags, 0o644, OpenType::File)
+            .map_err(ssh_error)?;
+        io::copy(&mut File::open(file)?, &mut remote)?;
+        Ok(())
+    })();
+    let unlocked = sftp.unlink(&lock).map_err(ssh_error);
+    written.and(unlocked)
+}
+
+/// The version of a file on the server, its modification time and size.
+fn version_of(stat: &ssh2::FileStat) -> String {
+    format!(
+        "{}-{}",
+        stat.mtime.unwrap_or_default(),
+        stat.size.unwrap_or_default()
+    )
+}
+
+/// Makes the folders of `path` that are not on the server yet.
+fn make_folders(sftp: &Sftp, path: &Path) -> Result<(), AppError> {
+    let mut folders: Vec<&Path> = path.ancestors().skip(1).collect();
+    folders.reverse();
+    for folder in folders {
+        if folder.as_os_str().len() <= 1 || exists(sftp, folder)? {
+            continue;
+        }
+        if let Err(e) = sftp.mkdir(folder, 0o755) {
+            // Another bench can make it in the meantime
+            if !exists(sftp, folder)? {
+                return Err(ssh_error(e));
+            }
+        }
+    }
+    Ok(())
+}
+
+fn exists(sftp: &Sftp, path: &Path) -> Result<bool, AppError> {
+    match sftp.stat(path) {
+        Ok(_) => Ok(true),
+        Err(e) if not_found(&e) => Ok(false),
+        Err(e) => Err(ssh_error(e)),
+    }
+}
+
+fn not_found(e: &ssh2::Error) -> bool {
+    matches!(e.code(), ErrorCode::SFTP(NO_SUCH_FILE))
+}
+
+/// A file in `~/.ssh`, when it is there.
+fn ssh_file(name: &str) -> Option<PathBuf> {
+    let file = directories::BaseDirs::new()?
+        .home_dir()
+        .join(".ssh")
+        .join(name);
+    file.exists().then_some(file)
+}
+
+fn ssh_error(e: ssh2::Error) -> AppError {
+    AppError::Upload(e.to_string())
+}
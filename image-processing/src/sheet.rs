@@ -0,0 +1,313 @@
+use std::collections::{BTreeMap, HashMap};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use image::codecs::jpeg::{JpegEncoder, PixelDensity};
+use image::{Rgb, RgbImage};
+use imageproc::drawing::draw_line_segment_mut;
+use jiff::civil::{Date, DateTime, Time};
+use walkdir::WalkDir;
+
+use crate::config::Config;
+use crate::draw_text::{
+    DrawPosition, FontSize, MultilineDraw, PhotoOffset, PhotoSize, StampOffset,
+};
+use crate::error::AppError;
+use crate::{fonts, image_ops, manifest, thumbnails};
+
+const A4_WIDTH_MM: f32 = 210.0;
+const A4_HEIGHT_MM: f32 = 297.0;
+const PAGE_MARGIN_MM: f32 = 10.0;
+const HEADER_MM: f32 = 15.0;
+const HEADER_PT: usize = 14;
+/// Length of the cut marks at the corners of every photo
+const CUT_MARK_MM: f32 = 4.0;
+/// Space between a photo and its cut marks
+const CUT_MARK_GAP_MM: f32 = 1.0;
+
+/// How many photos go onto one A4 page.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, clap::ValueEnum)]
+pub enum SheetLayout {
+    /// Two columns and two rows
+    #[default]
+    #[value(name = "4")]
+    Four,
+    /// Two columns and three rows
+    #[value(name = "6")]
+    Six,
+}
+
+impl SheetLayout {
+    fn grid(&self) -> (u32, u32) {
+        match self {
+            SheetLayout::Four => (2, 2),
+            SheetLayout::Six => (2, 3),
+        }
+    }
+
+    fn per_page(&self) -> usize {
+        let (cols, rows) = self.grid();
+        (cols * rows) as usize
+    }
+}
+
+/// Composes the processed photos in the target onto A4 pages, one set of pages per
+/// output folder. Writes a jpeg per page, or one pdf with all the pages.
+/// Returns the files written.
+pub fn compose_sheets(
+    target: &Path,
+    config: &Config,
+    layout: SheetLayout,
+    out: &Path,
+    pdf: bool,
+) -> Result<Vec<PathBuf>, AppError> {
+    let template = config.name_template()?;
+    let font = fonts::load_font(config.date_font())?;
+    let dates: HashMap<PathBuf, DateTime> = manifest::read_manifest(target)?
+        .into_iter()
+        .map(|e| (target.join(e.output), e.date))
+        .collect();
+
+    // The photos per output folder in the order of their numbers
+    let mut folders: BTreeMap<PathBuf, Vec<(usize, PathBuf)>> = BTreeMap::new();
+    for entry in WalkDir::new(target).into_iter().filter_map(|e| e.ok()) {
+        let path = entry.path();
+        if !entry.file_type().is_file() || !image_ops::is_image_file(path) {
+            continue;
+        }
+        let Some(number) = template.number_of(path) else {
+            continue;
+        };
+        let folder = path.parent().unwrap_or(target).to_path_buf();
+        folders
+            .entry(folder)
+            .or_default()
+            .push((number, path.to_path_buf()));
+    }
+
+    fs::create_dir_all(out)?;
+    let mut pages = vec![];
+    for (folder, mut photos) in folders {
+        photos.sort();
+        let name = folder
+            .strip_prefix(target)
+            .unwrap_or(&folder)
+            .to_string_lossy()
+            .replace(['/', '\\'], "_");
+        let name = if name.is_empty() {
+            "sheet".into()
+        } else {
+            name
+        };
+        for (i, photos) in photos.chunks(layout.per_page()).enumerate() {
+            let paths: Vec<&Path> = photos.iter().map(|(_, p)| p.as_path()).collect();
+            let header = header_text(&paths, &dates, config);
+            let page = compose_page(&paths, &header, layout, config, &font)?;
+            pages.push((format!("{name}_{:02}", i + 1), page));
+        }
+    }
+
+    if pdf {
+        let path = out.join("sheets.pdf");
+        let mut jpegs = vec![];
+        for (_, page) in &pages {
+            jpegs.push((page.width(), page.height(), encode_jpeg(page, config)?));
+        }
+        write_pdf(&jpegs, &path)?;
+        return Ok(vec![path]);
+    }
+
+    let mut written = vec![];
+    for (name, page) in &pages {
+        let path = out.join(format!("{name}.jpg"));
+        fs::write(&path, encode_jpeg(page, config)?)?;
+        written.push(path);
+    }
+    Ok(written)
+}
+
+/// The dates of the photos on a page, e.g. `24 12 2025` or `24 12 2025 bis 26 12 2025`.
+fn header_text(photos: &[&Path], dates: &HashMap<PathBuf, DateTime>, config: &Config) -> String {
+    let mut days: Vec<Date> = photos
+        .iter()
+        .filter_map(|p| {
+            dates.get(*p).map(|d| d.date()).or_else(|| {
+                // Photos processed before there was a manifest are found by their date folder
+                let folder = p.parent()?.file_name()?.to_str()?;
+                Date::strptime("%Y%m%d", folder).ok()
+            })
+        })
+        .collect();
+    days.sort();
+    let format = |d: &Date| config.format_date(&d.to_datetime(Time::midnight()));
+    match (days.first(), days.last()) {
+        (Some(first), Some(last)) if first != last => {
+            config.locale.date_range(&format(first), &format(last))
+        }
+        (Some(day), _) => format(day),
+        _ => String::new(),
+    }
+}
+
+fn compose_page(
+    photos: &[&Path],
+    header: &str,
+    layout: SheetLayout,
+    config: &Config,
+    font: &ab_glyph::FontArc,
+) -> Result<RgbImage, AppError> {
+    let (width, height) = (config.mm_to_px(A4_WIDTH_MM), config.mm_to_px(A4_HEIGHT_MM));
+    let margin = config.mm_to_px(PAGE_MARGIN_MM);
+    let header_px = config.mm_to_px(HEADER_MM);
+    let mut page = RgbImage::from_pixel(width, height, Rgb([255, 255, 255]));
+
+    let mut draw = MultilineDraw {
+        photo_size: PhotoSize {
+            width: width - 2 * margin,
+            height: header_px,
+        },
+        photo_offset: PhotoOffset {
+            x: margin,
+            y: margin,
+        },
+        margin_px: 0,
+        safe_area_px: 0,
+        offset_px: StampOffset::default(),
+        destination: &mut page,
+        rtl: config.locale.is_rtl(),
+    };
+    let font_size = FontSize {
+        pt: HEADER_PT,
+        dpi: config.dpi,
+    };
+    draw.draw_multiline_text(
+        &[header],
+        font,
+        font_size,
+        image::Rgba([0, 0, 0, 255]),
+        config.for_direction(DrawPosition::TopLeft),
+    );
+
+    let (cols, rows) = layout.grid();
+    let cell_w = (width - 2 * margin) / cols;
+    let cell_h = (height - 2 * margin - header_px) / rows;
+    for (i, path) in photos.iter().enumerate() {
+        let (col, row) = (i as u32 % cols, i as u32 / cols);
+        // Only ever made smaller so the photos print at the size they were processed for
+        let photo = thumbnails::thumbnail(path, cell_w, cell_h)?;
+        let x = margin + col * cell_w + (cell_w - photo.width()) / 2;
+        let y = margin + header_px + row * cell_h + (cell_h - photo.height()) / 2;
+        image_ops::blit_rgb(&mut page, &photo, x, y)?;
+        draw_cut_marks(&mut page, x, y, photo.width(), photo.height(), config);
+    }
+    Ok(page)
+}
+
+/// Draws short lines outside the corners of a photo along its edges to cut along.
+fn draw_cut_marks(page: &mut RgbImage, x: u32, y: u32, w: u32, h: u32, config: &Config) {
+    let color = Rgb([128, 128, 128]);
+    let len = config.mm_to_px(CUT_MARK_MM) as f32;
+    let gap = config.mm_to_px(CUT_MARK_GAP_MM) as f32;
+    let (left, top) = (x as f32, y as f32);
+    let (right, bottom) = ((x + w) as f32, (y + h) as f32);
+    for (cx, cy, dx, dy) in [
+        (left, top, -1.0, -1.0),
+        (right, top, 1.0, -1.0),
+        (left, bottom, -1.0, 1.0),
+        (right, bottom, 1.0, 1.0),
+    ] {
+        // Horizontal mark in line with the top or bottom edge
+        draw_line_segment_mut(
+            page,
+            (cx + dx * gap, cy),
+            (cx + dx * (gap + len), cy),
+            color,
+        );
+        // Vertical mark in line with the left or right edge
+        draw_line_segment_mut(
+            page,
+            (cx, cy + dy * gap),
+            (cx, cy + dy * (gap + len)),
+            color,
+        );
+    }
+}
+
+pub(crate) fn encode_jpeg(page: &RgbImage, config: &Config) -> Result<Vec<u8>, AppError> {
+    let mut bytes = vec![];
+    let mut encoder = JpegEncoder::new_with_quality(&mut bytes, config.jpeg_quality);
+    encoder.set_pixel_density(PixelDensity::dpi(config.dpi.round() as u16));
+    encoder.encode_image(page)?;
+    Ok(bytes)
+}
+
+/// Writes a pdf with one A4 page per jpeg page. The jpegs are embedded as they are.
+fn write_pdf(pages: &[(u32, u32, Vec<u8>)], out: &Path) -> Result<(), AppError> {
+    // A4 in points
+    let (page_w, page_h) = (595.28, 841.89);
+    let mut pdf = b"%PDF-1.4\n".to_vec();
+    let mut offsets = vec![];
+
+    // Objects 1 and 2 are the catalog and the page tree, every page has 3 objects after that
+    let page_ids: Vec<usize> = (0..pages.len()).map(|i| 3 + i * 3).collect();
+    let kids = page_ids
+        .iter()
+        .map(|id| format!("{id} 0 R"))
+        .collect::<Vec<_>>()
+        .join(" ");
+    offsets.push(pdf.len());
+    pdf.extend_from_slice(b"1 0 obj\n<< /Type /Catalog /Pages 2 0 R >>\nendobj\n");
+    offsets.push(pdf.len());
+    pdf.extend_from_slice(
+        format!(
+            "2 0 obj\n<< /Type /Pages /Kids [{kids}] /Count {} >>\nendobj\n",
+            pages.len()
+        )
+        .as_bytes(),
+    );
+
+    for ((w, h, jpeg), page_id) in pages.iter().zip(page_ids) {
+        let (content_id, image_id) = (page_id + 1, page_id + 2);
+        offsets.push(pdf.len());
+        pdf.extend_from_slice(
+            format!(
+                "{page_id} 0 obj\n<< /Type /Page /Parent 2 0 R /MediaBox [0 0 {page_w} {page_h}] \
+                 /Resources << /XObject << /Im0 {image_id} 0 R >> >> /Contents {content_id} 0 R >>\nendobj\n"
+            )
+            .as_bytes(),
+        );
+        let content = format!("q {page_w} 0 0 {page_h} 0 0 cm /Im0 Do Q");
+        offsets.push(pdf.len());
+        pdf.extend_from_slice(
+            format!(
+                "{content_id} 0 obj\n<< /Length {} >>\nstream\n{content}\nendstream\nendobj\n",
+                content.len()
+            )
+            .as_bytes(),
+        );
+        offsets.push(pdf.len());
+        pdf.extend_from_slice(
+            format!(
+                "{image_id} 0 obj\n<< /Type /XObject /Subtype /Image /Width {w} /Height {h} \
+                 /ColorSpace /DeviceRGB /BitsPerComponent 8 /Filter /DCTDecode /Length {} >>\nstream\n",
+                jpeg.len()
+            )
+            .as_bytes(),
+        );
+        pdf.extend_from_slice(jpeg);
+        pdf.extend_from_slice(b"\nendstream\nendobj\n");
+    }
+
+    let xref = pdf.len();
+    let mut table = format!("xref\n0 {}\n0000000000 65535 f \n", offsets.len() + 1);
+    for offset in &offsets {
+        table.push_str(&format!("{offset:010} 00000 n \n"));
+    }
+    table.push_str(&format!(
+        "trailer\n<< /Size {} /Root 1 0 R >>\nstartxref\n{xref}\n%%EOF\n",
+        offsets.len() + 1
+    ));
+    pdf.extend_from_slice(table.as_bytes());
+    fs::write(out, pdf)?;
+    Ok(())
+}
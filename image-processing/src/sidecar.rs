@@ -0,0 +1,38 @@
+use std::fs;
+use std::path::Path;
+
+use jiff::civil::DateTime;
+use serde::Serialize;
+
+use crate::config::Config;
+use crate::error::AppError;
+use crate::metadata::ExifSummary;
+use crate::quality::QualityWarning;
+
+/// Everything known about an output photo, written as json next to it so other
+/// systems do not have to read the stamped pixels.
+#[derive(Debug, Serialize)]
+pub struct Sidecar<'a> {
+    pub number: usize,
+    /// The original file that was processed
+    pub source: &'a Path,
+    pub original_file_name: &'a str,
+    pub date: DateTime,
+    pub exif: Option<ExifSummary>,
+    /// The settings the photo was processed with
+    pub config: &'a Config,
+    /// SHA-256 of the original file
+    pub original_sha256: String,
+    /// SHA-256 of the output file
+    pub output_sha256: &'a str,
+    pub quality_warnings: &'a [QualityWarning],
+}
+
+impl Sidecar<'_> {
+    /// Writes the sidecar next to the output photo, e.g. `12.json` for `12.jpg`.
+    pub fn write(&self, output: &Path) -> Result<(), AppError> {
+        let json = serde_json::to_string_pretty(self)?;
+        fs::write(output.with_extension("json"), json)?;
+        Ok(())
+    }
+}
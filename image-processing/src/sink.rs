@@ -0,0 +1,506 @@
+use std::collections::{BTreeSet, HashSet};
+use std::fmt;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::thread;
+use std::time::{Duration, SystemTime};
+
+use serde::{Deserialize, Serialize};
+use tracing::{info, warn};
+use walkdir::WalkDir;
+
+use crate::config::Config;
+use crate::error::AppError;
+use crate::manifest::{read_manifest, read_manifest_file, Manifest, MANIFEST_FILE_NAME};
+use crate::naming::NameTemplate;
+use crate::numbering::COUNTER_FILE_NAME;
+
+/// Folder in the data directory the outputs for remote targets are written to first.
+const STAGING_DIR: &str = "remote-targets";
+/// File in a staging folder with the keys that could not be stored, tried again by the next run.
+const PENDING_FILE_NAME: &str = ".pending-uploads";
+/// File in a staging folder the manifest of the sink is fetched into.
+const FETCHED_MANIFEST_FILE_NAME: &str = ".fetched-manifest.jsonl";
+/// How often the manifest is fetched and merged again when another bench put its own in the
+/// meantime, before it is left for the next run.
+const MANIFEST_MERGES: u32 = 5;
+
+/// How `s3://` targets are reached.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct S3Settings {
+    pub region: String,
+    /// Another S3 compatible service, e.g. `http://localhost:9000` for MinIO, instead of AWS
+    pub endpoint: Option<String>,
+    /// Defaults to the `AWS_ACCESS_KEY_ID` environment variable
+    pub access_key: Option<String>,
+    /// Defaults to the `AWS_SECRET_ACCESS_KEY` environment variable
+    pub secret_key: Option<String>,
+}
+
+impl Default for S3Settings {
+    fn default() -> Self {
+        Self {
+            region: "us-east-1".to_string(),
+            endpoint: None,
+            access_key: None,
+            secret_key: None,
+        }
+    }
+}
+
+/// How `sftp://` targets are logged in to. Without a key or password the ssh agent and the keys
+/// in `~/.ssh` are tried.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct SftpSettings {
+    /// The private key file
+    pub key: Option<PathBuf>,
+    /// The passphrase of the key
+    pub passphrase: Option<String>,
+    /// The password of the user in the url, instead of a key
+    pub password: Option<String>,
+}
+
+/// How `dav://` and `davs://` targets are logged in to, e.g. a Nextcloud app password.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct WebDavSettings {
+    pub user: Option<String>,
+    pub password: Option<String>,
+}
+
+/// What a put needs of the file stored under its key. A sink that can put conditionally fails
+/// with [`AppError::AlreadyStored`] instead of replacing a file it does not need.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PutIf<'a> {
+    /// Any file stored under the key is replaced
+    Always,
+    /// Nothing is stored under the key
+    Absent,
+    /// The file stored under the key is still the version [`OutputSink::get`] fetched, the
+    /// ETag on S3 and WebDAV and the modification time and size on SFTP.
+    Unchanged(&'a str),
+}
+
+/// A remote place the outputs are stored in, e.g. a bucket of object storage.
+pub trait OutputSink: fmt::Display + Send + Sync {
+    /// Stores the local file `file` under `key`, its path relative to the target with `/`.
+    /// `resume` is set when trying again after a failed put, a sink that can continue a
+    /// partial upload does so.
+    fn put(&self, file: &Path, key: &str, resume: bool, condition: PutIf) -> Result<(), AppError>;
+
+    /// Fetches the file stored under `key` into the local file `to`. Returns its version, or
+    /// `None` when nothing is stored under the key.
+    fn get(&self, key: &str, to: &Path) -> Result<Option<Version>, AppError>;
+}
+
+/// The version of a file fetched from a sink, the condition of a put replacing it.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Version {
+    /// The ETag of the file, or its modification time and size on SFTP. `None` when the
+    /// server sent no ETag, a put replacing the file then replaces any version.
+    pub etag: Option<String>,
+}
+
+/// A target given as a url, e.g. `s3://bucket/prefix`. The run writes into a local staging
+/// folder for it as into any target, and the files the run wrote are stored in the sink
+/// afterwards. The numbering continues after the photos in the manifest of the sink, see
+/// [`RemoteTarget::fetch_manifest`].
+pub struct RemoteTarget {
+    pub sink: Box<dyn OutputSink>,
+    /// The local folder the run writes into
+    pub staging: PathBuf,
+    /// How often a failed put is tried again before the file is left for the next run
+    pub retries: u32,
+    /// Tells the numbered photos, which never replace one stored by another bench
+    template: NameTemplate,
+}
+
+impl RemoteTarget {
+    /// The remote target of `target`, or `None` for a local folder.
+    pub fn parse(target: &Path, config: &Config) -> Result<Option<Self>, AppError> {
+        let Some((scheme, location)) = target.to_str().and_then(|t| t.split_once("://")) else {
+            return Ok(None);
+        };
+        let location = location.trim_matches('/');
+        let sink = match scheme {
+            "s3" => s3_sink(location, config)?,
+            "sftp" => Box::new(crate::sftp::SftpSink::new(location, &config.sftp)?),
+            "dav" | "davs" => Box::new(crate::webdav::WebDavSink::new(
+                scheme,
+                location,
+                &config.webdav,
+            )),
+            _ => return Err(AppError::UnknownTarget(target.display().to_string())),
+        };
+        let root = match &config.remote_staging_dir {
+            Some(dir) => dir.clone(),
+            None => directories::ProjectDirs::from("", "", "photo-bench-ui")
+                .ok_or(AppError::NoDataDir)?
+                .data_dir()
+                .join(STAGING_DIR),
+        };
+        // The user and port of a url are no valid folder names on Windows
+        let staging = root.join(scheme).join(location.replace([':', '@'], "_"));
+        Ok(Some(Self {
+            sink,
+            staging,
+            retries: config.upload_retries,
+            template: config.name_template()?,
+        }))
+    }
+
+    /// Adds the photos in the manifest of the sink that the manifest of the staging folder
+    /// does not have, e.g. from another bench storing into the same target, so the numbering
+    /// continues after them. When the sink can not be reached the run goes on with the staging
+    /// folder alone, and a put that would replace a photo fails.
+    pub fn fetch_manifest(&self) -> Result<(), AppError> {
+        fs::create_dir_all(&self.staging)?;
+        let fetched = self.staging.join(FETCHED_MANIFEST_FILE_NAME);
+        match self.sink.get(MANIFEST_FILE_NAME, &fetched) {
+            Ok(Some(_)) => self.merge_manifest(&fetched),
+            Ok(None) => Ok(()),
+            Err(e) => {
+                warn!("{e}. Could not fetch the manifest of {}", self.sink);
+                Ok(())
+            }
+        }
+    }
+
+    /// Adds the photos in `fetched`, the manifest of the sink, that the manifest of the
+    /// staging folder does not have.
+    fn merge_manifest(&self, fetched: &Path) -> Result<(), AppError> {
+        let known: HashSet<PathBuf> = read_manifest(&self.staging)?
+            .into_iter()
+            .map(|entry| entry.output)
+            .collect();
+        let manifest = Manifest::open(&self.staging)?;
+        let mut added = 0;
+        for entry in read_manifest_file(fetched)? {
+            if !known.contains(&entry.output) {
+                manifest.record(&entry)?;
+                added += 1;
+            }
+        }
+        fs::remove_file(fetched)?;
+        info!("{added} photo(s) stored in {} by other runs", self.sink);
+        Ok(())
+    }
+
+    /// Stores the files in the staging folder that changed since `since` in the sink, and the
+    /// ones an earlier run could not store. Returns how many were stored. When a file still
+    /// fails after the retries the sink is taken to be down, and it and the rest are left for
+    /// the next run. The counter of the staging folder only numbers the runs of this bench and
+    /// is not stored, the manifest is stored last, see [`RemoteTarget::put_manifest`].
+    pub fn upload_since(&self, since: SystemTime) -> usize {
+        let pending_file = self.staging.join(PENDING_FILE_NAME);
+        let mut keys: BTreeSet<String> = fs::read_to_string(&pending_file)
+            .unwrap_or_default()
+            .lines()
+            .filter(|key| !key.is_empty() && *key != COUNTER_FILE_NAME)
+            .map(ToString::to_string)
+            .collect();
+        for entry in WalkDir::new(&self.staging)
+            .into_iter()
+            .filter_map(|e| e.ok())
+        {
+            let changed = entry
+                .metadata()
+                .ok()
+                .and_then(|m| m.modified().ok())
+                .is_some_and(|modified| modified >= since);
+            if !entry.file_type().is_file()
+                || !changed
+                || entry.path() == pending_file
+                || entry.file_name() == FETCHED_MANIFEST_FILE_NAME
+                || entry.path() == self.staging.join(COUNTER_FILE_NAME)
+            {
+                continue;
+            }
+            if let Ok(relative) = entry.path().strip_prefix(&self.staging) {
+                keys.insert(relative.to_string_lossy().replace('\\', "/"));
+            }
+        }
+
+        // The manifest only lists photos the sink has when it is stored after them
+        let manifest = keys.take(MANIFEST_FILE_NAME);
+        let mut stored = 0;
+        let mut failed = vec![];
+        for key in keys.into_iter().chain(manifest) {
+            let path = self.staging.join(&key);
+            if !path.is_file() {
+                continue;
+            }
+            if !failed.is_empty() {
+                failed.push(key);
+                continue;
+            }
+            let result = match key == MANIFEST_FILE_NAME {
+                true => self.put_manifest(&path),
+                false => {
+                    let condition = match self.template.matches(&path) {
+                        true => PutIf::Absent,
+                        false => PutIf::Always,
+                    };
+                    self.put(&path, &key, condition)
+                }
+            };
+            match result {
+                Ok(()) => stored += 1,
+                Err(e @ AppError::AlreadyStored(_)) => {
+                    warn!("{e}. It is left in {:?}", self.staging);
+                }
+                Err(e) => {
+                    warn!("{e}. Could not store {key} in {}", self.sink);
+                    failed.push(key);
+                }
+            }
+        }
+        info!("Stored {stored} file(s) in {}", self.sink);
+        let written = match failed.is_empty() {
+            true => fs::remove_file(&pending_file).or_else(|e| match e.kind() {
+                std::io::ErrorKind::NotFound => Ok(()),
+                _ => Err(e),
+            }),
+            false => {
+                warn!(
+                    "{} file(s) are left to store in {} by the next run",
+                    failed.len(),
+                    self.sink
+                );
+                fs::write(&pending_file, failed.join("\n") + "\n")
+            }
+        };
+        if let Err(e) = written {
+            warn!("{e}. Could not update {pending_file:?}");
+        }
+        stored
+    }
+
+    /// Puts the manifest of the staging folder, `path`, with the photos other benches stored
+    /// since the run fetched the manifest of the sink. The manifest of the sink is fetched and
+    /// merged again first, and only replaced while it is still the version merged, so the
+    /// photos another bench put in the meantime stay listed.
+    fn put_manifest(&self, path: &Path) -> Result<(), AppError> {
+        let fetched = self.staging.join(FETCHED_MANIFEST_FILE_NAME);
+        for _ in 0..MANIFEST_MERGES {
+            let version = self.sink.get(MANIFEST_FILE_NAME, &fetched)?;
+            if version.is_some() {
+                self.merge_manifest(&fetched)?;
+            }
+            let condition = match &version {
+                None => PutIf::Absent,
+                Some(Version { etag: Some(etag) }) => PutIf::Unchanged(etag),
+                Some(Version { etag: None }) => PutIf::Always,
+            };
+            match self.put(path, MANIFEST_FILE_NAME, condition) {
+                Err(AppError::AlreadyStored(_)) => {
+                    info!("Another bench stored its manifest in {}", self.sink);
+                }
+                result => return result,
+            }
+        }
+        Err(AppError::Upload(format!(
+            "The manifest in {} kept changing while it was merged",
+            self.sink
+        )))
+    }
+
+    /// Puts one file, trying again with growing pauses when the link drops.
+    fn put(&self, path: &Path, key: &str, condition: PutIf) -> Result<(), AppError> {
+        let mut attempt = 0;
+        loop {
+            match self.sink.put(path, key, attempt > 0, condition) {
+                Err(e @ AppError::AlreadyStored(_)) => return Err(e),
+                Err(e) if attempt < self.retries => {
+                    attempt += 1;
+                    let pause = Duration::from_secs(2u64.pow(attempt.min(6)));
+                    warn!("{e}. Trying {key} again in {}s", pause.as_secs());
+                    thread::sleep(pause);
+                }
+                result => return result,
+            }
+        }
+    }
+}
+
+/// Percent-encodes a key for a url, keeping the `/` between its parts.
+pub(crate) fn encode_key(key: &str) -> String {
+    let mut encoded = String::with_capacity(key.len());
+    for byte in key.bytes() {
+        match byte {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'.' | b'_' | b'~' | b'/' => {
+                encoded.push(byte as char)
+            }
+            _ => encoded.push_str(&format!("%{byte:02X}")),
+        }
+    }
+    encoded
+}
+
+#[cfg(feature = "s3")]
+fn s3_sink(location: &str, config: &Config) -> Result<Box<dyn OutputSink>, AppError> {
+    Ok(Box::new(crate::s3::S3Sink::new(location, &config.s3)?))
+}
+
+#[cfg(not(feature = "s3"))]
+fn s3_sink(_: &str, _: &Config) -> Result<Box<dyn OutputSink>, AppError> {
+    Err(AppError::TargetNotBuilt("s3"))
+}
+
+/// The media type uploads of `file` are sent with.
+pub(crate) fn content_type(file: &Path) -> &'static str {
+    match file
+        .extension()
+        .and_then(|e| e.to_str())
+        .map(str::to_lowercase)
+        .as_deref()
+    {
+        Some("jpg" | "jpeg") => "image/jpeg",
+        Some("png") => "image/png",
+        Some("json") => "application/json",
+        Some("jsonl") => "application/x-ndjson",
+        Some("pdf") => "application/pdf",
+        _ => "application/octet-stream",
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::manifest::ManifestEntry;
+    use crate::test_util::temp_dir;
+    use std::collections::HashMap;
+    use std::sync::{Arc, Mutex};
+
+    /// A sink in memory with a version per file, shared by the benches of a test.
+    #[derive(Default)]
+    struct MemorySink {
+        files: Mutex<HashMap<String, (Vec<u8>, u32)>>,
+        /// A manifest another bench puts right after the next get
+        put_after_get: Mutex<Option<Vec<u8>>>,
+    }
+
+    impl OutputSink for Arc<MemorySink> {
+        fn put(&self, file: &Path, key: &str, _: bool, condition: PutIf) -> Result<(), AppError> {
+            let mut files = self.files.lock().unwrap();
+            let version = files.get(key).map(|(_, version)| version.to_string());
+            let stored = match condition {
+                PutIf::Always => true,
+                PutIf::Absent => version.is_none(),
+                PutIf::Unchanged(etag) => version.as_deref() == Some(etag),
+            };
+            if !stored {
+                return Err(AppError::AlreadyStored(key.to_string()));
+            }
+            let next = files.get(key).map_or(0, |(_, version)| version + 1);
+            files.insert(key.to_string(), (fs::read(file)?, next));
+            Ok(())
+        }
+
+        fn get(&self, key: &str, to: &Path) -> Result<Option<Version>, AppError> {
+            let mut files = self.files.lock().unwrap();
+            let Some((content, version)) = files.get(key).cloned() else {
+                return Ok(None);
+            };
+            fs::write(to, content)?;
+            if let Some(other) = self.put_after_get.lock().unwrap().take() {
+                files.insert(key.to_string(), (other, version + 1));
+            }
+            Ok(Some(Version {
+                etag: Some(version.to_string()),
+            }))
+        }
+    }
+
+    impl fmt::Display for MemorySink {
+        fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+            f.write_str("memory://")
+        }
+    }
+
+    fn bench(sink: &Arc<MemorySink>, staging: &Path) -> RemoteTarget {
+        RemoteTarget {
+            sink: Box::new(sink.clone()),
+            staging: staging.to_path_buf(),
+            retries: 0,
+            template: Config::default().name_template().unwrap(),
+        }
+    }
+
+    fn entry(output: &str) -> ManifestEntry {
+        ManifestEntry {
+            number: 1,
+            source: PathBuf::from("IMG_0001.jpg"),
+            output: PathBuf::from(output),
+            date: jiff::civil::date(2025, 12, 24).at(10, 15, 0, 0),
+            sha256: String::new(),
+            original_sha256: None,
+            operator: None,
+            number_range: None,
+            quality_warnings: vec![],
+            run_id: None,
+        }
+    }
+
+    /// Records `output` in the staging folder of `bench` as a run would.
+    fn process(bench: &RemoteTarget, output: &str) {
+        fs::write(bench.staging.join(output), b"photo").unwrap();
+        Manifest::open(&bench.staging)
+            .unwrap()
+            .record(&entry(output))
+            .unwrap();
+        fs::write(bench.staging.join(COUNTER_FILE_NAME), b"{}").unwrap();
+    }
+
+    fn stored_outputs(sink: &MemorySink) -> BTreeSet<PathBuf> {
+        let dir = temp_dir("stored-manifest");
+        let manifest = dir.path().join(MANIFEST_FILE_NAME);
+        fs::write(&manifest, &sink.files.lock().unwrap()[MANIFEST_FILE_NAME].0).unwrap();
+        read_manifest_file(&manifest)
+            .unwrap()
+            .into_iter()
+            .map(|entry| entry.output)
+            .collect()
+    }
+
+    #[test]
+    fn benches_storing_into_one_target_keep_each_others_photos_in_the_manifest() {
+        let sink = Arc::new(MemorySink::default());
+        let (one, two) = (temp_dir("bench-one"), temp_dir("bench-two"));
+        let (one, two) = (bench(&sink, one.path()), bench(&sink, two.path()));
+        one.fetch_manifest().unwrap();
+        two.fetch_manifest().unwrap();
+        process(&one, "one.jpg");
+        process(&two, "two.jpg");
+
+        assert_eq!(one.upload_since(SystemTime::UNIX_EPOCH), 2);
+        assert_eq!(two.upload_since(SystemTime::UNIX_EPOCH), 2);
+        let expected = BTreeSet::from(["one.jpg".into(), "two.jpg".into()]);
+        assert_eq!(stored_outputs(&sink), expected);
+        assert!(!sink.files.lock().unwrap().contains_key(COUNTER_FILE_NAME));
+    }
+
+    #[test]
+    fn a_manifest_put_in_the_meantime_is_merged_again() {
+        let sink = Arc::new(MemorySink::default());
+        let (one, two) = (temp_dir("bench-one"), temp_dir("bench-two"));
+        let (one, two) = (bench(&sink, one.path()), bench(&sink, two.path()));
+        process(&one, "one.jpg");
+        assert_eq!(one.upload_since(SystemTime::UNIX_EPOCH), 2);
+        process(&two, "two.jpg");
+        let other = serde_json::to_string(&entry("three.jpg")).unwrap() + "\n";
+        *sink.put_after_get.lock().unwrap() = Some(
+            [
+                sink.files.lock().unwrap()[MANIFEST_FILE_NAME].0.clone(),
+                other.into_bytes(),
+            ]
+            .concat(),
+        );
+
+        assert_eq!(two.upload_since(SystemTime::UNIX_EPOCH), 2);
+        let expected = BTreeSet::from(["one.jpg".into(), "three.jpg".into(), "two.jpg".into()]);
+        assert_eq!(stored_outputs(&sink), expected);
+    }
+}
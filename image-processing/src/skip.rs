@@ -0,0 +1,260 @@
+use std::fmt;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use tracing::debug;
+
+use crate::metadata::{self, ExifSummary};
+use crate::raw;
+
+/// Why a file in the source was not processed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum SkipReason {
+    /// Not a jpeg or png
+    NotAnImage,
+    /// The file name matches the name template, so it is an output of an earlier run
+    AlreadyNumbered,
+    /// The ledger of the target has it from an earlier run
+    ProcessedBefore,
+    /// Given as a source or listed in the file list, but not there
+    Missing,
+    /// Smaller than the minimum resolution, e.g. an icon or a screenshot
+    TooSmall,
+    /// Taken with a camera that does not match the camera filter
+    OtherCamera,
+    /// Matches a pattern in a `.photobenchignore` file
+    Ignored,
+    /// None of the date sources before `ask` had a date for it
+    NoDate,
+}
+
+impl fmt::Display for SkipReason {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let reason = match self {
+            SkipReason::NotAnImage => "not a jpeg or png",
+            SkipReason::AlreadyNumbered => "the name matches the name template",
+            SkipReason::ProcessedBefore => "processed in an earlier run",
+            SkipReason::Missing => "not found",
+            SkipReason::TooSmall => "smaller than the minimum resolution",
+            SkipReason::OtherCamera => "taken with a camera not matching the camera filter",
+            SkipReason::Ignored => "left out by a .photobenchignore file",
+            SkipReason::NoDate => "no date found, give it one in the file name",
+        };
+        f.write_str(reason)
+    }
+}
+
+/// A file in the source that was not processed.
+#[derive(Debug, Clone, Serialize)]
+pub struct Skipped {
+    pub path: PathBuf,
+    pub reason: SkipReason,
+}
+
+/// The smallest photo worth printing, e.g. `800x600`. Portrait photos are compared
+/// the other way around, so `800x600` also lets through a photo of 600 by 800.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(try_from = "String", into = "String")]
+pub struct Resolution {
+    pub width: u32,
+    pub height: u32,
+}
+
+impl Resolution {
+    /// Whether the image at `path` is at least this big. Only reads the header of the image,
+    /// images that cannot be read are left for the decode to report.
+    pub fn is_above(&self, path: &Path) -> bool {
+        let Ok((w, h)) = raw::dimensions(path) else {
+            return true;
+        };
+        let (long, short) = (self.width.max(self.height), self.width.min(self.height));
+        w.max(h) >= long && w.min(h) >= short
+    }
+}
+
+impl FromStr for Resolution {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let invalid = || format!("{s} is not a resolution like 800x600");
+        let (width, height) = s.split_once(['x', 'X']).ok_or_else(invalid)?;
+        let width = width.trim().parse().map_err(|_| invalid())?;
+        let height = height.trim().parse().map_err(|_| invalid())?;
+        Ok(Self { width, height })
+    }
+}
+
+impl TryFrom<String> for Resolution {
+    type Error = String;
+
+    fn try_from(value: String) -> Result<Self, Self::Error> {
+        value.parse()
+    }
+}
+
+impl From<Resolution> for String {
+    fn from(value: Resolution) -> Self {
+        value.to_string()
+    }
+}
+
+impl fmt::Display for Resolution {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}x{}", self.width, self.height)
+    }
+}
+
+/// Only the images taken with matching cameras, e.g. `Canon*` or `*iPhone 15*`. `*` matches
+/// anything and `?` one character, ignoring case. The pattern is matched against the make and
+/// model from the EXIF data, and against the model alone.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(try_from = "String", into = "String")]
+pub struct CameraFilter {
+    pattern: String,
+    regex: Regex,
+}
+
+impl CameraFilter {
+    /// Whether the image at `path` was taken with a matching camera. Images without a camera
+    /// in their EXIF data never match.
+    pub fn matches(&self, path: &Path) -> bool {
+        match metadata::get_exif_summary(path) {
+            Ok(Some(camera)) => self.matches_camera(&camera),
+            Ok(None) => false,
+            Err(e) => {
+                debug!("{e}. Could not read the camera of {path:?}");
+                false
+            }
+        }
+    }
+
+    /// Whether the make and model in the EXIF data match.
+    pub(crate) fn matches_camera(&self, camera: &ExifSummary) -> bool {
+        let name = [camera.make.as_deref(), camera.model.as_deref()]
+            .into_iter()
+            .flatten()
+            .collect::<Vec<_>>()
+            .join(" ");
+        self.regex.is_match(&name)
+            || camera
+                .model
+                .as_ref()
+                .is_some_and(|model| self.regex.is_match(model))
+    }
+}
+
+impl PartialEq for CameraFilter {
+    fn eq(&self, other: &Self) -> bool {
+        self.pattern == other.pattern
+    }
+}
+
+impl FromStr for CameraFilter {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let pattern = s.trim();
+        if pattern.is_empty() {
+            return Err("The camera filter is empty".to_string());
+        }
+        let regex = pattern
+            .split('*')
+            .map(|part| {
+                part.split('?')
+                    .map(regex::escape)
+                    .collect::<Vec<_>>()
+                    .join(".")
+            })
+            .collect::<Vec<_>>()
+            .join(".*");
+        let regex = Regex::new(&format!("(?i)^{regex}$")).map_err(|e| e.to_string())?;
+        Ok(Self {
+            pattern: pattern.to_string(),
+            regex,
+        })
+    }
+}
+
+impl TryFrom<String> for CameraFilter {
+    type Error = String;
+
+    fn try_from(value: String) -> Result<Self, Self::Error> {
+        value.parse()
+    }
+}
+
+impl From<CameraFilter> for String {
+    fn from(value: CameraFilter) -> Self {
+        value.pattern
+    }
+}
+
+impl fmt::Display for CameraFilter {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.pattern)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn resolutions_need_a_width_and_a_height() {
+        let resolution: Resolution = "800x600".parse().unwrap();
+        assert_eq!((resolution.width, resolution.height), (800, 600));
+        assert_eq!(resolution.to_string(), "800x600");
+        assert_eq!(
+            " 600 X 800 ".parse::<Resolution>().unwrap().to_string(),
+            "600x800"
+        );
+
+        for bad in [
+            "",
+            "800",
+            "800x",
+            "x600",
+            "800*600",
+            "800x600x1",
+            "-800x600",
+            "800.5x600",
+        ] {
+            assert!(bad.parse::<Resolution>().is_err(), "{bad}");
+        }
+    }
+
+    fn camera(make: Option<&str>, model: Option<&str>) -> ExifSummary {
+        ExifSummary {
+            make: make.map(str::to_string),
+            model: model.map(str::to_string),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn camera_filters_match_make_and_model_with_wildcards() {
+        let canon: CameraFilter = " Canon* ".parse().unwrap();
+        assert_eq!(canon.to_string(), "Canon*");
+        assert!(canon.matches_camera(&camera(Some("Canon"), Some("EOS 80D"))));
+        assert!(canon.matches_camera(&camera(None, Some("canon eos 80d"))));
+        assert!(!canon.matches_camera(&camera(Some("Nikon"), Some("D750"))));
+        assert!(!canon.matches_camera(&camera(None, None)));
+
+        let iphone: CameraFilter = "*iPhone 1?".parse().unwrap();
+        assert!(iphone.matches_camera(&camera(Some("Apple"), Some("iPhone 15"))));
+        assert!(!iphone.matches_camera(&camera(Some("Apple"), Some("iPhone 15 Pro"))));
+        assert!(!iphone.matches_camera(&camera(Some("Apple"), Some("iPhone 9"))));
+
+        // Regex characters in the pattern are matched as they are
+        let dotted: CameraFilter = "E.M1 (II)".parse().unwrap();
+        assert!(dotted.matches_camera(&camera(None, Some("E.M1 (II)"))));
+        assert!(!dotted.matches_camera(&camera(None, Some("E-M1 (II)"))));
+
+        for bad in ["", "   "] {
+            assert!(bad.parse::<CameraFilter>().is_err(), "{bad:?}");
+        }
+    }
+}
@@ -0,0 +1,310 @@
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+use std::fmt;
+use std::path::{Path, PathBuf};
+
+use jiff::civil::Date;
+use serde::Serialize;
+use tracing::debug;
+use walkdir::WalkDir;
+
+use crate::config::Config;
+use crate::error::AppError;
+use crate::manifest::{read_manifest, KnownOutputs};
+use crate::numbering::Numbering;
+use crate::{image_ops, metadata};
+
+/// Summary of the numbered photos found in a target folder.
+#[derive(Debug, Default)]
+pub struct ArchiveStats {
+    pub total_photos: usize,
+    pub total_bytes: u64,
+    /// Photo count per day the photos were taken, from the manifest or else the name of their
+    /// date folder, so folders per camera or month are counted by day too
+    pub per_date: BTreeMap<Date, usize>,
+    /// Photo count per month, e.g. `2025-12`
+    pub per_month: BTreeMap<String, usize>,
+    /// The photos neither the manifest nor their folder has the date of
+    pub undated: usize,
+    pub cameras: BTreeMap<String, usize>,
+    /// The numbers in use, per folder relative to the target when every folder has its own
+    /// numbers
+    pub numbers: BTreeMap<Option<PathBuf>, BTreeSet<usize>>,
+}
+
+/// Inclusive ranges of numbers missing between the first and last number.
+pub fn gaps(numbers: &BTreeSet<usize>) -> Vec<(usize, usize)> {
+    let mut gaps = vec![];
+    let mut prev: Option<usize> = None;
+    for &n in numbers {
+        if let Some(p) = prev
+            && n > p + 1
+        {
+            gaps.push((p + 1, n - 1));
+        }
+        prev = Some(n);
+    }
+    gaps
+}
+
+/// The gaps as `2, 5 - 7`.
+fn format_gaps(gaps: &[(usize, usize)]) -> String {
+    let gaps: Vec<String> = gaps
+        .iter()
+        .map(|&(from, to)| match from == to {
+            true => from.to_string(),
+            false => format!("{from} - {to}"),
+        })
+        .collect();
+    gaps.join(", ")
+}
+
+/// A folder of the target with numbered photos in it, e.g. to show what the archive holds
+/// before a run adds to it.
+#[derive(Debug, Clone, Serialize)]
+pub struct TargetFolder {
+    /// Relative to the target, e.g. `20251224`
+    pub folder: PathBuf,
+    /// The date of a date folder, e.g. `2025-12-24`
+    pub date: Option<Date>,
+    pub photos: usize,
+    pub highest_number: usize,
+}
+
+/// The folders of the target with numbered photos, sorted by their path.
+pub fn target_folders(target: &Path, config: &Config) -> Result<Vec<TargetFolder>, AppError> {
+    let template = config.name_template()?;
+    let mut outputs = KnownOutputs::default();
+    let mut folders: BTreeMap<PathBuf, TargetFolder> = BTreeMap::new();
+
+    for entry in WalkDir::new(target).into_iter().filter_map(|e| e.ok()) {
+        let path = entry.path();
+        if !entry.file_type().is_file()
+            || !image_ops::is_image_file(path)
+            || !outputs.is_output(&template, path)
+        {
+            continue;
+        }
+        let Some(number) = template.number_of(path) else {
+            continue;
+        };
+        let Some(parent) = path.parent() else {
+            continue;
+        };
+        let folder = parent.strip_prefix(target).unwrap_or(parent).to_path_buf();
+        let entry = folders
+            .entry(folder.clone())
+            .or_insert_with(|| TargetFolder {
+                date: folder
+                    .file_name()
+                    .and_then(|x| x.to_str())
+                    .and_then(|name| Date::strptime("%Y%m%d", name).ok()),
+                folder,
+                photos: 0,
+                highest_number: 0,
+            });
+        entry.photos += 1;
+        entry.highest_number = entry.highest_number.max(number);
+    }
+
+    Ok(folders.into_values().collect())
+}
+
+/// Walks the target folder and collects statistics over all the numbered photos in it.
+pub fn collect_stats(target: &Path, config: &Config) -> Result<ArchiveStats, AppError> {
+    let template = config.name_template()?;
+    let mut outputs = KnownOutputs::default();
+    let mut stats = ArchiveStats::default();
+    let dates: HashMap<PathBuf, Date> = read_manifest(target)?
+        .into_iter()
+        .map(|entry| (target.join(entry.output), entry.date.date()))
+        .collect();
+
+    for entry in WalkDir::new(target).into_iter().filter_map(|e| e.ok()) {
+        if !entry.file_type().is_file() {
+            continue;
+        }
+        let path = entry.path();
+        if !image_ops::is_image_file(path) || !outputs.is_output(&template, path) {
+            continue;
+        }
+        let Some(number) = template.number_of(path) else {
+            continue;
+        };
+
+        stats.total_photos += 1;
+        stats.total_bytes += entry.metadata().map(|m| m.len()).unwrap_or_default();
+        let scope = match config.numbering {
+            Numbering::Global => None,
+            Numbering::PerFolder => path
+                .parent()
+                .map(|parent| parent.strip_prefix(target).unwrap_or(parent).to_path_buf()),
+        };
+        stats.numbers.entry(scope).or_default().insert(number);
+
+        let date = dates.get(path).copied().or_else(|| {
+            let folder = path.parent()?.file_name()?.to_str()?;
+            Date::strptime("%Y%m%d", folder).ok()
+        });
+        match date {
+            Some(date) => {
+                *stats
+                    .per_month
+                    .entry(date.strftime("%Y-%m").to_string())
+                    .or_default() += 1;
+                *stats.per_date.entry(date).or_default() += 1;
+            }
+            None => stats.undated += 1,
+        }
+
+        match metadata::get_camera_model(path) {
+            Ok(Some(model)) => *stats.cameras.entry(model).or_default() += 1,
+            Ok(None) => {}
+            Err(e) => debug!("{e}. Could not read the camera model of {path:?}"),
+        }
+    }
+
+    Ok(stats)
+}
+
+impl fmt::Display for ArchiveStats {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "Photos: {}", self.total_photos)?;
+        writeln!(
+            f,
+            "Total size: {:.1} MB",
+            self.total_bytes as f64 / (1024. * 1024.)
+        )?;
+        match self.numbers.get(&None) {
+            Some(numbers) => fmt_numbers(f, numbers)?,
+            None if self.numbers.is_empty() => writeln!(f, "Numbers: none")?,
+            // Every folder has its own numbers
+            None => {
+                writeln!(f, "Numbers per folder:")?;
+                for (folder, numbers) in &self.numbers {
+                    let (Some(first), Some(last)) = (numbers.first(), numbers.last()) else {
+                        continue;
+                    };
+                    let folder = folder.as_deref().unwrap_or(Path::new("")).display();
+                    let gaps = gaps(numbers);
+                    match gaps.is_empty() {
+                        true => writeln!(f, "  {folder}: {first} - {last}")?,
+                        false => writeln!(
+                            f,
+                            "  {folder}: {first} - {last}, gaps: {}",
+                            format_gaps(&gaps)
+                        )?,
+                    }
+                }
+            }
+        }
+
+        writeln!(f, "Per month:")?;
+        for (month, count) in &self.per_month {
+            writeln!(f, "  {month}: {count}")?;
+        }
+        writeln!(f, "Per date:")?;
+        for (date, count) in &self.per_date {
+            writeln!(f, "  {date}: {count}")?;
+        }
+        if self.undated > 0 {
+            writeln!(f, "Without a date: {}", self.undated)?;
+        }
+        if self.cameras.is_empty() {
+            writeln!(f, "Cameras: no camera metadata found")?;
+        } else {
+            writeln!(f, "Cameras:")?;
+            for (camera, count) in &self.cameras {
+                writeln!(f, "  {camera}: {count}")?;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// The first and last number and the gaps of one sequence over the whole target.
+fn fmt_numbers(f: &mut fmt::Formatter<'_>, numbers: &BTreeSet<usize>) -> fmt::Result {
+    match (numbers.first(), numbers.last()) {
+        (Some(first), Some(last)) => writeln!(f, "Numbers: {first} - {last}")?,
+        _ => writeln!(f, "Numbers: none")?,
+    }
+
+    let gaps = gaps(numbers);
+    if gaps.is_empty() {
+        writeln!(f, "Gaps in numbering: none")?;
+    } else {
+        writeln!(f, "Gaps in numbering:")?;
+        for (from, to) in gaps {
+            if from == to {
+                writeln!(f, "  {from}")?;
+            } else {
+                writeln!(f, "  {from} - {to}")?;
+            }
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::manifest::{Manifest, ManifestEntry};
+    use crate::test_util::temp_dir;
+    use std::fs;
+
+    #[test]
+    fn gaps_are_the_numbers_missing_between_the_first_and_last() {
+        assert_eq!(gaps(&BTreeSet::from([1, 2, 3])), []);
+        assert_eq!(gaps(&BTreeSet::from([2, 3, 5, 9])), [(4, 4), (6, 8)]);
+        assert_eq!(gaps(&BTreeSet::new()), []);
+        assert_eq!(format_gaps(&[(4, 4), (6, 8)]), "4, 6 - 8");
+    }
+
+    #[test]
+    fn photos_are_counted_by_the_day_they_were_taken_and_their_folder_numbers() {
+        let dir = temp_dir("stats");
+        let target = dir.path();
+        let manifest = Manifest::open(target).unwrap();
+        for (folder, number, day) in [("Canon", 1, 24), ("Canon", 3, 25), ("Nikon", 1, 25)] {
+            let output = Path::new(folder).join(format!("{number:04}.jpg"));
+            fs::create_dir_all(target.join(folder)).unwrap();
+            fs::write(target.join(&output), b"").unwrap();
+            manifest
+                .record(&ManifestEntry {
+                    number,
+                    source: PathBuf::from("IMG_0001.jpg"),
+                    output,
+                    date: jiff::civil::date(2025, 12, day).at(10, 15, 0, 0),
+                    sha256: String::new(),
+                    original_sha256: None,
+                    operator: None,
+                    number_range: None,
+                    quality_warnings: vec![],
+                    run_id: None,
+                })
+                .unwrap();
+        }
+        // A photo of the old tool, only dated by its folder
+        fs::create_dir_all(target.join("20251223")).unwrap();
+        fs::write(target.join("20251223").join("0007.jpg"), b"").unwrap();
+        let config = Config {
+            numbering: Numbering::PerFolder,
+            ..Config::default()
+        };
+
+        let stats = collect_stats(target, &config).unwrap();
+        assert_eq!(stats.total_photos, 4);
+        let day = |day| jiff::civil::date(2025, 12, day);
+        assert_eq!(
+            stats.per_date,
+            BTreeMap::from([(day(23), 1), (day(24), 1), (day(25), 2)])
+        );
+        assert_eq!(stats.per_month, BTreeMap::from([("2025-12".into(), 4)]));
+        let canon = &stats.numbers[&Some(PathBuf::from("Canon"))];
+        assert_eq!(gaps(canon), [(2, 2)]);
+        assert_eq!(
+            stats.numbers[&Some(PathBuf::from("Nikon"))],
+            BTreeSet::from([1])
+        );
+        assert_eq!(stats.numbers.len(), 3);
+    }
+}
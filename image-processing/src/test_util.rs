@@ -0,0 +1,10 @@
+use tempfile::TempDir;
+
+/// A folder for the files of a test, named after `name`. It is removed when it is dropped,
+/// also when the test fails.
+pub fn temp_dir(name: &str) -> TempDir {
+    tempfile::Builder::new()
+        .prefix(&format!("photo-bench-{name}-"))
+        .tempdir()
+        .expect("The temp directory can be written to")
+}
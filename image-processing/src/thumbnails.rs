@@ -0,0 +1,125 @@
+//! The cache of thumbnails, shared by everything that shows small versions of the photos.
+//! A thumbnail is made once per file, size and version of the file. Editing or replacing
+//! a photo changes its size or modification time, so a new thumbnail is made for it.
+
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::UNIX_EPOCH;
+
+use image::{DynamicImage, ImageFormat, RgbImage};
+use sha2::{Digest, Sha256};
+use tracing::debug;
+
+use crate::error::AppError;
+use crate::{image_ops, raw};
+
+/// Folder in the cache directory of the app the thumbnails are kept in.
+const THUMBNAIL_DIR: &str = "thumbnails";
+/// Changed when thumbnails are made differently, so the cached ones are made again.
+const THUMBNAIL_VERSION: u8 = 2;
+
+/// The folder the thumbnails are cached in, in the cache directory of the app.
+fn cache_dir() -> Result<PathBuf, AppError> {
+    let Some(proj_dir) = directories::ProjectDirs::from("", "", "photo-bench-ui") else {
+        return Err(AppError::NoCacheDir);
+    };
+    Ok(proj_dir.cache_dir().join(THUMBNAIL_DIR))
+}
+
+/// Name of the thumbnail of `path` that fits in `max_w` by `max_h` pixels.
+fn key(path: &Path, max_w: u32, max_h: u32) -> Result<String, AppError> {
+    let metadata = fs::metadata(path)?;
+    let modified = metadata
+        .modified()?
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_nanos();
+    let path = path.canonicalize()?;
+    let mut hasher = Sha256::new();
+    hasher.update(path.to_string_lossy().as_bytes());
+    hasher.update(modified.to_le_bytes());
+    hasher.update(metadata.len().to_le_bytes());
+    hasher.update(max_w.to_le_bytes());
+    hasher.update(max_h.to_le_bytes());
+    hasher.update([THUMBNAIL_VERSION]);
+    let hash: String = hasher
+        .finalize()
+        .iter()
+        .map(|b| format!("{b:02x}"))
+        .collect();
+    Ok(format!("{hash}.png"))
+}
+
+/// The thumbnail of the photo at `path`, made smaller to fit in `max_w` by `max_h` pixels
+/// but never larger. Made and cached on first use.
+pub fn thumbnail(path: &Path, max_w: u32, max_h: u32) -> Result<RgbImage, AppError> {
+    if fits(path, max_w, max_h)? {
+        return Ok(open_upright(path)?.to_rgb8());
+    }
+    let cached = cache_dir()?.join(key(path, max_w, max_h)?);
+    if let Ok(thumbnail) = image::open(&cached) {
+        return Ok(thumbnail.to_rgb8());
+    }
+    let photo = open_upright(path)?;
+    let thumbnail = image_ops::resize_to_fit(&photo, max_w, max_h).to_rgb8();
+    if let Err(e) = store(&thumbnail, &cached) {
+        debug!("{e}. Could not cache the thumbnail of {path:?}");
+    }
+    Ok(thumbnail)
+}
+
+/// The file of the thumbnail of the photo at `path` in `dir`, e.g. the
+/// [`PreviewDir`](crate::previews::PreviewDir) of the app. It is made in the cache on first
+/// use and linked into `dir`, or copied when they are on different drives.
+/// The photo itself when it is small enough and not a raw.
+pub fn thumbnail_file(
+    path: &Path,
+    max_w: u32,
+    max_h: u32,
+    dir: &Path,
+) -> Result<PathBuf, AppError> {
+    if !raw::is_raw_file(path) && fits(path, max_w, max_h)? {
+        return Ok(path.to_path_buf());
+    }
+    let name = key(path, max_w, max_h)?;
+    let shown = dir.join(&name);
+    if shown.exists() {
+        return Ok(shown);
+    }
+    let cached = cache_dir()?.join(&name);
+    if !cached.exists() {
+        let photo = open_upright(path)?;
+        store(
+            &image_ops::resize_to_fit(&photo, max_w, max_h).to_rgb8(),
+            &cached,
+        )?;
+    }
+    if let Err(e) = fs::hard_link(&cached, &shown) {
+        debug!("{e}. Could not link the thumbnail of {path:?}, copying it instead");
+        fs::copy(&cached, &shown)?;
+    }
+    Ok(shown)
+}
+
+/// Decodes the photo as it is shown, turned upright by its EXIF Orientation.
+fn open_upright(path: &Path) -> Result<DynamicImage, AppError> {
+    let photo = raw::open(path)?.to_rgb8();
+    Ok(DynamicImage::ImageRgb8(image_ops::orient(photo, path)))
+}
+
+/// Whether the photo is small enough to be its own thumbnail. Only reads the header.
+fn fits(path: &Path, max_w: u32, max_h: u32) -> Result<bool, AppError> {
+    let (w, h) = raw::dimensions(path)?;
+    Ok(w <= max_w && h <= max_h)
+}
+
+/// Writes next to the cached file first, so a half written thumbnail is never read.
+fn store(thumbnail: &RgbImage, cached: &Path) -> Result<(), AppError> {
+    if let Some(dir) = cached.parent() {
+        fs::create_dir_all(dir)?;
+    }
+    let partial = cached.with_extension(format!("{}.part", std::process::id()));
+    thumbnail.save_with_format(&partial, ImageFormat::Png)?;
+    fs::rename(&partial, cached)?;
+    Ok(())
+}
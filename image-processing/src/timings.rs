@@ -0,0 +1,104 @@
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::{Duration, Instant};
+
+use tracing::{debug_span, field};
+
+use crate::metrics;
+
+/// A step of processing one photo, timed for the run summary.
+#[derive(Debug, Clone, Copy, serde::Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum Stage {
+    /// Copying the original to the local cache, slow on network drives
+    Copy,
+    Decode,
+    /// Denoise and the color adjustments
+    Adjust,
+    Resize,
+    Stamp,
+    Encode,
+    /// Moving the output from the cache to the target
+    Transfer,
+    /// The extra outputs from the config
+    Variants,
+}
+
+impl Stage {
+    const ALL: [Stage; 8] = [
+        Stage::Copy,
+        Stage::Decode,
+        Stage::Adjust,
+        Stage::Resize,
+        Stage::Stamp,
+        Stage::Encode,
+        Stage::Transfer,
+        Stage::Variants,
+    ];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Stage::Copy => "copy",
+            Stage::Decode => "decode",
+            Stage::Adjust => "adjust",
+            Stage::Resize => "resize",
+            Stage::Stamp => "stamp",
+            Stage::Encode => "encode",
+            Stage::Transfer => "transfer",
+            Stage::Variants => "variants",
+        }
+    }
+}
+
+/// Time spent in each stage, added up over all the photos and workers of a run.
+#[derive(Debug, Default)]
+pub struct StageTimings {
+    nanos: [AtomicU64; Stage::ALL.len()],
+}
+
+impl StageTimings {
+    /// Runs `f` in a `stage` span and adds how long it took to the stage.
+    pub fn time<T>(&self, stage: Stage, f: impl FnOnce() -> T) -> T {
+        let span = debug_span!("stage", stage = stage.name(), ms = field::Empty).entered();
+        let start = Instant::now();
+        let out = f();
+        let elapsed = start.elapsed();
+        span.record("ms", elapsed.as_millis());
+        metrics::stage_duration(stage.name(), elapsed);
+        self.nanos[stage as usize].fetch_add(elapsed.as_nanos() as u64, Ordering::Relaxed);
+        out
+    }
+
+    /// The time per stage, so it is clear whether e.g. the network copy or the encode is the
+    /// bottleneck. One line per stage, empty when no photo was processed.
+    pub fn summary(&self, photos: usize, wall: Duration) -> Vec<String> {
+        if photos == 0 {
+            return vec![];
+        }
+        let spent: Vec<_> = Stage::ALL
+            .iter()
+            .map(|&stage| {
+                let nanos = self.nanos[stage as usize].load(Ordering::Relaxed);
+                (stage, Duration::from_nanos(nanos))
+            })
+            .collect();
+        let total: Duration = spent.iter().map(|(_, d)| *d).sum();
+        let mut lines = vec![format!(
+            "⏱ {photos} photo(s) in {:.1}s, {:.2}s of work per photo:",
+            wall.as_secs_f32(),
+            total.as_secs_f32() / photos as f32
+        )];
+        for (stage, duration) in spent {
+            if duration.is_zero() {
+                continue;
+            }
+            lines.push(format!(
+                "  {:<9} {:>8.2}s {:>5.1}%  {:>7.0}ms per photo",
+                stage.name(),
+                duration.as_secs_f32(),
+                duration.as_secs_f32() / total.as_secs_f32().max(f32::EPSILON) * 100.0,
+                duration.as_secs_f32() * 1000.0 / photos as f32
+            ));
+        }
+        lines
+    }
+}
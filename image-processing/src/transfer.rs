@@ -0,0 +1,64 @@
+use std::fs::{self, File};
+use std::io::{self, BufReader, BufWriter, Write};
+use std::path::Path;
+
+use serde::{Deserialize, Serialize};
+use tracing::{debug, error};
+
+use crate::error::AppError;
+use crate::image_ops;
+
+/// How the finished photo is moved from the cache directory into the target.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize, clap::ValueEnum)]
+#[serde(rename_all = "kebab-case")]
+pub enum Transfer {
+    /// Copy the bytes, works between any two drives
+    #[default]
+    Copy,
+    /// Rename the file, falls back to a copy between drives
+    Rename,
+    /// Hard link the file, falls back to a copy between drives
+    Hardlink,
+    /// Share the data blocks on filesystems that support it (Btrfs, XFS, APFS, ReFS),
+    /// falls back to a copy
+    Reflink,
+}
+
+impl Transfer {
+    /// Moves the cached file `from` to `to` in the target.
+    pub fn apply(&self, from: &Path, to: &Path) -> Result<(), AppError> {
+        match self {
+            Transfer::Copy => copy(from, to)?,
+            Transfer::Rename => return image_ops::move_file(from, to, false),
+            Transfer::Hardlink => {
+                if let Err(e) = fs::hard_link(from, to) {
+                    debug!("{e}. Could not hard link {from:?}, copying it instead");
+                    copy(from, to)?;
+                }
+            }
+            Transfer::Reflink => {
+                reflink_copy::reflink_or_copy(from, to)?;
+            }
+        }
+        if let Err(e) = fs::remove_file(from) {
+            error!("{e:?}. Could not remove cached ouput file.");
+        }
+        Ok(())
+    }
+}
+
+/// Writes an output kept in memory straight to `to` in the target, without a cache file.
+pub fn write(data: &[u8], to: &Path) -> Result<(), AppError> {
+    let mut target = BufWriter::new(File::create(to)?);
+    target.write_all(data)?;
+    target.flush()?;
+    Ok(())
+}
+
+fn copy(from: &Path, to: &Path) -> Result<(), AppError> {
+    let mut source = BufReader::new(File::open(from)?);
+    let mut target = BufWriter::new(File::create(to)?);
+    io::copy(&mut source, &mut target)?;
+    target.flush()?;
+    Ok(())
+}
@@ -0,0 +1,357 @@
+use std::collections::{BTreeMap, HashMap};
+use std::path::{Path, PathBuf};
+
+use serde::Serialize;
+use tracing::warn;
+use walkdir::WalkDir;
+
+use crate::config::{Config, FitMode, StampLayout};
+use crate::error::AppError;
+use crate::manifest::read_manifest;
+use crate::numbering::Numbering;
+use crate::{hashing, image_ops};
+
+/// Machine readable result of verifying a target folder.
+#[derive(Debug, Default, Serialize)]
+pub struct VerifyReport {
+    /// Amount of numbered photos found in the target
+    pub checked: usize,
+    pub issues: Vec<VerifyIssue>,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(tag = "kind", rename_all = "kebab-case")]
+pub enum VerifyIssue {
+    DecodeFailed {
+        file: PathBuf,
+        error: String,
+    },
+    WrongDimensions {
+        file: PathBuf,
+        width: u32,
+        height: u32,
+        expected_width: u32,
+        expected_height: u32,
+    },
+    WrongDpi {
+        file: PathBuf,
+        dpi: Option<(u16, u16)>,
+        expected: u16,
+    },
+    HashMismatch {
+        file: PathBuf,
+        expected: String,
+        actual: String,
+    },
+    NotInManifest {
+        file: PathBuf,
+    },
+    /// The manifest has an entry for a file that is not in the target anymore
+    MissingFile {
+        file: PathBuf,
+        number: usize,
+    },
+    DuplicateNumber {
+        number: usize,
+        files: Vec<PathBuf>,
+    },
+    /// Inclusive range of numbers that have no file
+    MissingNumbers {
+        /// The date folder when every folder has its own numbers
+        #[serde(skip_serializing_if = "Option::is_none")]
+        folder: Option<PathBuf>,
+        from: usize,
+        to: usize,
+    },
+}
+
+/// The most label lines a caption strip is looked for with. The strip is as high as the label of
+/// the photo, which verify does not know.
+const MAX_LABEL_LINES: usize = 32;
+
+/// The heights the caption strip under a photo can have, for the labels of 1 to
+/// [`MAX_LABEL_LINES`] lines, lowest first. Only 0 with the stamps on the photo.
+fn strip_heights(config: &Config) -> Vec<u32> {
+    let mut heights: Vec<u32> = match config.layout {
+        StampLayout::Overlay => vec![0],
+        StampLayout::CaptionStrip => (1..=MAX_LABEL_LINES)
+            .map(|lines| config.caption_strip_px(lines))
+            .collect(),
+    };
+    heights.dedup();
+    heights
+}
+
+/// The size `process_image` writes every output in, `None` when it follows the size of the photo.
+fn expected_size(config: &Config) -> Option<(u32, u32)> {
+    match (config.resize, config.fit_mode) {
+        (true, FitMode::Letterbox | FitMode::Stretch) => {
+            Some((config.target_width_px(), config.target_height_px()))
+        }
+        // Only scaled down to fit the longer side of the print, or kept at its own size
+        (true, FitMode::None) | (false, _) => None,
+    }
+}
+
+/// The dpi `process_image` writes in an output of `width` x `height`, one for every height the
+/// caption strip can have. Photos kept at their own size get the dpi that prints them as big
+/// as the print.
+fn expected_dpis(config: &Config, strip_heights: &[u32], width: u32, height: u32) -> Vec<u16> {
+    if config.resize {
+        return vec![config.dpi.round() as u16];
+    }
+    let long_side = config.target_width_px().max(config.target_height_px()) as f32;
+    let dpi = |photo_h: u32| (config.dpi * width.max(photo_h) as f32 / long_side).round() as u16;
+    let mut dpis: Vec<u16> = strip_heights
+        .iter()
+        .filter_map(|&strip| photo_height(width, height, strip, long_side))
+        .map(dpi)
+        .collect();
+    dpis.dedup();
+    if dpis.is_empty() {
+        dpis.push(dpi(height));
+    }
+    dpis
+}
+
+/// The height of the photo above a caption strip of `strip` pixels at the print size, in an
+/// output of `width` x `height` kept at the size of the photo. The strip is scaled with the
+/// longer side of the photo, like the stamps.
+fn photo_height(width: u32, height: u32, strip: u32, long_side: f32) -> Option<u32> {
+    let scaled_strip =
+        |photo_h: u32| (strip as f32 * width.max(photo_h) as f32 / long_side).round() as u32;
+    let landscape = height.checked_sub(scaled_strip(0));
+    let portrait = (height as f32 * long_side / (long_side + strip as f32)).round() as u32;
+    landscape
+        .into_iter()
+        .chain([portrait.saturating_sub(1), portrait, portrait + 1])
+        .find(|&photo_h| photo_h + scaled_strip(photo_h) == height)
+}
+
+/// Checks every numbered photo in the target decodes, has the expected size and dpi,
+/// matches the hash in the manifest and that the numbering has no duplicates or gaps.
+pub fn verify_target(target: &Path, config: &Config) -> Result<VerifyReport, AppError> {
+    let mut report = VerifyReport::default();
+    let expected_size = expected_size(config);
+    let strip_heights = strip_heights(config);
+    let template = config.name_template()?;
+    let manifest = read_manifest(target)?;
+    if manifest.is_empty() {
+        warn!("No manifest found in {target:?}. Skipping the hash checks.");
+    }
+    let mut expected_hashes: HashMap<PathBuf, (usize, String)> = manifest
+        .into_iter()
+        .map(|e| (e.output, (e.number, e.sha256)))
+        .collect();
+    let check_hashes = !expected_hashes.is_empty();
+
+    // The files per number, per date folder when every folder has its own numbers
+    let mut numbers: BTreeMap<Option<PathBuf>, BTreeMap<usize, Vec<PathBuf>>> = BTreeMap::new();
+
+    for entry in WalkDir::new(target).into_iter().filter_map(|e| e.ok()) {
+        if !entry.file_type().is_file() {
+            continue;
+        }
+        let path = entry.path();
+        if !image_ops::is_image_file(path) {
+            continue;
+        }
+        let Some(number) = template.number_of(path) else {
+            continue;
+        };
+        let relative = path.strip_prefix(target).unwrap_or(path).to_path_buf();
+        report.checked += 1;
+        let scope = match config.numbering {
+            Numbering::Global => None,
+            Numbering::PerFolder => relative.parent().map(Path::to_path_buf),
+        };
+        numbers
+            .entry(scope)
+            .or_default()
+            .entry(number)
+            .or_default()
+            .push(relative.clone());
+        // Taken before the decode checks, so a file that does not decode is not also missing
+        let expected = expected_hashes.remove(&relative);
+
+        let (width, height) = match image::image_dimensions(path) {
+            Ok(dimensions) => dimensions,
+            Err(e) => {
+                report.issues.push(VerifyIssue::DecodeFailed {
+                    file: relative.clone(),
+                    error: e.to_string(),
+                });
+                continue;
+            }
+        };
+        if let Some(expected) = expected_size
+            && !strip_heights
+                .iter()
+                .any(|strip| (width, height) == (expected.0, expected.1 + strip))
+        {
+            report.issues.push(VerifyIssue::WrongDimensions {
+                file: relative.clone(),
+                width,
+                height,
+                expected_width: expected.0,
+                expected_height: expected.1 + strip_heights[0],
+            });
+        }
+        // Reading the dimensions only parses the header, make sure the whole image decodes
+        if let Err(e) = image::open(path) {
+            report.issues.push(VerifyIssue::DecodeFailed {
+                file: relative.clone(),
+                error: e.to_string(),
+            });
+            continue;
+        }
+
+        let dpi = image_ops::read_jpeg_dpi(path)?;
+        let expected_dpis = expected_dpis(config, &strip_heights, width, height);
+        if !expected_dpis.iter().any(|&d| dpi == Some((d, d))) {
+            report.issues.push(VerifyIssue::WrongDpi {
+                file: relative.clone(),
+                dpi,
+                expected: expected_dpis[0],
+            });
+        }
+
+        if !check_hashes {
+            continue;
+        }
+        match expected {
+            Some((_, expected)) => {
+                let actual = hashing::sha256_file(path)?;
+                if actual != expected {
+                    report.issues.push(VerifyIssue::HashMismatch {
+                        file: relative,
+                        expected,
+                        actual,
+                    });
+                }
+            }
+            None => report
+                .issues
+                .push(VerifyIssue::NotInManifest { file: relative }),
+        }
+    }
+
+    let mut missing_files: Vec<_> = expected_hashes.into_iter().collect();
+    missing_files.sort_by_key(|(_, (number, _))| *number);
+    for (file, (number, _)) in missing_files {
+        report
+            .issues
+            .push(VerifyIssue::MissingFile { file, number });
+    }
+
+    for (folder, numbers) in numbers {
+        let mut prev: Option<usize> = None;
+        for (number, files) in numbers {
+            if files.len() > 1 {
+                report
+                    .issues
+                    .push(VerifyIssue::DuplicateNumber { number, files });
+            }
+            if let Some(p) = prev
+                && number > p + 1
+            {
+                report.issues.push(VerifyIssue::MissingNumbers {
+                    folder: folder.clone(),
+                    from: p + 1,
+                    to: number - 1,
+                });
+            }
+            prev = Some(number);
+        }
+    }
+
+    Ok(report)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs::{self, File};
+
+    use image::codecs::jpeg::{JpegEncoder, PixelDensity};
+    use image::{ExtendedColorType, RgbImage};
+    use tempfile::TempDir;
+
+    use super::*;
+    use crate::test_util::temp_dir;
+
+    /// A target with one output of `width` x `height` at `dpi`.
+    fn temp_target(name: &str, width: u32, height: u32, dpi: u16) -> TempDir {
+        let target = temp_dir(&format!("verify-{name}"));
+        fs::create_dir(target.path().join("20251224")).unwrap();
+        let file = File::create(target.path().join("20251224").join("1.jpg")).unwrap();
+        let mut encoder = JpegEncoder::new(file);
+        encoder.set_pixel_density(PixelDensity::dpi(dpi));
+        let photo = RgbImage::new(width, height);
+        encoder
+            .encode(&photo, width, height, ExtendedColorType::Rgb8)
+            .unwrap();
+        target
+    }
+
+    fn wrong_dimensions(report: &VerifyReport) -> usize {
+        report
+            .issues
+            .iter()
+            .filter(|issue| matches!(issue, VerifyIssue::WrongDimensions { .. }))
+            .count()
+    }
+
+    #[test]
+    fn outputs_without_a_canvas_keep_the_size_of_the_photo() {
+        let config = Config {
+            fit_mode: FitMode::None,
+            ..Config::default()
+        };
+        let target = temp_target("fit-none", 300, 200, 300);
+
+        let report = verify_target(target.path(), &config).unwrap();
+        assert_eq!(report.checked, 1);
+        assert_eq!(wrong_dimensions(&report), 0);
+
+        let config = Config::default();
+        let target = temp_target("letterbox", 300, 200, 300);
+        let report = verify_target(target.path(), &config).unwrap();
+        assert_eq!(wrong_dimensions(&report), 1);
+    }
+
+    #[test]
+    fn outputs_with_a_caption_strip_are_higher_than_the_print() {
+        let config = Config {
+            layout: StampLayout::CaptionStrip,
+            ..Config::default()
+        };
+        let (width, height) = (config.target_width_px(), config.target_height_px());
+        let strip = config.caption_strip_px(2);
+        let target = temp_target("caption-strip", width, height + strip, 300);
+
+        let report = verify_target(target.path(), &config).unwrap();
+        assert_eq!(wrong_dimensions(&report), 0);
+    }
+
+    #[test]
+    fn outputs_kept_at_their_size_print_as_big_as_the_print() {
+        let config = Config {
+            resize: false,
+            ..Config::default()
+        };
+        let long_side = config.target_width_px().max(config.target_height_px());
+        let dpi = (config.dpi * 600.0 / long_side as f32).round() as u16;
+        let target = temp_target("no-resize", 600, 400, dpi);
+
+        let report = verify_target(target.path(), &config).unwrap();
+        assert_eq!(report.checked, 1);
+        assert!(
+            report.issues.iter().all(|issue| !matches!(
+                issue,
+                VerifyIssue::WrongDimensions { .. } | VerifyIssue::WrongDpi { .. }
+            )),
+            "{:?}",
+            report.issues
+        );
+    }
+}
@@ -0,0 +1,118 @@
+use std::collections::HashMap;
+use std::fs;
+use std::path::PathBuf;
+use std::sync::mpsc::{self, Receiver, RecvTimeoutError};
+use std::time::{Duration, Instant, SystemTime};
+
+use notify::event::EventKind;
+use notify::{Event, RecommendedWatcher, RecursiveMode, Watcher};
+use tracing::{debug, warn};
+use walkdir::WalkDir;
+
+use crate::error::AppError;
+
+/// How often the files that are still changing are looked at again.
+const POLL: Duration = Duration::from_millis(500);
+
+/// The size and modification time of a file, which change while it is copied.
+type FileState = Option<(u64, SystemTime)>;
+
+/// Waits for new and changed files in the sources of a `--watch` run.
+pub struct SourceWatcher {
+    _watcher: RecommendedWatcher,
+    events: Receiver<notify::Result<Event>>,
+    settle: Duration,
+    /// The changed files with their state and when it last changed
+    pending: HashMap<PathBuf, (FileState, Instant)>,
+}
+
+impl SourceWatcher {
+    /// Starts watching the sources and all the folders in them.
+    pub fn start(sources: &[PathBuf], settle: Duration) -> Result<Self, AppError> {
+        let (sender, events) = mpsc::channel();
+        let mut watcher = notify::recommended_watcher(sender)?;
+        for source in sources {
+            watcher.watch(source, RecursiveMode::Recursive)?;
+        }
+        Ok(Self {
+            _watcher: watcher,
+            events,
+            settle,
+            pending: HashMap::new(),
+        })
+    }
+
+    /// Blocks until files in the sources were added or changed and then stayed the same for the
+    /// settle time, and returns them. Files removed in the meantime are left out.
+    pub fn next_settled(&mut self) -> Result<Vec<PathBuf>, AppError> {
+        loop {
+            let event = match self.pending.is_empty() {
+                true => self
+                    .events
+                    .recv()
+                    .map_err(|_| RecvTimeoutError::Disconnected),
+                false => self.events.recv_timeout(POLL),
+            };
+            match event {
+                Ok(Ok(event)) => self.changed(event),
+                Ok(Err(e)) => warn!("{e}. Could not watch the sources for a moment"),
+                Err(RecvTimeoutError::Timeout) => {}
+                Err(RecvTimeoutError::Disconnected) => {
+                    return Err(notify::Error::generic("The watcher stopped").into());
+                }
+            }
+            let settled = self.settled();
+            if !settled.is_empty() {
+                return Ok(settled);
+            }
+        }
+    }
+
+    fn changed(&mut self, event: Event) {
+        if !matches!(event.kind, EventKind::Create(_) | EventKind::Modify(_)) {
+            return;
+        }
+        for path in event.paths {
+            // A folder moved into the source only has an event of its own, not of its files
+            let files: Vec<PathBuf> = match path.is_dir() {
+                true => WalkDir::new(&path)
+                    .into_iter()
+                    .filter_map(Result::ok)
+                    .filter(|entry| entry.file_type().is_file())
+                    .map(|entry| entry.into_path())
+                    .collect(),
+                false => vec![path],
+            };
+            for file in files {
+                debug!("{file:?} changed");
+                self.pending.insert(file, (None, Instant::now()));
+            }
+        }
+    }
+
+    /// Takes the pending files that did not change for the settle time.
+    fn settled(&mut self) -> Vec<PathBuf> {
+        let mut settled = vec![];
+        self.pending.retain(|path, (state, since)| {
+            let Ok(meta) = fs::metadata(path) else {
+                return false;
+            };
+            let now = Some((
+                meta.len(),
+                meta.modified().unwrap_or(SystemTime::UNIX_EPOCH),
+            ));
+            if now != *state {
+                *state = now;
+                *since = Instant::now();
+                return true;
+            }
+            if since.elapsed() < self.settle {
+                return true;
+            }
+            settled.push(path.clone());
+            false
+        });
+        settled.sort();
+        settled
+    }
+}
@@ -0,0 +1,187 @@
+use std::collections::HashSet;
+use std::fmt;
+use std::fs::File;
+use std::io;
+use std::path::Path;
+use std::sync::Mutex;
+use std::time::Duration;
+
+use base64::Engine;
+use ureq::http::{Method, Request, Response};
+use ureq::Agent;
+
+use crate::error::AppError;
+use crate::sink::{content_type, encode_key, OutputSink, PutIf, Version, WebDavSettings};
+
+/// How long the server may take to answer a request.
+const TIMEOUT: Duration = Duration::from_secs(60);
+
+/// A folder on a WebDAV server like a Nextcloud share. `dav://` is reached over http and
+/// `davs://` over https.
+pub struct WebDavSink {
+    /// The url of the folder, over http or https
+    base: String,
+    /// The target as it was given
+    target: String,
+    /// The `Authorization` header, when there is a user
+    authorization: Option<String>,
+    agent: Agent,
+    /// The folders made on the server so far
+    made: Mutex<HashSet<String>>,
+}
+
+impl WebDavSink {
+    /// `location` is the url without the scheme, e.g.
+    /// `cloud.example.com/remote.php/dav/files/bench/Photos`.
+    pub fn new(scheme: &str, location: &str, settings: &WebDavSettings) -> Self {
+        let http = match scheme {
+            "davs" => "https",
+            _ => "http",
+        };
+        let authorization = settings.user.as_ref().map(|user| {
+            let login = format!(
+                "{user}:{}",
+                settings.password.as_deref().unwrap_or_default()
+            );
+            format!(
+                "Basic {}",
+                base64::engine::general_purpose::STANDARD.encode(login)
+            )
+        });
+        // The statuses are told apart here, e.g. a 412 of a put that would replace a file
+        let agent = Agent::config_builder()
+            .http_status_as_error(false)
+            .allow_non_standard_methods(true)
+            .timeout_connect(Some(TIMEOUT))
+            .timeout_recv_response(Some(TIMEOUT))
+            .build()
+            .new_agent();
+        Self {
+            base: format!("{http}://{location}"),
+            target: format!("{scheme}://{location}"),
+            authorization,
+            agent,
+            made: Mutex::default(),
+        }
+    }
+
+    /// Sends a request with the login, returning the status and the response.
+    fn send(
+        &self,
+        method: &str,
+        url: &str,
+        headers: &[(&str, &str)],
+        body: Option<File>,
+    ) -> Result<(u16, Response<ureq::Body>), AppError> {
+        let method = Method::from_bytes(method.as_bytes()).map_err(upload_error)?;
+        let mut request = Request::builder().method(method).uri(url);
+        if let Some(authorization) = &self.authorization {
+            request = request.header("Authorization", authorization);
+        }
+        for (name, value) in headers {
+            request = request.header(*name, *value);
+        }
+        let response = match body {
+            Some(body) => self.agent.run(request.body(body).map_err(upload_error)?),
+            None => self.agent.run(request.body(()).map_err(upload_error)?),
+        }
+        .map_err(|e| AppError::Upload(format!("{url}: {e}")))?;
+        Ok((response.status().as_u16(), response))
+    }
+
+    /// Makes the folder `dir` under the target, unless this run made it already or the server
+    /// has it. Servers do not make the folders of a put themselves. A folder that could not be
+    /// made fails the put after it.
+    fn make_folder(&self, dir: &str) -> Result<(), AppError> {
+        if self
+            .made
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .contains(dir)
+        {
+            return Ok(());
+        }
+        let url = match dir {
+            "" => format!("{}/", self.base),
+            dir => format!("{}/{}/", self.base, encode_key(dir)),
+        };
+        let status = match self.send("PROPFIND", &url, &[("Depth", "0")], None)? {
+            (404, _) => self.send("MKCOL", &url, &[], None)?.0,
+            (status, _) => status,
+        };
+        // 405 is a folder made by another bench in the meantime
+        if !matches!(status, 200..=299 | 405) {
+            return Err(AppError::Upload(format!(
+                "Could not make {url}, the server returned {status}"
+            )));
+        }
+        self.made
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .insert(dir.to_string());
+        Ok(())
+    }
+}
+
+impl OutputSink for WebDavSink {
+    /// WebDAV has no standard way to continue a partial upload, so a put that is tried again
+    /// after the link dropped sends the whole file again.
+    fn put(&self, file: &Path, key: &str, _resume: bool, condition: PutIf) -> Result<(), AppError> {
+        let made = self.make_folder("").and_then(|()| {
+            key.match_indices('/')
+                .try_for_each(|(i, _)| self.make_folder(&key[..i]))
+        });
+        let url = format!("{}/{}", self.base, encode_key(key));
+        let mut headers = vec![("Content-Type", content_type(file))];
+        match condition {
+            PutIf::Always => {}
+            PutIf::Absent => headers.push(("If-None-Match", "*")),
+            PutIf::Unchanged(etag) => headers.push(("If-Match", etag)),
+        }
+        let error =
+            match made.and_then(|()| self.send("PUT", &url, &headers, Some(File::open(file)?))) {
+                Ok((200..=299, _)) => return Ok(()),
+                Ok((412, _)) => return Err(AppError::AlreadyStored(key.to_string())),
+                Ok((status, _)) => {
+                    AppError::Upload(format!("Could not put {url}, the server returned {status}"))
+                }
+                Err(e) => e,
+            };
+        // The folders are made again when the put is tried again, in case the link dropped
+        // while making them
+        self.made.lock().unwrap_or_else(|e| e.into_inner()).clear();
+        Err(error)
+    }
+
+    fn get(&self, key: &str, to: &Path) -> Result<Option<Version>, AppError> {
+        let url = format!("{}/{}", self.base, encode_key(key));
+        match self.send("GET", &url, &[], None)? {
+            (200..=299, response) => {
+                let etag = response
+                    .headers()
+                    .get("ETag")
+                    .and_then(|etag| etag.to_str().ok())
+                    .map(ToString::to_string);
+                io::copy(
+                    &mut response.into_body().as_reader(),
+                    &mut File::create(to)?,
+                )?;
+                Ok(Some(Version { etag }))
+            }
+            (404, _) => Ok(None),
+            (status, _) => Err(AppError::Upload(format!(
+                "Could not get {url}, the server returned {status}"
+            ))),
+        }
+    }
+}
+
+impl fmt::Display for WebDavSink {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.target)
+    }
+}
+
+fn upload_error(e: impl fmt::Display) -> AppError {
+    AppError::Upload(e.to_string())
+}
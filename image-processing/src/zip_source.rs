@@ -0,0 +1,193 @@
+use std::ffi::OsStr;
+use std::fs::{self, File};
+use std::io;
+use std::path::{Path, PathBuf};
+use std::time::UNIX_EPOCH;
+
+use tracing::{info, warn};
+use zip::ZipArchive;
+
+use crate::captions::CAPTIONS_FILE_NAME;
+use crate::error::AppError;
+use crate::hashing;
+use crate::ignore_file::IGNORE_FILE_NAME;
+use crate::image_ops;
+
+/// Folder in the cache directory the photos of zip sources are extracted into.
+const EXTRACT_DIR: &str = "zip-sources";
+
+pub fn is_zip(path: &Path) -> bool {
+    path.is_file()
+        && path
+            .extension()
+            .and_then(OsStr::to_str)
+            .is_some_and(|ext| ext.eq_ignore_ascii_case("zip"))
+}
+
+/// The folders zip sources were extracted into for a run. They are removed when it is dropped
+/// at the end of the run.
+#[derive(Default)]
+pub struct Extracted {
+    folders: Vec<PathBuf>,
+}
+
+impl Drop for Extracted {
+    fn drop(&mut self) {
+        for folder in &self.folders {
+            if let Err(e) = fs::remove_dir_all(folder) {
+                warn!("{e}. Could not remove the extracted photos in {folder:?}");
+            }
+        }
+    }
+}
+
+/// The sources with every zip, e.g. photos that came by mail, replaced by the folder in the
+/// cache its photos are extracted into. They are processed from there like loose files, until
+/// the returned [`Extracted`] is dropped.
+///
+/// The zips are extracted before the run, also a dry run, and not as the workers reach the
+/// photos: the photos are numbered in the order they were taken, so the date of every photo,
+/// and its size and camera for the filters, are read before the first number is given out.
+pub fn extract_sources(sources: Vec<PathBuf>) -> Result<(Vec<PathBuf>, Extracted), AppError> {
+    let mut extracted = Extracted::default();
+    let sources = sources
+        .into_iter()
+        .map(|source| match is_zip(&source) {
+            true => {
+                let folder = extract(&source)?;
+                extracted.folders.push(folder.clone());
+                Ok(folder)
+            }
+            false => Ok(source),
+        })
+        .collect::<Result<_, AppError>>()?;
+    Ok((sources, extracted))
+}
+
+/// Extracts the photos, captions and ignore files of `zip`, one entry at a time. The folder is
+/// named after the zip, its size and when it was changed, so a run that was stopped before it
+/// could remove the folder only extracts what is missing.
+fn extract(zip: &Path) -> Result<PathBuf, AppError> {
+    let root = directories::ProjectDirs::from("", "", "photo-bench-ui")
+        .ok_or(AppError::NoCacheDir)?
+        .cache_dir()
+        .join(EXTRACT_DIR);
+    extract_into(zip, &root)
+}
+
+/// Extracts `zip` into a folder in `root`, see [`extract`].
+fn extract_into(zip: &Path, root: &Path) -> Result<PathBuf, AppError> {
+    let meta = fs::metadata(zip)?;
+    let changed = meta
+        .modified()?
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_nanos();
+    let zip = zip.canonicalize()?;
+    let key = hashing::sha256_bytes(format!("{zip:?}:{}:{changed}", meta.len()).as_bytes());
+    let stem = zip.file_stem().and_then(OsStr::to_str).unwrap_or("zip");
+    let name = format!("{stem}-{}", hashing::short_id(&key));
+    let folder = root.join(&name);
+
+    info!("Extracting the photos of {zip:?} into {folder:?}");
+    let zip_error = |e| AppError::Zip(zip.clone(), e);
+    let mut archive = ZipArchive::new(File::open(&zip)?).map_err(zip_error)?;
+    let mut extracted = 0;
+    for i in 0..archive.len() {
+        let mut entry = archive.by_index(i).map_err(zip_error)?;
+        // Names that would climb out of the folder are left out
+        let Some(name) = entry.enclosed_name() else {
+            continue;
+        };
+        let file_name = name.file_name().unwrap_or_default();
+        if !entry.is_file()
+            || !(image_ops::is_image_file(&name)
+                || file_name == CAPTIONS_FILE_NAME
+                || file_name == IGNORE_FILE_NAME)
+        {
+            continue;
+        }
+        let path = folder.join(&name);
+        if fs::metadata(&path).is_ok_and(|m| m.len() == entry.size()) {
+            continue;
+        }
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir)?;
+        }
+        // A half extracted file is never taken for a photo
+        let partial = path.with_extension("part");
+        io::copy(&mut entry, &mut File::create(&partial)?)?;
+        fs::rename(&partial, &path)?;
+        extracted += 1;
+    }
+    fs::create_dir_all(&folder)?;
+    info!("Extracted {extracted} file(s) from {zip:?}");
+    Ok(folder)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Write;
+
+    use zip::ZipWriter;
+    use zip::write::SimpleFileOptions;
+
+    use super::*;
+    use crate::test_util::temp_dir;
+
+    fn write_zip(path: &Path, entries: &[(&str, &[u8])]) {
+        let mut zip = ZipWriter::new(File::create(path).unwrap());
+        for (name, data) in entries {
+            zip.start_file(*name, SimpleFileOptions::default()).unwrap();
+            zip.write_all(data).unwrap();
+        }
+        zip.finish().unwrap();
+    }
+
+    #[test]
+    fn extracts_the_photos_but_nothing_outside_the_folder() {
+        let dir = temp_dir("zip-source");
+        let zip = dir.path().join("mail.zip");
+        write_zip(
+            &zip,
+            &[
+                ("day1/a.jpg", b"photo"),
+                ("captions.csv", b"a.jpg,Beach"),
+                ("notes.txt", b"not a photo"),
+                ("../escaped.jpg", b"photo"),
+                ("day1/../../escaped2.jpg", b"photo"),
+            ],
+        );
+        let root = dir.path().join("cache");
+
+        let folder = extract_into(&zip, &root).unwrap();
+        assert!(folder.starts_with(&root));
+        assert_eq!(
+            fs::read(folder.join("day1").join("a.jpg")).unwrap(),
+            b"photo"
+        );
+        assert!(folder.join(CAPTIONS_FILE_NAME).is_file());
+        assert!(!folder.join("notes.txt").exists());
+        assert!(!root.join("escaped.jpg").exists());
+        assert!(!dir.path().join("escaped.jpg").exists());
+        assert!(!dir.path().join("escaped2.jpg").exists());
+        // Extracting again finds the same folder
+        assert_eq!(extract_into(&zip, &root).unwrap(), folder);
+    }
+
+    #[test]
+    fn the_extracted_folders_are_removed_on_drop() {
+        let dir = temp_dir("zip-source");
+        let zip = dir.path().join("mail.zip");
+        write_zip(&zip, &[("a.jpg", b"photo")]);
+        let folder = extract_into(&zip, &dir.path().join("cache")).unwrap();
+        let extracted = Extracted {
+            folders: vec![folder.clone()],
+        };
+        assert!(folder.join("a.jpg").is_file());
+
+        drop(extracted);
+        assert!(!folder.exists());
+        assert!(zip.is_file());
+    }
+}
@@ -18,6 +18,9 @@ pub fn run() {
     tauri::Builder::default()
         .setup(|app| {
             app.manage(AppState {});
+            app.manage(photobench::RotationOverrides::default());
+            app.manage(photobench::SelectedPreset::default());
+            app.manage(photo_date_bench::previews::PreviewDir::create()?);
             tracing::init_tracing(app.app_handle().clone());
             Ok(())
         })
@@ -26,10 +29,26 @@ pub fn run() {
         .invoke_handler(tauri::generate_handler![
             greet,
             open_download_folder,
-            photobench::process_images
+            photobench::check_previous_run,
+            photobench::check_budget,
+            photobench::target_folders,
+            photobench::thumbnail,
+            photobench::set_rotation_overrides,
+            photobench::date_sources,
+            photobench::list_presets,
+            photobench::apply_preset,
+            photobench::process_pasted_image,
+            photobench::process_images,
+            photobench::clear_previews
         ])
-        .run(tauri::generate_context!())
-        .expect("error while running tauri application");
+        .build(tauri::generate_context!())
+        .expect("error while running tauri application")
+        .run(|app, event| {
+            // Managed state is not dropped when the app exits
+            if let tauri::RunEvent::Exit = event {
+                app.state::<photo_date_bench::previews::PreviewDir>().remove();
+            }
+        });
 }
 
 #[tauri::command]
@@ -1,15 +1,210 @@
 use tauri::Emitter;
 use photo_date_bench::App;
-use tauri::AppHandle;
-use std::path::PathBuf;
+use photo_date_bench::adjust::{Rotation, RotationOverride};
+use photo_date_bench::config::Config;
+use photo_date_bench::date_source::DateSource;
+use photo_date_bench::error::AppError;
+use photo_date_bench::presets::{self, StampPreset};
+use photo_date_bench::previews::PreviewDir;
+use photo_date_bench::progress::ProgressEvent;
+use photo_date_bench::stats::TargetFolder;
+use tauri::{AppHandle, State};
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
 use tracing::error;
 
+/// The warning to show before the run when the source was processed into the target before.
+#[tauri::command]
+pub async fn check_previous_run(
+    selected_preset: State<'_, SelectedPreset>,
+    source_folders: Vec<PathBuf>,
+    target_folder: PathBuf,
+    date_sources: Option<Vec<DateSource>>,
+    proof: Option<bool>,
+    proof_text: Option<String>,
+) -> Result<Option<String>, String> {
+    let app = run_app(&selected_preset, source_folders, target_folder, date_sources, proof, proof_text);
+    tauri::async_runtime::spawn_blocking(move || app.previous_run())
+    .await
+    .map_err(|e| e.to_string())?
+    .map(|previous| previous.map(|p| p.to_string()))
+    .map_err(|e| e.to_string())
+}
+
+/// The folders of the target with their number of photos and highest number, to show what the
+/// archive holds before a run adds to it. Read with the config of a run, so the names of the
+/// preset are counted.
+#[tauri::command]
+pub async fn target_folders(
+    selected_preset: State<'_, SelectedPreset>,
+    target_folder: PathBuf,
+) -> Result<Vec<TargetFolder>, String> {
+    let app = run_app(&selected_preset, vec![], target_folder, None, None, None);
+    tauri::async_runtime::spawn_blocking(move || {
+        let config = app.run_config()?;
+        photo_date_bench::stats::target_folders(&app.target, &config)
+    })
+    .await
+    .map_err(|e| e.to_string())?
+    .map_err(|e| e.to_string())
+}
+
+/// The options of a run from the app, also used for the checks before it so they see the same
+/// config. The app confirms the run itself, so it runs with `yes`.
+fn run_app(
+    selected_preset: &State<'_, SelectedPreset>,
+    source_folders: Vec<PathBuf>,
+    target_folder: PathBuf,
+    date_sources: Option<Vec<DateSource>>,
+    proof: Option<bool>,
+    proof_text: Option<String>,
+) -> App {
+    App {
+        sources: source_folders,
+        target: target_folder,
+        date_sources: date_sources.unwrap_or_default(),
+        preset: selected_preset.0.lock().unwrap_or_else(|e| e.into_inner()).clone(),
+        proof: proof.unwrap_or_default().then_some(proof_text),
+        yes: true,
+        ..Default::default()
+    }
+}
+
+/// The estimate of the run and the limits of the budget it is over, to confirm before the run,
+/// or `None` when it is within the budget.
+#[tauri::command]
+pub async fn check_budget(
+    selected_preset: State<'_, SelectedPreset>,
+    source_folders: Vec<PathBuf>,
+    target_folder: PathBuf,
+    date_sources: Option<Vec<DateSource>>,
+    proof: Option<bool>,
+    proof_text: Option<String>,
+) -> Result<Option<String>, String> {
+    let app = run_app(&selected_preset, source_folders, target_folder, date_sources, proof, proof_text);
+    tauri::async_runtime::spawn_blocking(move || {
+        let config = app.run_config()?;
+        let estimate = photo_date_bench::budget::estimate_run(
+            &app.sources,
+            &app.target,
+            &config,
+            std::thread::available_parallelism().map_or(1, |n| n.get()),
+        )?;
+        let over = estimate.over_budget(&config.budget);
+        Ok::<_, AppError>((!over.is_empty()).then(|| format!("{estimate}. This is {}", over.join(", "))))
+    })
+    .await
+    .map_err(|e| e.to_string())?
+    .map_err(|e| e.to_string())
+}
+
+/// The thumbnail of a photo that fits in `max_px` by `max_px`, from the thumbnail cache and
+/// linked into the previews of the session.
+#[tauri::command]
+pub async fn thumbnail(previews: State<'_, PreviewDir>, path: PathBuf, max_px: u32) -> Result<PathBuf, String> {
+    let dir = previews.path().to_path_buf();
+    tauri::async_runtime::spawn_blocking(move || {
+        photo_date_bench::thumbnails::thumbnail_file(&path, max_px, max_px, &dir)
+    })
+    .await
+    .map_err(|e| e.to_string())?
+    .map_err(|e| e.to_string())
+}
+
+/// Processes an image pasted from the clipboard or dragged from a browser into the target with
+/// the next number, e.g. a one-off screenshot. Returns the output photo.
+#[tauri::command]
+pub async fn process_pasted_image(
+    overrides: State<'_, RotationOverrides>,
+    selected_preset: State<'_, SelectedPreset>,
+    bytes: Vec<u8>,
+    target_folder: PathBuf,
+    date_sources: Option<Vec<DateSource>>,
+    proof: Option<bool>,
+    proof_text: Option<String>,
+) -> Result<PathBuf, String> {
+    // The same options as a run, the rotations are kept for the next one
+    let rotate = overrides.0.lock().unwrap_or_else(|e| e.into_inner()).iter()
+        .map(|(path, degrees)| RotationOverride { path: path.clone(), degrees: *degrees })
+        .collect();
+    let app = App { rotate, ..run_app(&selected_preset, vec![], target_folder, date_sources, proof, proof_text) };
+    tauri::async_runtime::spawn_blocking(move || {
+        photo_date_bench::paste::process_bytes(&bytes, app, None)
+    })
+    .await
+    .map_err(|e| e.to_string())?
+    .map_err(|e| e.to_string())
+}
+
+/// Removes the preview renders and thumbnails of this session, e.g. when the sources change.
+#[tauri::command]
+pub fn clear_previews(previews: State<'_, PreviewDir>) -> Result<(), String> {
+    previews.clear().map_err(|e| e.to_string())
+}
+
+/// The order the dates of the photos are taken from in the config, for the settings panel.
+#[tauri::command]
+pub fn date_sources() -> Result<Vec<DateSource>, String> {
+    Ok(Config::load(None).map_err(|e| e.to_string())?.date_sources)
+}
+
+/// The rotations fixed in the preview, used by the next run.
+#[derive(Default)]
+pub struct RotationOverrides(Mutex<HashMap<PathBuf, i32>>);
+
+/// Sets the clockwise rotation in degrees of sideways originals for the next run, replacing
+/// the ones set before. The originals are not changed.
+#[tauri::command]
+pub fn set_rotation_overrides(
+    overrides: State<'_, RotationOverrides>,
+    rotations: HashMap<PathBuf, i32>,
+) -> Result<(), String> {
+    for degrees in rotations.values() {
+        Rotation::from_degrees(*degrees)?;
+    }
+    *overrides.0.lock().unwrap_or_else(|e| e.into_inner()) = rotations;
+    Ok(())
+}
+
+/// The stamp preset chosen in the settings, used by every run until another is chosen.
+#[derive(Default)]
+pub struct SelectedPreset(Mutex<Option<String>>);
+
+/// The stamp presets shipped with the program, for the settings panel.
+#[tauri::command]
+pub fn list_presets() -> Vec<StampPreset> {
+    presets::presets()
+}
+
+/// Stamps the next runs with the preset called `name`, or as the config says when it is not
+/// given.
+#[tauri::command]
+pub fn apply_preset(
+    selected: State<'_, SelectedPreset>,
+    name: Option<String>,
+) -> Result<Option<StampPreset>, String> {
+    let preset = name.as_deref().map(presets::find).transpose().map_err(|e| e.to_string())?;
+    *selected.0.lock().unwrap_or_else(|e| e.into_inner()) = name;
+    Ok(preset)
+}
+
 #[tauri::command]
 pub async fn process_images(
     app: AppHandle,
-    source_folder: PathBuf,
+    overrides: State<'_, RotationOverrides>,
+    selected_preset: State<'_, SelectedPreset>,
+    source_folders: Vec<PathBuf>,
     target_folder: PathBuf,
+    date_sources: Option<Vec<DateSource>>,
+    dry_run: Option<bool>,
+    proof: Option<bool>,
+    proof_text: Option<String>,
 ) -> Result<(), ()> {
+    let rotate: Vec<RotationOverride> = std::mem::take(&mut *overrides.0.lock().unwrap_or_else(|e| e.into_inner()))
+        .into_iter()
+        .map(|(path, degrees)| RotationOverride { path, degrees })
+        .collect();
 
     let send_event = move |event: &str, payload: String| {
         println!("{event}: {payload}");
@@ -17,9 +212,59 @@ pub async fn process_images(
             error!("{e}, while emitting event {event}");
         }
     };
+    let file_name = |file: &Path| {
+        file.file_name()
+            .and_then(|x| x.to_str())
+            .unwrap_or_default()
+            .to_string()
+    };
+    // Translate the progress of the run to the events the UI listens to
+    let progress = move |event: &ProgressEvent| {
+        match event {
+            ProgressEvent::Scanned { total } => send_event("process-file-total", total.to_string()),
+            ProgressEvent::Skipped { file, reason } => send_event(
+                "process-file-skipped",
+                serde_json::json!({ "file": file.display().to_string(), "reason": reason.to_string() }).to_string(),
+            ),
+            ProgressEvent::Started { file } => send_event("process-file", file_name(file)),
+            ProgressEvent::Stage { file, stage } => send_event(
+                "process-file-stage",
+                serde_json::json!({ "file": file_name(file), "stage": stage }).to_string(),
+            ),
+            ProgressEvent::Finished { file, .. } | ProgressEvent::Failed { file, .. } => {
+                if let ProgressEvent::Finished { output, exif, .. } = event {
+                    // The results table shows the EXIF data without reading the originals again
+                    let camera = exif.and_then(|exif| exif.camera_name());
+                    send_event(
+                        "process-file-finished",
+                        serde_json::json!({ "file": file_name(file), "output": output.display().to_string(), "camera": camera, "exif": exif }).to_string(),
+                    );
+                }
+                if let Some(pct) = event.percent() {
+                    send_event("process-progress", pct.to_string());
+                }
+                send_event("process-file-done", file_name(file));
+            }
+            ProgressEvent::Planned { file, output, date, number } => send_event(
+                "process-file-planned",
+                serde_json::json!({ "file": file_name(file), "output": output.display().to_string(), "date": date.to_string(), "number": number }).to_string(),
+            ),
+            ProgressEvent::Warning { file, warnings } => send_event(
+                "process-file-warning",
+                serde_json::json!({ "file": file_name(file), "warnings": warnings }).to_string(),
+            ),
+            ProgressEvent::Done { .. } => send_event("process-complete", "".to_string()),
+            ProgressEvent::Summary { .. } => {}
+        }
+    };
 
+    let app = App {
+        rotate,
+        dry_run: dry_run.unwrap_or_default(),
+        ..run_app(&selected_preset, source_folders, target_folder, date_sources, proof, proof_text)
+    };
      tauri::async_runtime::spawn_blocking(|| {
-        if let Err(e) = photo_date_bench::run_image_processing(App {source: source_folder, target: target_folder, threads: None}, send_event) {
+        if let Err(e) = photo_date_bench::run_image_processing(app, Some(Arc::new(progress))) {
             error!("{e}");
         }
      }).await.unwrap();
@@ -1,7 +1,9 @@
+use photo_date_bench::config::Config;
 use serde::Serialize;
 use tauri::{AppHandle, Emitter};
 use tracing::{info, Event, Level, Subscriber};
 use tracing_subscriber::prelude::__tracing_subscriber_SubscriberExt;
+use tracing_subscriber::filter::LevelFilter;
 use tracing_subscriber::util::SubscriberInitExt;
 use tracing_subscriber::Layer;
 
@@ -67,9 +69,23 @@ impl tracing::field::Visit for JsonVisitor {
 pub fn init_tracing(app_handle: AppHandle) {
     let tauri_layer = TauriLogLayer { app_handle };
 
+    // The log file of the [log] section of the config, for support to see what happened in a run
+    let log_file = Config::load(None).map(|c| c.log).unwrap_or_default();
+    let file_layer = match log_file.writer("photo-bench-ui") {
+        Ok(writer) => writer.map(|writer| {
+            tracing_subscriber::fmt::layer()
+                .with_writer(writer)
+                .with_ansi(false)
+                .with_filter(LevelFilter::INFO)
+        }),
+        Err(e) => {
+            eprintln!("Could not open the log file: {e}");
+            None
+        }
+    };
+
     tracing_subscriber::registry()
         .with(tauri_layer)
-        // Add other layers if desired (e.g., console output)
-        // .with(tracing_subscriber::fmt::Layer::default())
+        .with(file_layer)
         .init();
 }
@@ -8,7 +8,10 @@ import {
   CircularProgress,
   LinearProgress,
   Tooltip,
-  IconButton
+  IconButton,
+  Checkbox,
+  FormControlLabel,
+  MenuItem
 } from '@mui/material';
 import { createTheme, ThemeProvider } from '@mui/material/styles';
 import { listen } from '@tauri-apps/api/event';
@@ -16,7 +19,22 @@ import { invoke } from "@tauri-apps/api/core";
 import { open } from '@tauri-apps/plugin-dialog';
 import CssBaseline from '@mui/material/CssBaseline';
 import FolderIcon from '@mui/icons-material/Folder';
+import ImageIcon from '@mui/icons-material/Image';
+import AddIcon from '@mui/icons-material/Add';
+import CloseIcon from '@mui/icons-material/Close';
 import GoogleIcon from '@mui/icons-material/Google';
+import ArrowUpwardIcon from '@mui/icons-material/ArrowUpward';
+import ArrowDownwardIcon from '@mui/icons-material/ArrowDownward';
+
+// Where the dates of the photos can be taken from, by their names in the config
+const DATE_SOURCE_LABELS = {
+  'exif-original': 'When the photo was taken (EXIF)',
+  'exif-digitized': 'When the photo was stored or scanned (EXIF)',
+  'xmp': 'The date of edited photos and screenshots (XMP)',
+  'file-name': 'A date in the file name',
+  'modified': 'When the file was last changed',
+  'ask': 'Ask me: skip and list the photo',
+};
 
 const darkTheme = createTheme({
   palette: {
@@ -27,6 +45,8 @@ const darkTheme = createTheme({
 
 function App() {
   const [sourceFolder, setSourceFolder] = useState('');
+  // More folders numbered in the same run, e.g. a camera card and a phone export
+  const [extraSources, setExtraSources] = useState([]);
   const [targetFolder, setTargetFolder] = useState('');
   const [progress, setProgress] = useState(0);
   const [isProcessing, setIsProcessing] = useState(false);
@@ -34,6 +54,85 @@ function App() {
   const [files, setFiles] = useState([]);
   const [fileCount, setFileCount] = useState(0);
   const [logs, setLogs] = useState("");
+  const [warnings, setWarnings] = useState([]);
+  const [stages, setStages] = useState({});
+  const [skipped, setSkipped] = useState([]);
+  // The photos written, with the EXIF data of their originals
+  const [results, setResults] = useState([]);
+  // Whether the last run was a dry run, and where it would have written the photos
+  const [dryRun, setDryRun] = useState(false);
+  const [planned, setPlanned] = useState([]);
+  // The last image pasted or dropped into the window, and where it was saved
+  const [pasted, setPasted] = useState("");
+  // The date sources in the order they are tried, the ones not used unchecked at the end
+  const [dateSources, setDateSources] = useState([]);
+  // The folders already in the target, with their number of photos and highest number
+  const [targetFolders, setTargetFolders] = useState([]);
+  // The stamp presets shipped with the program and the one chosen, empty to stamp as the config says
+  const [presets, setPresets] = useState([]);
+  const [preset, setPreset] = useState('');
+  // Stamp a large text across the photos of a draft run, empty for the text of the config
+  const [proof, setProof] = useState(false);
+  const [proofText, setProofText] = useState('');
+
+  useEffect(() => {
+    if (!targetFolder) {
+      setTargetFolders([]);
+      return;
+    }
+    invoke('target_folders', { targetFolder })
+      .then(setTargetFolders)
+      .catch((error) => {
+        console.error('Failed to read the target folder:', error);
+        setTargetFolders([]);
+      });
+  }, [targetFolder, isDone, preset]);
+
+  // The thumbnails and preview renders of the last source are not needed anymore
+  useEffect(() => {
+    invoke('clear_previews')
+      .catch((error) => console.error('Failed to clear the previews:', error));
+  }, [sourceFolder]);
+
+  useEffect(() => {
+    invoke('date_sources')
+      .then((used) => setDateSources([
+        ...used.map((source) => ({ source, enabled: true })),
+        ...Object.keys(DATE_SOURCE_LABELS)
+          .filter((source) => !used.includes(source))
+          .map((source) => ({ source, enabled: false })),
+      ]))
+      .catch((error) => console.error('Failed to read the date sources:', error));
+  }, []);
+
+  useEffect(() => {
+    invoke('list_presets')
+      .then(setPresets)
+      .catch((error) => console.error('Failed to read the presets:', error));
+  }, []);
+
+  const handlePresetChange = async (name) => {
+    try {
+      await invoke('apply_preset', { name: name || null });
+      setPreset(name);
+    } catch (error) {
+      console.error('Failed to apply the preset:', error);
+      alert(`Could not use the preset: ${error}`);
+    }
+  };
+
+  const moveDateSource = (index, by) => {
+    setDateSources((prev) => {
+      const next = [...prev];
+      const [moved] = next.splice(index, 1);
+      next.splice(Math.max(0, Math.min(next.length, index + by)), 0, moved);
+      return next;
+    });
+  };
+
+  const toggleDateSource = (index) => {
+    setDateSources((prev) => prev.map((x, i) => i === index ? { ...x, enabled: !x.enabled } : x));
+  };
 
 
   useEffect(() => {
@@ -56,6 +155,34 @@ function App() {
       setFiles((prev) => prev.filter(x=>x!=file))
     });
 
+    // The step a file is on, so big photos do not look stuck
+    const unlistenFileStage = listen('process-file-stage', (event) => {
+      const { file, stage } = JSON.parse(event.payload);
+      setStages((prev) => ({ ...prev, [file]: stage }));
+    });
+
+    // Photos that look blurry or badly exposed
+    const unlistenFileWarning = listen('process-file-warning', (event) => {
+      const warning = JSON.parse(event.payload);
+      setWarnings((prev) => [...prev, warning]);
+    });
+
+    // Files in the source that are not processed, with the reason why
+    const unlistenFileSkipped = listen('process-file-skipped', (event) => {
+      const skip = JSON.parse(event.payload);
+      setSkipped((prev) => [...prev, skip]);
+    });
+
+    const unlistenFileFinished = listen('process-file-finished', (event) => {
+      const result = JSON.parse(event.payload);
+      setResults((prev) => [...prev, result]);
+    });
+
+    const unlistenFilePlanned = listen('process-file-planned', (event) => {
+      const plan = JSON.parse(event.payload);
+      setPlanned((prev) => [...prev, plan]);
+    });
+
     const unlistenFileTotal = listen('process-file-total', (event) => {
       const fileCount = event.payload;
       setFileCount(parseInt(fileCount));
@@ -82,6 +209,11 @@ function App() {
       unlistenProgress();
       unlistenFile();
       unlistenFileDone();
+      unlistenFileStage();
+      unlistenFileWarning();
+      unlistenFileSkipped();
+      unlistenFileFinished();
+      unlistenFilePlanned();
       unlistenFileTotal();
       unlistenComplete();
       unlistenLogOutput();
@@ -92,6 +224,11 @@ function App() {
       unlistenProgress,
       unlistenFile,
       unlistenFileDone,
+      unlistenFileStage,
+      unlistenFileWarning,
+      unlistenFileSkipped,
+      unlistenFileFinished,
+      unlistenFilePlanned,
       unlistenFileTotal,
       unlistenComplete,
       unlistenLogOutput,
@@ -106,6 +243,58 @@ function App() {
     
 
 
+  // An image pasted from the clipboard or dragged from a browser is saved into the target
+  // with the next number straight away
+  useEffect(() => {
+    const processImage = async (file) => {
+      if (!targetFolder) {
+        alert('Please choose the target folder first.');
+        return;
+      }
+      try {
+        const bytes = Array.from(new Uint8Array(await file.arrayBuffer()));
+        const used = dateSources.filter((x) => x.enabled).map((x) => x.source);
+        const output = await invoke('process_pasted_image', {
+          bytes,
+          targetFolder,
+          dateSources: used.length > 0 ? used : null,
+          proof,
+          proofText: proofText.trim() || null,
+        });
+        setPasted(`Saved the pasted image as ${output}`);
+      } catch (error) {
+        setPasted(`${error}`);
+      }
+    };
+    const imageOf = (items) => Array.from(items || [])
+      .find((item) => item.kind === 'file' && item.type.startsWith('image/'))
+      ?.getAsFile();
+    const onPaste = (event) => {
+      // Text pasted into the fields is left alone
+      const image = imageOf(event.clipboardData?.items);
+      if (image) {
+        event.preventDefault();
+        processImage(image);
+      }
+    };
+    const onDragOver = (event) => event.preventDefault();
+    const onDrop = (event) => {
+      const image = imageOf(event.dataTransfer?.items);
+      if (image) {
+        event.preventDefault();
+        processImage(image);
+      }
+    };
+    window.addEventListener('paste', onPaste);
+    window.addEventListener('dragover', onDragOver);
+    window.addEventListener('drop', onDrop);
+    return () => {
+      window.removeEventListener('paste', onPaste);
+      window.removeEventListener('dragover', onDragOver);
+      window.removeEventListener('drop', onDrop);
+    };
+  }, [targetFolder, dateSources, proof, proofText]);
+
   const handleSelectFolder = async (folderSetFn) => {
     try {
       const selected = await open({
@@ -122,24 +311,75 @@ function App() {
     }
   };
 
+  // A single photo can be processed on its own instead of a whole folder
+  const handleSelectImage = async () => {
+    try {
+      const selected = await open({
+        multiple: false,
+        filters: [{ name: 'Images', extensions: ['jpg', 'jpeg', 'png'] }],
+      });
+      if (typeof selected === 'string') {
+        setSourceFolder(selected);
+      }
+    } catch (error) {
+      console.error('Failed to select image:', error);
+    }
+  };
+
   const openFolder = async () => {
     await invoke('open_download_folder', { targetFolder });
   }
 
-  const handleStartProcess = async () => {
+  // A dry run shows where every photo would go without writing anything, so it is not confirmed
+  const handleStartProcess = async (dryRun = false) => {
     if (!(sourceFolder && targetFolder)) {
       alert('Please fill in all fields.');
       return;
     }
+    const sourceFolders = [sourceFolder, ...extraSources];
+    const used = dateSources.filter((x) => x.enabled).map((x) => x.source);
+    // The checks before the run get the same options as the run
+    const runOptions = {
+      sourceFolders,
+      targetFolder,
+      dateSources: used.length > 0 ? used : null,
+      proof,
+      proofText: proofText.trim() || null,
+    };
+
+    if (!dryRun) try {
+      const previousRun = await invoke('check_previous_run', runOptions);
+      if (previousRun && !confirm(`${previousRun}. Continue?`)) {
+        return;
+      }
+    } catch (error) {
+      console.error('Failed to check for a previous run:', error);
+    }
+
+    // The run is only processed with yes once it was confirmed here
+    if (!dryRun) try {
+      const overBudget = await invoke('check_budget', runOptions);
+      if (overBudget && !confirm(`${overBudget}. Continue?`)) {
+        return;
+      }
+    } catch (error) {
+      console.error('Failed to estimate the run:', error);
+    }
 
     setIsDone(false);
     setIsProcessing(true);
     setProgress(0);
     setFileCount(0);
     setFiles([])
+    setStages({})
+    setSkipped([])
+    setWarnings([])
+    setResults([])
+    setPlanned([])
+    setDryRun(dryRun)
 
     try {
-      await invoke('process_images', { sourceFolder, targetFolder }); // Replace with your Tauri command
+      await invoke('process_images', { ...runOptions, dryRun });
     } catch (error) {
       console.error('Process failed:', error);
       setIsProcessing(false);
@@ -155,7 +395,7 @@ function App() {
         </Typography>
         <Box component="form" noValidate autoComplete="on">
           <Box display="flex" alignItems="center" marginY={2}>
-            <Tooltip title="Folder where the images to be processed are." placement="top-start">
+            <Tooltip title="Folder where the images to be processed are, or a single image." placement="top-start">
               <TextField
                 label="Source Folder"
                 variant="outlined"
@@ -173,7 +413,34 @@ function App() {
               >
                 <FolderIcon/>
             </IconButton>
+            <IconButton
+                variant="contained"
+                color="primary"
+                onClick={handleSelectImage}
+              >
+                <ImageIcon/>
+            </IconButton>
+            <Tooltip title="Add another source folder, numbered in the same run." placement="top-start">
+              <IconButton
+                  variant="contained"
+                  color="primary"
+                  onClick={() => handleSelectFolder((folder) => setExtraSources((prev) => [...prev, folder]))}
+                >
+                  <AddIcon/>
+              </IconButton>
+            </Tooltip>
           </Box>
+          {extraSources.map((folder) => (
+            <Box display="flex" alignItems="center" key={folder}>
+              <Typography variant="body2">{folder}</Typography>
+              <IconButton
+                  size="small"
+                  onClick={() => setExtraSources((prev) => prev.filter((x) => x != folder))}
+                >
+                  <CloseIcon fontSize="small"/>
+              </IconButton>
+            </Box>
+          ))}
           <Box display="flex" alignItems="center" marginY={2}>
             <Tooltip title="Folder where the processed images should go to." placement="top-start">
               <TextField
@@ -194,11 +461,85 @@ function App() {
                 <FolderIcon/>
             </IconButton>
           </Box>
-          
+          {targetFolders.length > 0 && (
+            <Box component="details" marginBottom={2}>
+              <Typography component="summary" variant="body2">
+                The target has {targetFolders.reduce((sum, f) => sum + f.photos, 0)} photo(s)
+                {' '}in {targetFolders.length} folder(s), up to number
+                {' '}{Math.max(...targetFolders.map((f) => f.highest_number))}
+              </Typography>
+              {targetFolders.map((f) => (
+                <Typography variant="body2" key={f.folder}>
+                  {f.folder}: {f.photos} photo(s), up to {f.highest_number}
+                </Typography>
+              ))}
+            </Box>
+          )}
+
+          {presets.length > 0 && (
+            <TextField
+              select
+              label="Stamp preset"
+              value={preset}
+              onChange={(e) => handlePresetChange(e.target.value)}
+              fullWidth
+              margin="normal"
+              helperText={presets.find((p) => p.name === preset)?.description ?? 'The fonts, sizes and colors of the config'}
+            >
+              <MenuItem value="">As in the config</MenuItem>
+              {presets.map((p) => (
+                <MenuItem value={p.name} key={p.name}>{p.name}</MenuItem>
+              ))}
+            </TextField>
+          )}
+
+          <Box display="flex" alignItems="center" gap={2}>
+            <FormControlLabel
+              control={<Checkbox checked={proof} onChange={(e) => setProof(e.target.checked)}/>}
+              label="Proof"
+            />
+            {proof && (
+              <TextField
+                label="Proof text"
+                value={proofText}
+                onChange={(e) => setProofText(e.target.value)}
+                placeholder="As in the config"
+                size="small"
+                helperText="Written across the photos, so draft prints are not mistaken for the final ones"
+              />
+            )}
+          </Box>
+
+          {dateSources.length > 0 && (
+            <Box component="details" marginY={2}>
+              <Typography component="summary" variant="body1">
+                Where the dates come from
+              </Typography>
+              <Typography variant="body2" marginY={1}>
+                The dates are taken from the first of these the photo has, from the top.
+              </Typography>
+              {dateSources.map(({ source, enabled }, index) => (
+                <Box display="flex" alignItems="center" key={source}>
+                  <FormControlLabel
+                    sx={{ flexGrow: 1 }}
+                    control={<Checkbox checked={enabled} onChange={() => toggleDateSource(index)}/>}
+                    label={DATE_SOURCE_LABELS[source] ?? source}
+                  />
+                  <IconButton size="small" disabled={index === 0} onClick={() => moveDateSource(index, -1)}>
+                    <ArrowUpwardIcon fontSize="small"/>
+                  </IconButton>
+                  <IconButton size="small" disabled={index === dateSources.length - 1} onClick={() => moveDateSource(index, 1)}>
+                    <ArrowDownwardIcon fontSize="small"/>
+                  </IconButton>
+                </Box>
+              ))}
+            </Box>
+          )}
+
           <Button
             variant="contained"
             color="primary"
-            onClick={handleStartProcess}
+            onClick={() => handleStartProcess(false)}
             disabled={isProcessing}
           >
             {isProcessing ? (
@@ -206,7 +547,23 @@ function App() {
             ) : (
               isDone ? 'Run Again' : 'Run'
             )}
-          </Button>          
+          </Button>
+          <Tooltip title="Show the date, folder and number every photo would get, without writing anything.">
+            <Button
+              variant="outlined"
+              color="primary"
+              onClick={() => handleStartProcess(true)}
+              disabled={isProcessing}
+              sx={{ ml: 1 }}
+            >
+              Dry run
+            </Button>
+          </Tooltip>          
+          {pasted && (
+            <Typography variant="body2" marginTop={1}>
+              {pasted}
+            </Typography>
+          )}
           {isProcessing && (
             <Box marginTop={4}>
               <LinearProgress variant="determinate" value={progress} />
@@ -219,16 +576,96 @@ function App() {
                 <Typography variant="caption">100%</Typography>
               </Box>
               <Typography variant="body1" gutterBottom>
-                { fileCount == 0 ? 'Setting up...' : `Processing (${fileCount}) files: ${files.join(',')}`}
+                { fileCount == 0 ? 'Setting up...' : `Processing (${fileCount}) files:`}
               </Typography>
+              {files.map((file) => (
+                <Box display="flex" alignItems="center" key={file}>
+                  <CircularProgress size={14} sx={{ mr: 1 }} />
+                  <Typography variant="body2">
+                    {file}{stages[file] ? ` (${stages[file]})` : ''}
+                  </Typography>
+                </Box>
+              ))}
             </Box>
           )}
           {
-            isDone && (
+            isDone && dryRun && (
+              <Box>
+                <Typography variant="body1" gutterBottom marginTop={1}>
+                  Dry run. {planned.length} photo(s) would be written, nothing was written yet
+                </Typography>
+                {planned.map((p) => (
+                  <Typography variant="body2" key={p.output}>
+                    {p.file} ({p.date.slice(0, 10)}) → {p.output}
+                  </Typography>
+                ))}
+                {skipped.length > 0 && (
+                  <Typography variant="body2" marginTop={1}>
+                    {skipped.length} file(s) in the source would be skipped
+                  </Typography>
+                )}
+              </Box>
+            )
+          }
+          {
+            isDone && !dryRun && (
               <Box>
                 <Typography variant="body1" gutterBottom marginTop={1}>
                   Done. {fileCount} files processed
                 </Typography>
+                {warnings.length > 0 && (
+                  <Box marginY={1}>
+                    <Typography variant="body1" color="warning.main" gutterBottom>
+                      {warnings.length} photo(s) might have to be retaken:
+                    </Typography>
+                    {warnings.map((w) => (
+                      <Typography variant="body2" key={w.file}>
+                        {w.file}: {w.warnings.map((x) => x.kind).join(', ')}
+                      </Typography>
+                    ))}
+                  </Box>
+                )}
+                {skipped.length > 0 && (
+                  <Box component="details" marginY={1}>
+                    <Typography component="summary" variant="body1" gutterBottom>
+                      {skipped.length} file(s) in the source were skipped
+                    </Typography>
+                    {skipped.map((s) => (
+                      <Typography variant="body2" key={s.file}>
+                        {s.file}: {s.reason}
+                      </Typography>
+                    ))}
+                  </Box>
+                )}
+                {results.length > 0 && (
+                  <Box component="details" marginY={1}>
+                    <Typography component="summary" variant="body1" gutterBottom>
+                      {results.length} photo(s) written
+                    </Typography>
+                    <Box component="table" sx={{ width: '100%', fontSize: '0.8rem' }}>
+                      <thead>
+                        <tr>
+                          <th align="left">Photo</th>
+                          <th align="left">Camera</th>
+                          <th align="left">Taken</th>
+                          <th align="left">Size</th>
+                          <th align="left">Orientation</th>
+                        </tr>
+                      </thead>
+                      <tbody>
+                        {results.map(({ file, output, camera, exif }) => (
+                          <tr key={output} title={output}>
+                            <td>{file}</td>
+                            <td>{camera ?? ''}</td>
+                            <td>{exif?.date_time_original ?? ''}</td>
+                            <td>{exif?.width && exif?.height ? `${exif.width} × ${exif.height}` : ''}</td>
+                            <td>{exif?.orientation ?? ''}</td>
+                          </tr>
+                        ))}
+                      </tbody>
+                    </Box>
+                  </Box>
+                )}
                 <Button
                   variant="contained"
                   color="primary"